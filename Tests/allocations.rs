@@ -0,0 +1,85 @@
+// Counts heap allocations while programs run, to check that a loop which
+// only does arithmetic doesn't allocate per iteration on either engine.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use touch_grass::{bytecode, lexer, parser, vm, Engine};
+
+struct Counting;
+
+thread_local! {
+    // Per thread, so tests running in parallel don't count each other.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations(run: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    run();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Declares, assigns, branches and loops: everything that used to allocate
+/// on every pass.
+fn program(iterations: i64) -> String {
+    format!(
+        "touch grass number i as 0
+touch grass number total as 0
+while i < {} then
+    set i to i + 1
+    touch grass number step as i - 1
+    go outside if step > 5 then
+        set total to total + step
+    fr fr instead
+        set total to total - 1
+    fr fr
+fr fr
+",
+        iterations
+    )
+}
+
+fn parse(source: &str) -> touch_grass::ast::Node {
+    let mut parser = parser::Parser::with_lines(lexer::tokenize_with_lines(source));
+    let ast = parser.parse();
+    assert!(parser.errors().is_empty());
+    ast
+}
+
+#[test]
+fn interpreter_loops_dont_allocate() {
+    let count = |iterations| {
+        let ast = parse(&program(iterations));
+        let mut engine = Engine::new();
+        allocations(|| engine.run_program(&ast).unwrap())
+    };
+    assert_eq!(count(10), count(1000));
+}
+
+#[test]
+fn vm_loops_dont_allocate() {
+    let count = |iterations| {
+        let chunk = bytecode::compile(&parse(&program(iterations)));
+        let mut vm = vm::Vm::new(&chunk);
+        allocations(|| vm.run(&chunk).unwrap())
+    };
+    assert_eq!(count(10), count(1000));
+}
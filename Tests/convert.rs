@@ -0,0 +1,54 @@
+// Conversions between Rust types and Value: nested containers survive the
+// round trip, and a value of the wrong shape is an error naming both types.
+
+use std::collections::HashMap;
+use touch_grass::interpreter::{RuntimeError, Value};
+
+#[test]
+fn nested_round_trip() {
+    let mut scores = HashMap::new();
+    scores.insert("grass".to_string(), vec![vec![1i64, 2], vec![]]);
+    scores.insert("sun".to_string(), vec![vec![3]]);
+
+    let value = Value::from(scores.clone());
+    let sun = Value::List(vec![Value::List(vec![Value::Number(3)])]);
+    match &value {
+        Value::Map(entries) => assert_eq!(entries.get("sun"), Some(&sun)),
+        other => panic!("expected a map, got {:?}", other),
+    }
+    assert_eq!(HashMap::<String, Vec<Vec<i64>>>::try_from(value).unwrap(), scores);
+
+    let flags = vec![Some(true), None, Some(false)];
+    let value = Value::from(flags);
+    assert_eq!(value, Value::List(vec![Value::Boolean(true), Value::Null, Value::Boolean(false)]));
+    let strings = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(Vec::<String>::try_from(Value::from(strings.clone())).unwrap(), strings);
+}
+
+fn message<T: std::fmt::Debug>(result: Result<T, RuntimeError>) -> String {
+    result.expect_err("the conversion should fail").message
+}
+
+#[test]
+fn wrong_element_type() {
+    let mixed = Value::List(vec![Value::Number(1), Value::String("two".to_string())]);
+    assert_eq!(message(Vec::<i64>::try_from(mixed)), "Expected a number but got a string");
+
+    // Deep inside a nested value too.
+    let nested = Value::from(vec![vec![1i64], vec![2]]);
+    assert_eq!(message(Vec::<Vec<bool>>::try_from(nested)), "Expected a boolean but got a number");
+
+    let mut entries = HashMap::new();
+    entries.insert("x".to_string(), Value::Boolean(true));
+    assert_eq!(message(HashMap::<String, String>::try_from(Value::from(entries))), "Expected a string but got a boolean");
+
+    assert_eq!(message(Vec::<i64>::try_from(Value::Number(1))), "Expected a list but got a number");
+    assert_eq!(message(HashMap::<String, i64>::try_from(Value::from(vec![1i64]))), "Expected a map but got a list");
+}
+
+#[test]
+fn non_string_key() {
+    let mut entries = HashMap::new();
+    entries.insert("1".to_string(), 1i64);
+    assert_eq!(message(HashMap::<i64, i64>::try_from(Value::from(entries))), "Expected a number but got a string");
+}
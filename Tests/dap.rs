@@ -0,0 +1,378 @@
+// Drives the debug adapter the way an editor does: requests go in over one
+// stream while responses and events come back over another.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use touch_grass::dap;
+use touch_grass::json::Json;
+use touch_grass::lsp::{read_message, write_message};
+
+/// How long to wait for the adapter before deciding it hung.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reads the bytes another thread sends, blocking until they arrive.
+struct ChannelReader {
+    chunks: Receiver<Vec<u8>>,
+    current: VecDeque<u8>,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.current.is_empty() {
+            match self.chunks.recv_timeout(TIMEOUT) {
+                Ok(chunk) => self.current.extend(chunk),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(0),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "no message from the adapter"))
+                }
+            }
+        }
+        let n = buf.len().min(self.current.len());
+        for (slot, byte) in buf.iter_mut().zip(self.current.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+}
+
+struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = self.0.send(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn channel_reader(chunks: Receiver<Vec<u8>>) -> BufReader<ChannelReader> {
+    BufReader::new(ChannelReader { chunks, current: VecDeque::new() })
+}
+
+/// The editor's side of a session with an adapter running on its own thread.
+struct Client {
+    requests: Option<ChannelWriter>,
+    replies: BufReader<ChannelReader>,
+    seq: i64,
+    /// Events read while waiting for something else.
+    events: Vec<Json>,
+    adapter: Option<JoinHandle<bool>>,
+}
+
+impl Client {
+    fn start() -> Client {
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (reply_sender, reply_receiver) = mpsc::channel();
+        let adapter = thread::spawn(move || {
+            dap::serve_streams(channel_reader(request_receiver), Box::new(ChannelWriter(reply_sender)))
+        });
+        Client {
+            requests: Some(ChannelWriter(request_sender)),
+            replies: channel_reader(reply_receiver),
+            seq: 0,
+            events: Vec::new(),
+            adapter: Some(adapter),
+        }
+    }
+
+    fn next(&mut self) -> Json {
+        read_message(&mut self.replies).unwrap().expect("the adapter hung up")
+    }
+
+    fn send(&mut self, command: &str, arguments: Json) -> i64 {
+        self.seq += 1;
+        let request = Json::object(vec![
+            ("seq", self.seq.into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments),
+        ]);
+        write_message(self.requests.as_mut().unwrap(), &request).unwrap();
+        self.seq
+    }
+
+    /// Sends a request and returns its response, which must be a success.
+    fn request(&mut self, command: &str, arguments: Json) -> Json {
+        let response = self.try_request(command, arguments);
+        assert_eq!(response.get("success"), Some(&Json::Bool(true)), "{} failed: {}", command, response);
+        response.get("body").cloned().unwrap_or(Json::Null)
+    }
+
+    fn try_request(&mut self, command: &str, arguments: Json) -> Json {
+        let seq = self.send(command, arguments);
+        loop {
+            let message = self.next();
+            if message.get("type").and_then(Json::as_str) == Some("event") {
+                self.events.push(message);
+            } else if message.get("request_seq").and_then(Json::as_i64) == Some(seq) {
+                return message;
+            }
+        }
+    }
+
+    /// Waits for the next event with this name and returns its body.
+    fn event(&mut self, name: &str) -> Json {
+        let is_named = |message: &Json| message.get("event").and_then(Json::as_str) == Some(name);
+        if let Some(i) = self.events.iter().position(is_named) {
+            return self.events.remove(i).get("body").cloned().unwrap_or(Json::Null);
+        }
+        loop {
+            let message = self.next();
+            if is_named(&message) {
+                return message.get("body").cloned().unwrap_or(Json::Null);
+            }
+            if message.get("type").and_then(Json::as_str) == Some("event") {
+                self.events.push(message);
+            }
+        }
+    }
+
+    /// Everything the program printed so far, from its output events.
+    fn printed(&mut self) -> String {
+        let mut text = String::new();
+        self.events.retain(|message| {
+            let body = message.get("body");
+            let stdout = body.and_then(|b| b.get("category")).and_then(Json::as_str) == Some("stdout");
+            if message.get("event").and_then(Json::as_str) == Some("output") && stdout {
+                text.push_str(body.and_then(|b| b.get("output")).and_then(Json::as_str).unwrap_or(""));
+                false
+            } else {
+                true
+            }
+        });
+        text
+    }
+
+    /// Sends `initialize`, `launch` and `configurationDone`.
+    fn launch(&mut self, program: &Program, extra: Vec<(&str, Json)>) {
+        self.request("initialize", Json::object(vec![("adapterID", "touch-grass".into())]));
+        self.event("initialized");
+        let mut arguments = vec![("program", program.path.to_string_lossy().into_owned().into())];
+        arguments.extend(extra);
+        self.request("launch", Json::object(arguments));
+    }
+
+    fn configuration_done(&mut self) {
+        self.request("configurationDone", Json::Null);
+    }
+
+    /// Disconnects and returns what the adapter's serve loop returned.
+    fn disconnect(mut self) -> bool {
+        self.request("disconnect", Json::Null);
+        self.requests = None;
+        self.adapter.take().unwrap().join().unwrap()
+    }
+}
+
+/// A program written to a temporary file for `launch`.
+struct Program {
+    path: PathBuf,
+}
+
+impl Program {
+    fn new(name: &str, source: &str) -> Program {
+        let path = std::env::temp_dir().join(format!("touch-grass-dap-{}-{}.tg", std::process::id(), name));
+        fs::write(&path, source).unwrap();
+        Program { path }
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn exit_code(body: &Json) -> Option<i64> {
+    body.get("exitCode").and_then(Json::as_i64)
+}
+
+#[test]
+fn program_io_goes_through_the_protocol() {
+    let program = Program::new("io", "touch grass number name as read_line(\"name? \")\nprint name\nprint read_line()\n");
+    let mut client = Client::start();
+    client.launch(&program, vec![("input", "Sam\n".into())]);
+    client.configuration_done();
+    assert_eq!(exit_code(&client.event("exited")), Some(0));
+    client.event("terminated");
+    // The prompt and prints arrive as output events, and the program reads
+    // the launch input rather than the adapter's stdin.
+    assert_eq!(client.printed(), "name? Sam\nnull\n");
+    assert!(client.disconnect());
+}
+
+#[test]
+fn program_input_is_empty_by_default() {
+    let program = Program::new("no-input", "print read_all()\nprint read_line()\n");
+    let mut client = Client::start();
+    client.launch(&program, Vec::new());
+    client.configuration_done();
+    assert_eq!(exit_code(&client.event("exited")), Some(0));
+    assert_eq!(client.printed(), "\nnull\n");
+    assert!(client.disconnect());
+}
+
+/// Where the program is stopped: the line of each frame, innermost first.
+fn stopped_lines(client: &mut Client) -> Vec<i64> {
+    let body = client.request("stackTrace", Json::object(vec![("threadId", 1i64.into())]));
+    body.get("stackFrames")
+        .and_then(Json::as_array)
+        .unwrap()
+        .iter()
+        .map(|frame| frame.get("line").and_then(Json::as_i64).unwrap())
+        .collect()
+}
+
+fn variable(client: &mut Client, name: &str) -> Option<String> {
+    let scopes = client.request("scopes", Json::object(vec![("frameId", 0i64.into())]));
+    let reference = scopes.at(&["scopes"]).and_then(Json::as_array).unwrap()[0]
+        .get("variablesReference")
+        .cloned()
+        .unwrap();
+    let body = client.request("variables", Json::object(vec![("variablesReference", reference)]));
+    body.get("variables")
+        .and_then(Json::as_array)
+        .unwrap()
+        .iter()
+        .find(|v| v.get("name").and_then(Json::as_str) == Some(name))
+        .and_then(|v| v.get("value").and_then(Json::as_str).map(String::from))
+}
+
+fn stop_reason(client: &mut Client) -> String {
+    client.event("stopped").get("reason").and_then(Json::as_str).unwrap().to_string()
+}
+
+const NESTED: &str = "\
+touch grass number x as 1
+go outside if x > 0 then
+    set x to 2
+    go outside if x > 1 then
+        set x to 3
+    fr fr
+    set x to 4
+fr fr
+print x
+";
+
+#[test]
+fn breakpoints_and_stepping() {
+    let program = Program::new("nested", NESTED);
+    let mut client = Client::start();
+    client.launch(&program, Vec::new());
+    let body = client.request("setBreakpoints", Json::object(vec![
+        ("breakpoints", Json::Array(vec![
+            Json::object(vec![("line", 3i64.into())]),
+            Json::object(vec![("line", 6i64.into())]), // `fr fr` isn't a statement
+        ])),
+    ]));
+    let verified: Vec<Option<&Json>> = body
+        .get("breakpoints")
+        .and_then(Json::as_array)
+        .unwrap()
+        .iter()
+        .map(|bp| bp.get("verified"))
+        .collect();
+    assert_eq!(verified, vec![Some(&Json::Bool(true)), Some(&Json::Bool(false))]);
+    client.configuration_done();
+
+    assert_eq!(stop_reason(&mut client), "breakpoint");
+    assert_eq!(stopped_lines(&mut client), vec![3, 2]);
+    assert_eq!(variable(&mut client, "x").as_deref(), Some("1"));
+    assert_eq!(variable(&mut client, "args").as_deref(), Some("[]"));
+
+    client.request("stepIn", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "step");
+    assert_eq!(stopped_lines(&mut client), vec![4, 2]);
+    assert_eq!(variable(&mut client, "x").as_deref(), Some("2"));
+
+    client.request("stepIn", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "step");
+    assert_eq!(stopped_lines(&mut client), vec![5, 4, 2]);
+
+    client.request("stepOut", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "step");
+    assert_eq!(stopped_lines(&mut client), vec![7, 2]);
+    assert_eq!(variable(&mut client, "x").as_deref(), Some("3"));
+
+    client.request("next", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "step");
+    assert_eq!(stopped_lines(&mut client), vec![9]);
+
+    let value = client.request("evaluate", Json::object(vec![("expression", "x + 10".into())]));
+    assert_eq!(value.get("result").and_then(Json::as_str), Some("14"));
+
+    client.request("continue", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(exit_code(&client.event("exited")), Some(0));
+    assert_eq!(client.printed(), "4\n");
+    assert!(client.disconnect());
+}
+
+#[test]
+fn step_over_skips_nested_blocks() {
+    let program = Program::new("step-over", NESTED);
+    let mut client = Client::start();
+    client.launch(&program, vec![("stopOnEntry", true.into())]);
+    client.configuration_done();
+
+    assert_eq!(stop_reason(&mut client), "entry");
+    assert_eq!(stopped_lines(&mut client), vec![1]);
+    client.request("next", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "step");
+    assert_eq!(stopped_lines(&mut client), vec![2]);
+    client.request("next", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "step");
+    assert_eq!(stopped_lines(&mut client), vec![9]);
+    assert_eq!(variable(&mut client, "x").as_deref(), Some("4"));
+    assert!(client.disconnect());
+}
+
+#[test]
+fn pause_stops_a_running_program() {
+    let program = Program::new("pause", "touch grass number i as 0\nwhile no_cap then\n    set i to i + 1\nfr fr\n");
+    let mut client = Client::start();
+    client.launch(&program, Vec::new());
+    client.configuration_done();
+
+    client.request("pause", Json::object(vec![("threadId", 1i64.into())]));
+    assert_eq!(stop_reason(&mut client), "pause");
+    // The pause can land before the loop body has run, if the program is quick
+    // to start.
+    let lines = stopped_lines(&mut client);
+    assert!(matches!(lines.as_slice(), [1] | [2] | [3, 2]), "{:?}", lines);
+    let threads = client.request("threads", Json::Null);
+    assert_eq!(threads.get("threads").and_then(Json::as_array).map(<[Json]>::len), Some(1));
+
+    // Disconnecting stops the program instead of waiting for it to finish.
+    assert!(client.disconnect());
+}
+
+#[test]
+fn runtime_errors_end_the_session() {
+    let program = Program::new("error", "touch grass number x as 1\nset y to 2\n");
+    let mut client = Client::start();
+    client.launch(&program, Vec::new());
+    client.configuration_done();
+    assert_eq!(exit_code(&client.event("exited")), Some(3));
+    client.event("terminated");
+
+    let response = client.try_request("stackTrace", Json::Null);
+    assert_eq!(response.get("success"), Some(&Json::Bool(false)));
+    assert!(client.disconnect());
+}
+
+#[test]
+fn launch_needs_a_readable_program() {
+    let mut client = Client::start();
+    client.request("initialize", Json::Null);
+    let response = client.try_request("launch", Json::object(vec![("program", "/nonexistent/missing.tg".into())]));
+    assert_eq!(response.get("success"), Some(&Json::Bool(false)));
+    assert!(client.disconnect());
+}
@@ -0,0 +1,70 @@
+// Runs every Tests/**/*.tg program on both engines, with and without the
+// optimizer, and checks that stdout, stderr and the exit code always match
+// the plain interpreter. A program's stdin is the .stdin file next to it, if
+// there is one.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn programs(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            programs(&path, out);
+        } else if path.extension().is_some_and(|e| e == "tg") {
+            out.push(path);
+        }
+    }
+}
+
+fn run(program: &Path, options: &[&str]) -> Output {
+    let stdin = match File::open(program.with_extension("stdin")) {
+        Ok(file) => Stdio::from(file),
+        Err(_) => Stdio::null(),
+    };
+    Command::new(env!("CARGO_BIN_EXE_touch-grass"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "-q"])
+        .args(options)
+        .arg(program)
+        .stdin(stdin)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn engines_agree() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    programs(&root.join("Tests"), &mut files);
+    files.sort();
+    assert!(!files.is_empty());
+
+    let mut failures = Vec::new();
+    for file in &files {
+        // Relative, as a user would run them, since errors can show the path.
+        let file = file.strip_prefix(root).unwrap();
+        let expected = run(file, &["--engine=tree"]);
+        for options in [&["--engine=vm"][..], &["--engine=tree", "-O"], &["--engine=vm", "-O"]] {
+            let actual = run(file, options);
+            if actual.status.code() != expected.status.code()
+                || actual.stdout != expected.stdout
+                || actual.stderr != expected.stderr
+            {
+                failures.push(format!(
+                    "{} with {}: exit code {:?}, expected {:?}\n--- stdout\n{}\n--- expected\n{}\n--- stderr\n{}\n--- expected\n{}",
+                    file.display(),
+                    options.join(" "),
+                    actual.status.code(),
+                    expected.status.code(),
+                    String::from_utf8_lossy(&actual.stdout),
+                    String::from_utf8_lossy(&expected.stdout),
+                    String::from_utf8_lossy(&actual.stderr),
+                    String::from_utf8_lossy(&expected.stderr),
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
@@ -0,0 +1,110 @@
+// The formatter keeps comments where they were, is a fixed point on its own
+// output, and prints optimized trees as source that parses.
+
+use touch_grass::formatter::format_source;
+
+fn fmt(source: &str) -> String {
+    format_source(source).unwrap_or_else(|errors| panic!("{:?}", errors))
+}
+
+#[test]
+fn formatting_twice_changes_nothing() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("Tests");
+    let mut checked = 0;
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("tg") {
+            continue;
+        }
+        let once = fmt(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(fmt(&once), once, "{} isn't stable", path.display());
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn comments_stay_put() {
+    let source = "#!/usr/bin/env touch-grass
+   // leading
+touch grass number x as 1    // trailing
+go outside if x > 0 then // on the header
+// first in the block
+print   x
+  // last in the block
+fr fr instead
+print 0 // in the else
+fr fr
+while x < 3 then
+    set x to x + 1
+    // after the last statement
+fr fr
+// at the end
+";
+    let expected = "#!/usr/bin/env touch-grass
+// leading
+touch grass number x as 1 // trailing
+go outside if x > 0 then // on the header
+    // first in the block
+    print x
+    // last in the block
+fr fr instead
+    print 0 // in the else
+fr fr
+while x < 3 then
+    set x to x + 1
+    // after the last statement
+fr fr
+// at the end
+";
+    let formatted = fmt(source);
+    assert_eq!(formatted, expected);
+    assert_eq!(fmt(&formatted), formatted);
+}
+
+#[test]
+fn comment_only_source() {
+    assert_eq!(fmt("  // nothing yet\n"), "// nothing yet\n");
+    assert_eq!(fmt("#!/usr/bin/env touch-grass\n"), "#!/usr/bin/env touch-grass\n");
+}
+
+#[test]
+fn unparsable_source_is_left_alone() {
+    assert!(format_source("print (1\n").is_err());
+}
+
+/// Folding `0 - 5` makes a negative literal, which has to print as source
+/// that parses back to the same number.
+#[test]
+fn optimized_programs_print_as_valid_source() {
+    use touch_grass::console::Buffer;
+    use touch_grass::formatter::format_program;
+    use touch_grass::lexer::tokenize_with_lines;
+    use touch_grass::optimizer::optimize;
+    use touch_grass::parser::Parser;
+    use touch_grass::Engine;
+
+    let source = "touch grass number x as 0 - 5
+print x > 0 - 5
+print 10 - (0 - 5)
+print 1 - (0 - 9223372036854775807 - 1)
+print 0 - 9223372036854775807 - 1
+";
+    let (optimized, _) = optimize(Parser::with_lines(tokenize_with_lines(source)).parse());
+    let printed = format_program(&optimized);
+    assert_eq!(printed, "touch grass number x as 0 - 5
+print x > 0 - 5
+print 15
+print 0 - 9223372036854775807
+print 0 - 9223372036854775807 - 1
+");
+
+    let run = |source: &str| {
+        let output = Buffer::new();
+        let mut engine = Engine::new();
+        engine.set_output(output.clone());
+        engine.run(source).unwrap();
+        output.contents()
+    };
+    assert_eq!(run(&printed), run(source));
+}
@@ -0,0 +1,28 @@
+/* Generated by touch-grass transpile from Tests/arithmetic.tg */
+
+static tg_value v_args;
+static tg_value v_a;
+static tg_value v_b;
+static tg_value v_big;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Operators, precedence and grouping.
+    v_a = tg_number(7);
+    v_b = tg_number(3);
+    tg_print(tg_add(v_a, v_b));
+    tg_print(tg_sub(tg_sub(v_a, v_b), tg_number(1)));
+    tg_print(tg_sub(v_a, tg_sub(v_b, tg_number(1))));
+    tg_print(tg_gt(v_a, v_b));
+    tg_print(tg_lt(v_a, v_b));
+    tg_print(tg_eq(v_a, tg_number(7)));
+    tg_print(tg_eq(tg_add(v_a, v_b), tg_number(10)));
+    tg_print(tg_add(tg_string("text"), tg_number(1)));
+    tg_print(tg_string("Bugatti!"));
+    // Arithmetic wraps around on overflow, on every engine and backend.
+    v_big = tg_number(9223372036854775807);
+    tg_print(tg_add(v_big, tg_number(1)));
+    tg_print(tg_sub(tg_sub(tg_number(0), v_big), tg_number(2)));
+    tg_print(tg_add(tg_number(9223372036854775807), tg_number(1)));
+    return tg_exit_code;
+}
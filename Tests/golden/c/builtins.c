@@ -0,0 +1,18 @@
+/* Generated by touch-grass transpile from Tests/builtins.tg */
+
+static tg_value v_args;
+static tg_value v_count;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Builtin functions and the args list.
+    tg_print(v_args);
+    tg_print(tg_call("len", 1, (tg_value[]){ v_args }));
+    tg_print(tg_call("len", 1, (tg_value[]){ tg_string("touch grass") }));
+    v_count = tg_add(tg_call("len", 1, (tg_value[]){ tg_string("abc") }), tg_number(1));
+    tg_print(v_count);
+    tg_print(tg_call("env", 1, (tg_value[]){ tg_string("TOUCH_GRASS_SURELY_UNSET") }));
+    tg_call("exit_code", 1, (tg_value[]){ tg_number(4) });
+    tg_print(tg_string("still runs after exit_code"));
+    return tg_exit_code;
+}
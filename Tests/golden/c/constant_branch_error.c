@@ -0,0 +1,20 @@
+/* Generated by touch-grass transpile from Tests/constant_branch_error.tg */
+
+static tg_value v_args;
+static tg_value v_nope;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // A runtime error inside branches the optimizer inlines.
+    tg_print(tg_string("before"));
+    if (tg_truthy(tg_lt(tg_number(1), tg_number(2)))) {
+        if (tg_truthy(tg_boolean(0))) {
+            tg_print(tg_string("never"));
+        } else {
+            tg_print(tg_string("inside"));
+            v_nope = tg_assign(v_nope, "nope", tg_number(1));
+        }
+    }
+    tg_print(tg_string("never printed"));
+    return tg_exit_code;
+}
@@ -0,0 +1,30 @@
+/* Generated by touch-grass transpile from Tests/constant_branches.tg */
+
+static tg_value v_args;
+static tg_value v_x;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Constant conditions everywhere.
+    v_x = tg_sub(tg_add(tg_number(2), tg_number(3)), tg_number(1));
+    if (tg_truthy(tg_lt(tg_number(1), tg_number(2)))) {
+        tg_print(tg_string("always")); // kept
+    } else {
+        tg_print(tg_string("never"));
+    }
+    while (tg_truthy(tg_boolean(0))) {
+        tg_print(tg_string("nope"));
+    }
+    while (tg_truthy(tg_lt(tg_number(5), tg_number(3)))) {
+        v_x = tg_add(v_x, tg_number(1));
+    }
+    if (tg_truthy(tg_gt(v_x, tg_add(tg_number(1), tg_number(1))))) {
+        tg_print(tg_add(v_x, tg_sub(tg_number(10), tg_number(4))));
+    }
+    if (tg_truthy(tg_number(0))) {
+        tg_print(tg_string("zero"));
+    }
+    tg_print(tg_add(tg_string("a"), tg_number(1)));
+    tg_print(tg_sub(tg_add(tg_call("len", 1, (tg_value[]){ tg_string("ab") }), tg_number(2)), tg_number(1)));
+    return tg_exit_code;
+}
@@ -0,0 +1,39 @@
+/* Generated by touch-grass transpile from Tests/control_flow.tg */
+
+static tg_value v_args;
+static tg_value v_i;
+static tg_value v_j;
+static tg_value v_missing;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Branches, nested loops and truthiness.
+    v_i = tg_number(0);
+    while (tg_truthy(tg_lt(v_i, tg_number(4)))) {
+        if (tg_truthy(tg_eq(v_i, tg_number(2)))) {
+            tg_print(tg_string("two"));
+        } else {
+            tg_print(v_i);
+        }
+        v_j = tg_number(0);
+        while (tg_truthy(tg_lt(v_j, v_i))) {
+            v_j = tg_add(v_j, tg_number(1));
+        }
+        v_i = tg_add(v_i, tg_number(1));
+    }
+    if (tg_truthy(tg_number(0))) {
+        tg_print(tg_string("zero is truthy"));
+    } else {
+        tg_print(tg_string("zero is falsy"));
+    }
+    if (tg_truthy(tg_string(""))) {
+        tg_print(tg_string("empty string is truthy"));
+    }
+    if (tg_truthy(v_args)) {
+        tg_print(tg_string("got args"));
+    } else {
+        tg_print(tg_string("no args"));
+    }
+    tg_print(tg_read(v_missing));
+    return tg_exit_code;
+}
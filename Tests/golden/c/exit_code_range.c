@@ -0,0 +1,13 @@
+/* Generated by touch-grass transpile from Tests/exit_code_range.tg */
+
+static tg_value v_args;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // exit_code only takes codes that fit in 32 bits.
+    tg_call("exit_code", 1, (tg_value[]){ tg_number(2147483647) });
+    tg_print(tg_string("fits"));
+    tg_call("exit_code", 1, (tg_value[]){ tg_number(4294967296) });
+    tg_print(tg_string("never printed"));
+    return tg_exit_code;
+}
@@ -0,0 +1,11 @@
+/* Generated by touch-grass transpile from Tests/files.tg */
+
+static tg_value v_args;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Filesystem builtins fail unless `run` is given --allow-read or --allow-write.
+    tg_print(tg_string("files"));
+    tg_print(tg_call("file_exists", 1, (tg_value[]){ tg_string("Tests") }));
+    return tg_exit_code;
+}
@@ -0,0 +1,25 @@
+/* Generated by touch-grass transpile from Tests/input.tg */
+
+static tg_value v_args;
+static tg_value v_name;
+static tg_value v_age;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Reading input. Tests/input.stdin is fed to this program's stdin.
+    v_name = tg_call("read_line", 1, (tg_value[]){ tg_string("What's your name\? ") });
+    tg_print(v_name);
+    v_age = tg_call("parse_number", 1, (tg_value[]){ tg_call("read_line", 0, NULL) });
+    tg_print(tg_add(v_age, tg_number(1)));
+    tg_print(tg_call("parse_number", 1, (tg_value[]){ tg_call("read_line", 1, (tg_value[]){ tg_string("Windows line ending: ") }) }));
+    tg_print(tg_call("parse_number", 1, (tg_value[]){ tg_string("-9223372036854775808") }));
+    tg_print(tg_call("parse_number", 1, (tg_value[]){ tg_string("9223372036854775807") }));
+    tg_print(tg_call("parse_number", 1, (tg_value[]){ tg_string("007") }));
+    tg_print(tg_call("read_line", 0, NULL));
+    tg_print(tg_call("len", 1, (tg_value[]){ tg_call("read_line", 0, NULL) }));
+    tg_print(tg_call("read_all", 0, NULL));
+    tg_print(tg_call("read_line", 0, NULL));
+    tg_print(tg_call("read_all", 0, NULL));
+    tg_print(tg_call("parse_number", 1, (tg_value[]){ tg_string("9223372036854775808") }));
+    return tg_exit_code;
+}
@@ -0,0 +1,37 @@
+/* Generated by touch-grass transpile from Tests/names.tg */
+
+static tg_value v_args;
+static tg_value v_console;
+static tg_value v_process;
+static tg_value v_ARITY;
+static tg_value v_require;
+static tg_value v_BigInt;
+static tg_value v_String;
+static tg_value v_Number;
+static tg_value v_Array;
+static tg_value v_TextDecoder;
+static tg_value v_let;
+static tg_value v_undefined;
+static tg_value v_main;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Variables named like things the generated programs use themselves.
+    v_console = tg_number(1);
+    v_process = tg_number(2);
+    v_ARITY = tg_number(3);
+    v_require = tg_number(4);
+    v_BigInt = tg_number(5);
+    v_String = tg_number(6);
+    v_Number = tg_number(7);
+    v_Array = tg_number(8);
+    v_TextDecoder = tg_number(9);
+    v_let = tg_number(10);
+    v_undefined = tg_number(11);
+    v_main = tg_number(12);
+    tg_print(tg_add(tg_add(tg_add(tg_add(v_console, v_process), v_ARITY), v_require), v_BigInt));
+    tg_print(tg_add(v_String, tg_number(0)));
+    tg_print(tg_add(tg_add(tg_add(tg_add(tg_add(v_Number, v_Array), v_TextDecoder), v_let), v_undefined), v_main));
+    tg_print(tg_call("len", 1, (tg_value[]){ v_args }));
+    return tg_exit_code;
+}
@@ -0,0 +1,271 @@
+/* The touch-grass runtime, the same in every program. */
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* Not every program uses every part of the runtime. */
+#if defined(__GNUC__)
+#define TG_RUNTIME static __attribute__((unused))
+#else
+#define TG_RUNTIME static
+#endif
+
+typedef enum { TG_UNDECLARED, TG_NULL, TG_NUMBER, TG_STRING, TG_BOOLEAN, TG_LIST } tg_tag;
+
+typedef struct tg_value {
+    tg_tag tag;
+    union {
+        int64_t number;
+        const char *string;
+        int boolean;
+        struct tg_list *list;
+    } as;
+} tg_value;
+
+typedef struct tg_list {
+    size_t len;
+    tg_value *items;
+} tg_list;
+
+static int tg_exit_code = 0;
+
+TG_RUNTIME tg_value tg_null(void) { tg_value v; v.tag = TG_NULL; v.as.number = 0; return v; }
+TG_RUNTIME tg_value tg_number(int64_t n) { tg_value v; v.tag = TG_NUMBER; v.as.number = n; return v; }
+TG_RUNTIME tg_value tg_string(const char *s) { tg_value v; v.tag = TG_STRING; v.as.string = s; return v; }
+TG_RUNTIME tg_value tg_boolean(int b) { tg_value v; v.tag = TG_BOOLEAN; v.as.boolean = b; return v; }
+
+TG_RUNTIME void tg_error(const char *format, ...) {
+    va_list args;
+    fflush(stdout);
+    fputs("Runtime error: ", stderr);
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fputc('\n', stderr);
+    exit(3);
+}
+
+TG_RUNTIME const char *tg_type_name(tg_value v) {
+    switch (v.tag) {
+    case TG_NUMBER: return "number";
+    case TG_STRING: return "string";
+    case TG_BOOLEAN: return "boolean";
+    case TG_LIST: return "list";
+    default: return "null";
+    }
+}
+
+/* Same text as Value's Display: strings inside lists are quoted. */
+TG_RUNTIME void tg_display(tg_value v, int quote_strings) {
+    size_t i;
+    switch (v.tag) {
+    case TG_NUMBER: printf("%lld", (long long)v.as.number); break;
+    case TG_STRING: printf(quote_strings ? "\"%s\"" : "%s", v.as.string); break;
+    case TG_BOOLEAN: fputs(v.as.boolean ? "true" : "false", stdout); break;
+    case TG_LIST:
+        putchar('[');
+        for (i = 0; i < v.as.list->len; i++) {
+            if (i > 0) fputs(", ", stdout);
+            tg_display(v.as.list->items[i], 1);
+        }
+        putchar(']');
+        break;
+    default: fputs("null", stdout); break;
+    }
+}
+
+TG_RUNTIME void tg_print(tg_value v) {
+    tg_display(v, 0);
+    putchar('\n');
+}
+
+TG_RUNTIME int tg_truthy(tg_value v) {
+    switch (v.tag) {
+    case TG_BOOLEAN: return v.as.boolean;
+    case TG_NUMBER: return v.as.number != 0;
+    case TG_STRING: return v.as.string[0] != '\0';
+    case TG_LIST: return v.as.list->len > 0;
+    default: return 0;
+    }
+}
+
+/* Reading a variable that was never declared gives null. */
+TG_RUNTIME tg_value tg_read(tg_value v) {
+    return v.tag == TG_UNDECLARED ? tg_null() : v;
+}
+
+/* `set` on a variable that was never declared is an error. */
+TG_RUNTIME tg_value tg_assign(tg_value current, const char *name, tg_value value) {
+    if (current.tag == TG_UNDECLARED) tg_error("Undefined variable: %s", name);
+    return value;
+}
+
+/* Operators only work on numbers; anything else gives null. Arithmetic wraps. */
+TG_RUNTIME tg_value tg_add(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_number((int64_t)((uint64_t)a.as.number + (uint64_t)b.as.number));
+}
+TG_RUNTIME tg_value tg_sub(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_number((int64_t)((uint64_t)a.as.number - (uint64_t)b.as.number));
+}
+TG_RUNTIME tg_value tg_gt(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_boolean(a.as.number > b.as.number);
+}
+TG_RUNTIME tg_value tg_lt(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_boolean(a.as.number < b.as.number);
+}
+TG_RUNTIME tg_value tg_eq(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_boolean(a.as.number == b.as.number);
+}
+
+typedef struct {
+    const char *name;
+    size_t min_args, max_args;
+} tg_builtin;
+
+static const tg_builtin tg_builtins[] = {
+    { "env", 1, 1 },
+    { "exit_code", 1, 1 },
+    { "len", 1, 1 },
+    { "at", 2, 2 },
+    { "read_line", 0, 1 },
+    { "read_all", 0, 0 },
+    { "parse_number", 1, 1 },
+    { "read_file", 1, 1 },
+    { "write_file", 2, 2 },
+    { "list_dir", 1, 1 },
+    { "file_exists", 1, 1 },
+    { "delete_file", 1, 1 },
+};
+
+TG_RUNTIME size_t tg_utf8_length(const char *s) {
+    size_t n = 0;
+    for (; *s; s++) {
+        if (((unsigned char)*s & 0xC0) != 0x80) n++;
+    }
+    return n;
+}
+
+/* The next line without its line ending, or null at the end of input. */
+TG_RUNTIME tg_value tg_read_line(void) {
+    size_t len = 0, capacity = 64;
+    char *line = malloc(capacity);
+    int c;
+    while ((c = getchar()) != EOF && c != '\n') {
+        if (len + 1 == capacity) line = realloc(line, capacity *= 2);
+        line[len++] = (char)c;
+    }
+    if (c == EOF && len == 0) {
+        free(line);
+        return tg_null();
+    }
+    if (c == '\n' && len > 0 && line[len - 1] == '\r') len--;
+    line[len] = '\0';
+    return tg_string(line);
+}
+
+TG_RUNTIME tg_value tg_read_all(void) {
+    size_t len = 0, capacity = 4096, n;
+    char *text = malloc(capacity);
+    while ((n = fread(text + len, 1, capacity - len - 1, stdin)) > 0) {
+        len += n;
+        if (len + 1 == capacity) text = realloc(text, capacity *= 2);
+    }
+    text[len] = '\0';
+    return tg_string(text);
+}
+
+/* An optional minus sign and then digits, nothing else. */
+TG_RUNTIME tg_value tg_parse_number(const char *text) {
+    const char *p = text + (text[0] == '-');
+    uint64_t n = 0, limit = text[0] == '-' ? (uint64_t)INT64_MAX + 1 : (uint64_t)INT64_MAX;
+    if (*p == '\0') tg_error("Cannot parse \"%s\" as a number", text);
+    for (; *p; p++) {
+        if (*p < '0' || *p > '9' || n > (limit - (uint64_t)(*p - '0')) / 10) {
+            tg_error("Cannot parse \"%s\" as a number", text);
+        }
+        n = n * 10 + (uint64_t)(*p - '0');
+    }
+    return tg_number(text[0] == '-' ? (int64_t)(0 - n) : (int64_t)n);
+}
+
+TG_RUNTIME tg_value tg_call(const char *name, size_t argc, const tg_value *args) {
+    const tg_builtin *builtin = NULL;
+    size_t i;
+    for (i = 0; i < sizeof tg_builtins / sizeof tg_builtins[0]; i++) {
+        if (strcmp(tg_builtins[i].name, name) == 0) builtin = &tg_builtins[i];
+    }
+    if (builtin == NULL) tg_error("Unknown function: %s", name);
+    if (argc < builtin->min_args || argc > builtin->max_args) {
+        if (builtin->min_args == builtin->max_args) {
+            tg_error("%s expects %zu argument(s) but got %zu", name, builtin->min_args, argc);
+        }
+        tg_error("%s expects %zu to %zu argument(s) but got %zu", name, builtin->min_args, builtin->max_args, argc);
+    }
+
+    if (strcmp(name, "env") == 0 && args[0].tag == TG_STRING) {
+        const char *value = getenv(args[0].as.string);
+        return value ? tg_string(value) : tg_null();
+    }
+    if (strcmp(name, "exit_code") == 0 && args[0].tag == TG_NUMBER) {
+        int64_t code = args[0].as.number;
+        if (code < INT32_MIN || code > INT32_MAX) tg_error("Exit code %lld is out of range", (long long)code);
+        tg_exit_code = (int32_t)code;
+        return tg_null();
+    }
+    if (strcmp(name, "len") == 0 && args[0].tag == TG_LIST) return tg_number((int64_t)args[0].as.list->len);
+    if (strcmp(name, "len") == 0 && args[0].tag == TG_STRING) return tg_number((int64_t)tg_utf8_length(args[0].as.string));
+    if (strcmp(name, "at") == 0 && args[0].tag == TG_LIST && args[1].tag == TG_NUMBER) {
+        int64_t index = args[1].as.number;
+        if (index < 0 || (uint64_t)index >= args[0].as.list->len) {
+            tg_error("Index %lld is out of range for a list of %zu items", (long long)index, args[0].as.list->len);
+        }
+        return args[0].as.list->items[index];
+    }
+    if (strcmp(name, "read_line") == 0 && (argc == 0 || args[0].tag == TG_STRING)) {
+        if (argc == 1) {
+            fputs(args[0].as.string, stdout);
+            fflush(stdout);
+        }
+        return tg_read_line();
+    }
+    if (strcmp(name, "read_all") == 0) return tg_read_all();
+    if (strcmp(name, "parse_number") == 0 && args[0].tag == TG_STRING) return tg_parse_number(args[0].as.string);
+    /* Generated programs get no filesystem access, like `run` without --allow-read or --allow-write. */
+    if ((strcmp(name, "read_file") == 0 || strcmp(name, "list_dir") == 0 || strcmp(name, "file_exists") == 0)
+        && args[0].tag == TG_STRING) {
+        tg_error("Not allowed to read \"%s\"", args[0].as.string);
+    }
+    if (((strcmp(name, "write_file") == 0 && args[1].tag == TG_STRING) || strcmp(name, "delete_file") == 0)
+        && args[0].tag == TG_STRING) {
+        tg_error("Not allowed to write \"%s\"", args[0].as.string);
+    }
+
+    fflush(stdout);
+    fprintf(stderr, "Runtime error: Invalid arguments to %s: ", name);
+    for (i = 0; i < argc; i++) {
+        fprintf(stderr, i > 0 ? ", %s" : "%s", tg_type_name(args[i]));
+    }
+    fputc('\n', stderr);
+    exit(3);
+}
+
+TG_RUNTIME tg_value tg_args(int argc, char **argv) {
+    static tg_list list;
+    tg_value v;
+    int i;
+    list.len = argc > 1 ? (size_t)(argc - 1) : 0;
+    list.items = malloc((list.len ? list.len : 1) * sizeof(tg_value));
+    for (i = 1; i < argc; i++) list.items[i - 1] = tg_string(argv[i]);
+    v.tag = TG_LIST;
+    v.as.list = &list;
+    return v;
+}
+
+/* End of the runtime. */
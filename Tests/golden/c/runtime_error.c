@@ -0,0 +1,13 @@
+/* Generated by touch-grass transpile from Tests/runtime_error.tg */
+
+static tg_value v_args;
+static tg_value v_nope;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    // Assigning to an undeclared variable stops the program.
+    tg_print(tg_string("before"));
+    v_nope = tg_assign(v_nope, "nope", tg_number(1));
+    tg_print(tg_string("never printed"));
+    return tg_exit_code;
+}
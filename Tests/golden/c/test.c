@@ -0,0 +1,9 @@
+/* Generated by touch-grass transpile from Tests/test.tg */
+
+static tg_value v_args;
+
+int main(int argc, char **argv) {
+    v_args = tg_args(argc, argv);
+    tg_print(tg_string("Hello World!"));
+    return tg_exit_code;
+}
@@ -0,0 +1,25 @@
+// Generated by touch-grass transpile from Tests/arithmetic.tg
+
+
+let v_args = $tg.args;
+let v_a, v_b, v_big;
+
+$tg.run(() => {
+  // Operators, precedence and grouping.
+  v_a = 7n;
+  v_b = 3n;
+  $tg.print($tg.add(v_a, v_b));
+  $tg.print($tg.sub($tg.sub(v_a, v_b), 1n));
+  $tg.print($tg.sub(v_a, $tg.sub(v_b, 1n)));
+  $tg.print($tg.gt(v_a, v_b));
+  $tg.print($tg.lt(v_a, v_b));
+  $tg.print($tg.eq(v_a, 7n));
+  $tg.print($tg.eq($tg.add(v_a, v_b), 10n));
+  $tg.print($tg.add("text", 1n));
+  $tg.print("Bugatti!");
+  // Arithmetic wraps around on overflow, on every engine and backend.
+  v_big = 9223372036854775807n;
+  $tg.print($tg.add(v_big, 1n));
+  $tg.print($tg.sub($tg.sub(0n, v_big), 2n));
+  $tg.print($tg.add(9223372036854775807n, 1n));
+});
@@ -0,0 +1,17 @@
+// Generated by touch-grass transpile from Tests/builtins.tg
+
+
+let v_args = $tg.args;
+let v_count;
+
+$tg.run(() => {
+  // Builtin functions and the args list.
+  $tg.print(v_args);
+  $tg.print($tg.call("len", v_args));
+  $tg.print($tg.call("len", "touch grass"));
+  v_count = $tg.add($tg.call("len", "abc"), 1n);
+  $tg.print(v_count);
+  $tg.print($tg.call("env", "TOUCH_GRASS_SURELY_UNSET"));
+  $tg.call("exit_code", 4n);
+  $tg.print("still runs after exit_code");
+});
@@ -0,0 +1,19 @@
+// Generated by touch-grass transpile from Tests/constant_branch_error.tg
+
+
+let v_args = $tg.args;
+let v_nope;
+
+$tg.run(() => {
+  // A runtime error inside branches the optimizer inlines.
+  $tg.print("before");
+  if ($tg.truthy($tg.lt(1n, 2n))) {
+    if ($tg.truthy(false)) {
+      $tg.print("never");
+    } else {
+      $tg.print("inside");
+      v_nope = $tg.assign(v_nope, "nope", 1n);
+    }
+  }
+  $tg.print("never printed");
+});
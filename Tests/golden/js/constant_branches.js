@@ -0,0 +1,29 @@
+// Generated by touch-grass transpile from Tests/constant_branches.tg
+
+
+let v_args = $tg.args;
+let v_x;
+
+$tg.run(() => {
+  // Constant conditions everywhere.
+  v_x = $tg.sub($tg.add(2n, 3n), 1n);
+  if ($tg.truthy($tg.lt(1n, 2n))) {
+    $tg.print("always"); // kept
+  } else {
+    $tg.print("never");
+  }
+  while ($tg.truthy(false)) {
+    $tg.print("nope");
+  }
+  while ($tg.truthy($tg.lt(5n, 3n))) {
+    v_x = $tg.add(v_x, 1n);
+  }
+  if ($tg.truthy($tg.gt(v_x, $tg.add(1n, 1n)))) {
+    $tg.print($tg.add(v_x, $tg.sub(10n, 4n)));
+  }
+  if ($tg.truthy(0n)) {
+    $tg.print("zero");
+  }
+  $tg.print($tg.add("a", 1n));
+  $tg.print($tg.sub($tg.add($tg.call("len", "ab"), 2n), 1n));
+});
@@ -0,0 +1,36 @@
+// Generated by touch-grass transpile from Tests/control_flow.tg
+
+
+let v_args = $tg.args;
+let v_i, v_j, v_missing;
+
+$tg.run(() => {
+  // Branches, nested loops and truthiness.
+  v_i = 0n;
+  while ($tg.truthy($tg.lt(v_i, 4n))) {
+    if ($tg.truthy($tg.eq(v_i, 2n))) {
+      $tg.print("two");
+    } else {
+      $tg.print(v_i);
+    }
+    v_j = 0n;
+    while ($tg.truthy($tg.lt(v_j, v_i))) {
+      v_j = $tg.add(v_j, 1n);
+    }
+    v_i = $tg.add(v_i, 1n);
+  }
+  if ($tg.truthy(0n)) {
+    $tg.print("zero is truthy");
+  } else {
+    $tg.print("zero is falsy");
+  }
+  if ($tg.truthy("")) {
+    $tg.print("empty string is truthy");
+  }
+  if ($tg.truthy(v_args)) {
+    $tg.print("got args");
+  } else {
+    $tg.print("no args");
+  }
+  $tg.print((v_missing ?? null));
+});
@@ -0,0 +1,12 @@
+// Generated by touch-grass transpile from Tests/exit_code_range.tg
+
+
+let v_args = $tg.args;
+
+$tg.run(() => {
+  // exit_code only takes codes that fit in 32 bits.
+  $tg.call("exit_code", 2147483647n);
+  $tg.print("fits");
+  $tg.call("exit_code", 4294967296n);
+  $tg.print("never printed");
+});
@@ -0,0 +1,10 @@
+// Generated by touch-grass transpile from Tests/files.tg
+
+
+let v_args = $tg.args;
+
+$tg.run(() => {
+  // Filesystem builtins fail unless `run` is given --allow-read or --allow-write.
+  $tg.print("files");
+  $tg.print($tg.call("file_exists", "Tests"));
+});
@@ -0,0 +1,23 @@
+// Generated by touch-grass transpile from Tests/input.tg
+
+
+let v_args = $tg.args;
+let v_name, v_age;
+
+$tg.run(() => {
+  // Reading input. Tests/input.stdin is fed to this program's stdin.
+  v_name = $tg.call("read_line", "What's your name? ");
+  $tg.print(v_name);
+  v_age = $tg.call("parse_number", $tg.call("read_line"));
+  $tg.print($tg.add(v_age, 1n));
+  $tg.print($tg.call("parse_number", $tg.call("read_line", "Windows line ending: ")));
+  $tg.print($tg.call("parse_number", "-9223372036854775808"));
+  $tg.print($tg.call("parse_number", "9223372036854775807"));
+  $tg.print($tg.call("parse_number", "007"));
+  $tg.print($tg.call("read_line"));
+  $tg.print($tg.call("len", $tg.call("read_line")));
+  $tg.print($tg.call("read_all"));
+  $tg.print($tg.call("read_line"));
+  $tg.print($tg.call("read_all"));
+  $tg.print($tg.call("parse_number", "9223372036854775808"));
+});
@@ -0,0 +1,25 @@
+// Generated by touch-grass transpile from Tests/names.tg
+
+
+let v_args = $tg.args;
+let v_console, v_process, v_ARITY, v_require, v_BigInt, v_String, v_Number, v_Array, v_TextDecoder, v_let, v_undefined, v_main;
+
+$tg.run(() => {
+  // Variables named like things the generated programs use themselves.
+  v_console = 1n;
+  v_process = 2n;
+  v_ARITY = 3n;
+  v_require = 4n;
+  v_BigInt = 5n;
+  v_String = 6n;
+  v_Number = 7n;
+  v_Array = 8n;
+  v_TextDecoder = 9n;
+  v_let = 10n;
+  v_undefined = 11n;
+  v_main = 12n;
+  $tg.print($tg.add($tg.add($tg.add($tg.add(v_console, v_process), v_ARITY), v_require), v_BigInt));
+  $tg.print($tg.add(v_String, 0n));
+  $tg.print($tg.add($tg.add($tg.add($tg.add($tg.add(v_Number, v_Array), v_TextDecoder), v_let), v_undefined), v_main));
+  $tg.print($tg.call("len", v_args));
+});
@@ -0,0 +1,154 @@
+// The touch-grass runtime, the same in every program.
+const $tg = (() => {
+  const ARITY = { env: [1, 1], exit_code: [1, 1], len: [1, 1], at: [2, 2], read_line: [0, 1], read_all: [0, 0], parse_number: [1, 1], read_file: [1, 1], write_file: [2, 2], list_dir: [1, 1], file_exists: [1, 1], delete_file: [1, 1] };
+
+  class RuntimeError extends Error {}
+  const node = typeof process !== "undefined" && process.versions != null && process.versions.node != null;
+
+  const typeName = (value) => {
+    switch (typeof value) {
+      case "bigint": return "number";
+      case "string": return "string";
+      case "boolean": return "boolean";
+      default: return Array.isArray(value) ? "list" : "null";
+    }
+  };
+
+  // Same text as Value's Display: strings inside lists are quoted.
+  const display = (value) => {
+    if (value === null || value === undefined) return "null";
+    if (Array.isArray(value)) {
+      return "[" + value.map((item) => typeof item === "string" ? `"${item}"` : display(item)).join(", ") + "]";
+    }
+    return String(value);
+  };
+
+  // Stdin is read a byte at a time, so a prompt shows before each line.
+  let inputEnded = false;
+  const readBytes = (stopAtNewline) => {
+    const bytes = [];
+    const buffer = new Uint8Array(1);
+    while (node && !inputEnded) {
+      let n = 0;
+      try {
+        n = require("fs").readSync(0, buffer, 0, 1, null);
+      } catch (error) {
+        if (error.code === "EAGAIN") continue;
+        if (error.code !== "EOF") throw error;
+      }
+      if (n === 0) {
+        inputEnded = true;
+      } else if (stopAtNewline && buffer[0] === 10) {
+        return { bytes, newline: true };
+      } else {
+        bytes.push(buffer[0]);
+      }
+    }
+    return { bytes, newline: false };
+  };
+  const decode = (bytes) => new TextDecoder().decode(Uint8Array.from(bytes));
+
+  // Generated programs get no filesystem access, like `run` without --allow-read or --allow-write.
+  const denied = (access, path) => {
+    if (typeof path !== "string") return undefined;
+    throw new RuntimeError(`Not allowed to ${access} "${path}"`);
+  };
+
+  const numbers = (op) => (a, b) => typeof a === "bigint" && typeof b === "bigint" ? op(a, b) : null;
+
+  const builtins = {
+    env: (key) => typeof key === "string" ? (node ? process.env[key] ?? null : null) : undefined,
+    exit_code: (code) => {
+      if (typeof code !== "bigint") return undefined;
+      if (BigInt.asIntN(32, code) !== code) throw new RuntimeError(`Exit code ${code} is out of range`);
+      $tg.exitCode = Number(code);
+      return null;
+    },
+    len: (value) => {
+      if (Array.isArray(value)) return BigInt(value.length);
+      if (typeof value === "string") return BigInt([...value].length);
+      return undefined;
+    },
+    at: (list, index) => {
+      if (!Array.isArray(list) || typeof index !== "bigint") return undefined;
+      if (index < 0n || index >= BigInt(list.length)) {
+        throw new RuntimeError(`Index ${index} is out of range for a list of ${list.length} items`);
+      }
+      return list[Number(index)];
+    },
+    read_line: (prompt) => {
+      if (prompt !== undefined && typeof prompt !== "string") return undefined;
+      if (prompt !== undefined) $tg.prompt(prompt);
+      const { bytes, newline } = readBytes(true);
+      if (!newline && bytes.length === 0) return null;
+      if (newline && bytes[bytes.length - 1] === 13) bytes.pop();
+      return decode(bytes);
+    },
+    read_all: () => decode(readBytes(false).bytes),
+    parse_number: (text) => {
+      if (typeof text !== "string") return undefined;
+      const n = /^-?[0-9]+$/.test(text) ? BigInt(text) : null;
+      if (n === null || n !== BigInt.asIntN(64, n)) throw new RuntimeError(`Cannot parse "${text}" as a number`);
+      return n;
+    },
+    read_file: (path) => denied("read", path),
+    write_file: (path, text) => typeof text === "string" ? denied("write", path) : undefined,
+    list_dir: (path) => denied("read", path),
+    file_exists: (path) => denied("read", path),
+    delete_file: (path) => denied("write", path),
+  };
+
+  return {
+    RuntimeError,
+    args: node ? process.argv.slice(2) : [],
+    exitCode: null,
+    // Replace to send output somewhere other than the console.
+    write: (text) => console.log(text),
+    // Shows read_line's prompt, without a newline; replace along with write.
+    prompt: (text) => node ? process.stdout.write(text) : console.log(text),
+    print(value) {
+      this.write(display(value));
+    },
+    truthy(value) {
+      if (typeof value === "bigint") return value !== 0n;
+      if (typeof value === "string" || Array.isArray(value)) return value.length > 0;
+      return value === true;
+    },
+    add: numbers((a, b) => BigInt.asIntN(64, a + b)),
+    sub: numbers((a, b) => BigInt.asIntN(64, a - b)),
+    gt: numbers((a, b) => a > b),
+    lt: numbers((a, b) => a < b),
+    eq: numbers((a, b) => a === b),
+    // `set` on a variable that was never declared is an error.
+    assign(current, name, value) {
+      if (current === undefined) throw new RuntimeError(`Undefined variable: ${name}`);
+      return value;
+    },
+    call(name, ...args) {
+      const arity = ARITY[name];
+      if (arity === undefined) throw new RuntimeError(`Unknown function: ${name}`);
+      const [min, max] = arity;
+      if (args.length < min || args.length > max) {
+        const expected = min === max ? `${min}` : `${min} to ${max}`;
+        throw new RuntimeError(`${name} expects ${expected} argument(s) but got ${args.length}`);
+      }
+      const result = builtins[name](...args);
+      if (result === undefined) {
+        throw new RuntimeError(`Invalid arguments to ${name}: ${args.map(typeName).join(", ")}`);
+      }
+      return result;
+    },
+    run(program) {
+      try {
+        program();
+      } catch (error) {
+        if (!(error instanceof RuntimeError)) throw error;
+        console.error(`Runtime error: ${error.message}`);
+        if (node) process.exitCode = 3;
+        return;
+      }
+      if (node && this.exitCode !== null) process.exitCode = this.exitCode;
+    },
+  };
+})();
+// End of the runtime.
@@ -0,0 +1,12 @@
+// Generated by touch-grass transpile from Tests/runtime_error.tg
+
+
+let v_args = $tg.args;
+let v_nope;
+
+$tg.run(() => {
+  // Assigning to an undeclared variable stops the program.
+  $tg.print("before");
+  v_nope = $tg.assign(v_nope, "nope", 1n);
+  $tg.print("never printed");
+});
@@ -0,0 +1,8 @@
+// Generated by touch-grass transpile from Tests/test.tg
+
+
+let v_args = $tg.args;
+
+$tg.run(() => {
+  $tg.print("Hello World!");
+});
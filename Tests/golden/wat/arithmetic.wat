@@ -0,0 +1,111 @@
+;; Generated by touch-grass transpile from Tests/arithmetic.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4112))
+  (data (i32.const 4096) "textBugatti!")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.a.t i32) (local $v.a.v i64)
+    (local $v.b.t i32) (local $v.b.v i64)
+    (local $v.big.t i32) (local $v.big.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Operators, precedence and grouping.
+    i32.const 2
+    i64.const 7
+    local.set $v.a.v
+    local.set $v.a.t
+    i32.const 2
+    i64.const 3
+    local.set $v.b.v
+    local.set $v.b.t
+    local.get $v.a.t
+    local.get $v.a.v
+    local.get $v.b.t
+    local.get $v.b.v
+    call $add
+    call $print
+    local.get $v.a.t
+    local.get $v.a.v
+    local.get $v.b.t
+    local.get $v.b.v
+    call $sub
+    i32.const 2
+    i64.const 1
+    call $sub
+    call $print
+    local.get $v.a.t
+    local.get $v.a.v
+    local.get $v.b.t
+    local.get $v.b.v
+    i32.const 2
+    i64.const 1
+    call $sub
+    call $sub
+    call $print
+    local.get $v.a.t
+    local.get $v.a.v
+    local.get $v.b.t
+    local.get $v.b.v
+    call $gt
+    call $print
+    local.get $v.a.t
+    local.get $v.a.v
+    local.get $v.b.t
+    local.get $v.b.v
+    call $lt
+    call $print
+    local.get $v.a.t
+    local.get $v.a.v
+    i32.const 2
+    i64.const 7
+    call $eq
+    call $print
+    local.get $v.a.t
+    local.get $v.a.v
+    local.get $v.b.t
+    local.get $v.b.v
+    call $add
+    i32.const 2
+    i64.const 10
+    call $eq
+    call $print
+    i32.const 3
+    i64.const 17592186044420
+    i32.const 2
+    i64.const 1
+    call $add
+    call $print
+    i32.const 3
+    i64.const 17609365913608
+    call $print
+    ;; Arithmetic wraps around on overflow, on every engine and backend.
+    i32.const 2
+    i64.const 9223372036854775807
+    local.set $v.big.v
+    local.set $v.big.t
+    local.get $v.big.t
+    local.get $v.big.v
+    i32.const 2
+    i64.const 1
+    call $add
+    call $print
+    i32.const 2
+    i64.const 0
+    local.get $v.big.t
+    local.get $v.big.v
+    call $sub
+    i32.const 2
+    i64.const 2
+    call $sub
+    call $print
+    i32.const 2
+    i64.const 9223372036854775807
+    i32.const 2
+    i64.const 1
+    call $add
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,49 @@
+;; Generated by touch-grass transpile from Tests/builtins.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4160))
+  (data (i32.const 4096) "touch grassabcTOUCH_GRASS_SURELY_UNSETstill runs after exit_code")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.count.t i32) (local $v.count.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Builtin functions and the args list.
+    local.get $v.args.t
+    local.get $v.args.v
+    call $print
+    local.get $v.args.t
+    local.get $v.args.v
+    call $builtin_len
+    call $print
+    i32.const 3
+    i64.const 17592186044427
+    call $builtin_len
+    call $print
+    i32.const 3
+    i64.const 17639430684675
+    call $builtin_len
+    i32.const 2
+    i64.const 1
+    call $add
+    local.set $v.count.v
+    local.set $v.count.t
+    local.get $v.count.t
+    local.get $v.count.v
+    call $print
+    i32.const 3
+    i64.const 17652315586584
+    call $builtin_env
+    call $print
+    i32.const 2
+    i64.const 4
+    call $builtin_exit_code
+    drop
+    drop
+    i32.const 3
+    i64.const 17755394801690
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,49 @@
+;; Generated by touch-grass transpile from Tests/constant_branch_error.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4152))
+  (data (i32.const 4096) "beforeneverinsideUndefined variable: nopenever printed")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.nope.t i32) (local $v.nope.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; A runtime error inside branches the optimizer inlines.
+    i32.const 3
+    i64.const 17592186044422
+    call $print
+    i32.const 2
+    i64.const 1
+    i32.const 2
+    i64.const 2
+    call $lt
+    call $truthy
+    if
+      i32.const 4
+      i64.const 0
+      call $truthy
+      if
+        i32.const 3
+        i64.const 17617955848197
+        call $print
+      else
+        i32.const 3
+        i64.const 17639430684678
+        call $print
+        i32.const 2
+        i64.const 1
+        local.get $v.nope.t
+        i32.const 4113
+        i32.const 24
+        call $check_declared
+        local.set $v.nope.v
+        local.set $v.nope.t
+      end
+    end
+    i32.const 3
+    i64.const 17768279703565
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,117 @@
+;; Generated by touch-grass transpile from Tests/constant_branches.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4120))
+  (data (i32.const 4096) "alwaysnevernopezeroaab")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.x.t i32) (local $v.x.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Constant conditions everywhere.
+    i32.const 2
+    i64.const 2
+    i32.const 2
+    i64.const 3
+    call $add
+    i32.const 2
+    i64.const 1
+    call $sub
+    local.set $v.x.v
+    local.set $v.x.t
+    i32.const 2
+    i64.const 1
+    i32.const 2
+    i64.const 2
+    call $lt
+    call $truthy
+    if
+      i32.const 3
+      i64.const 17592186044422
+      call $print ;; kept
+    else
+      i32.const 3
+      i64.const 17617955848197
+      call $print
+    end
+    block $break1
+      loop $continue1
+        i32.const 4
+        i64.const 0
+        call $truthy
+        i32.eqz
+        br_if $break1
+        i32.const 3
+        i64.const 17639430684676
+        call $print
+        br $continue1
+      end
+    end
+    block $break2
+      loop $continue2
+        i32.const 2
+        i64.const 5
+        i32.const 2
+        i64.const 3
+        call $lt
+        call $truthy
+        i32.eqz
+        br_if $break2
+        local.get $v.x.t
+        local.get $v.x.v
+        i32.const 2
+        i64.const 1
+        call $add
+        local.set $v.x.v
+        local.set $v.x.t
+        br $continue2
+      end
+    end
+    local.get $v.x.t
+    local.get $v.x.v
+    i32.const 2
+    i64.const 1
+    i32.const 2
+    i64.const 1
+    call $add
+    call $gt
+    call $truthy
+    if
+      local.get $v.x.t
+      local.get $v.x.v
+      i32.const 2
+      i64.const 10
+      i32.const 2
+      i64.const 4
+      call $sub
+      call $add
+      call $print
+    end
+    i32.const 2
+    i64.const 0
+    call $truthy
+    if
+      i32.const 3
+      i64.const 17656610553860
+      call $print
+    end
+    i32.const 3
+    i64.const 17673790423041
+    i32.const 2
+    i64.const 1
+    call $add
+    call $print
+    i32.const 3
+    i64.const 17678085390338
+    call $builtin_len
+    i32.const 2
+    i64.const 2
+    call $add
+    i32.const 2
+    i64.const 1
+    call $sub
+    call $print
+    global.get $exit_code)
+)
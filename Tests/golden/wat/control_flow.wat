@@ -0,0 +1,116 @@
+;; Generated by touch-grass transpile from Tests/control_flow.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4168))
+  (data (i32.const 4096) "twozero is truthyzero is falsyempty string is truthygot argsno args")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.i.t i32) (local $v.i.v i64)
+    (local $v.j.t i32) (local $v.j.v i64)
+    (local $v.missing.t i32) (local $v.missing.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Branches, nested loops and truthiness.
+    i32.const 2
+    i64.const 0
+    local.set $v.i.v
+    local.set $v.i.t
+    block $break1
+      loop $continue1
+        local.get $v.i.t
+        local.get $v.i.v
+        i32.const 2
+        i64.const 4
+        call $lt
+        call $truthy
+        i32.eqz
+        br_if $break1
+        local.get $v.i.t
+        local.get $v.i.v
+        i32.const 2
+        i64.const 2
+        call $eq
+        call $truthy
+        if
+          i32.const 3
+          i64.const 17592186044419
+          call $print
+        else
+          local.get $v.i.t
+          local.get $v.i.v
+          call $print
+        end
+        i32.const 2
+        i64.const 0
+        local.set $v.j.v
+        local.set $v.j.t
+        block $break2
+          loop $continue2
+            local.get $v.j.t
+            local.get $v.j.v
+            local.get $v.i.t
+            local.get $v.i.v
+            call $lt
+            call $truthy
+            i32.eqz
+            br_if $break2
+            local.get $v.j.t
+            local.get $v.j.v
+            i32.const 2
+            i64.const 1
+            call $add
+            local.set $v.j.v
+            local.set $v.j.t
+            br $continue2
+          end
+        end
+        local.get $v.i.t
+        local.get $v.i.v
+        i32.const 2
+        i64.const 1
+        call $add
+        local.set $v.i.v
+        local.set $v.i.t
+        br $continue1
+      end
+    end
+    i32.const 2
+    i64.const 0
+    call $truthy
+    if
+      i32.const 3
+      i64.const 17605070946318
+      call $print
+    else
+      i32.const 3
+      i64.const 17665200488461
+      call $print
+    end
+    i32.const 3
+    i64.const 17721035063296
+    call $truthy
+    if
+      i32.const 3
+      i64.const 17721035063318
+      call $print
+    end
+    local.get $v.args.t
+    local.get $v.args.v
+    call $truthy
+    if
+      i32.const 3
+      i64.const 17815524343816
+      call $print
+    else
+      i32.const 3
+      i64.const 17849884082183
+      call $print
+    end
+    local.get $v.missing.t
+    local.get $v.missing.v
+    call $read
+    call $print
+    global.get $exit_code)
+)
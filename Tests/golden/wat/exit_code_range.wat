@@ -0,0 +1,30 @@
+;; Generated by touch-grass transpile from Tests/exit_code_range.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4120))
+  (data (i32.const 4096) "fitsnever printed")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; exit_code only takes codes that fit in 32 bits.
+    i32.const 2
+    i64.const 2147483647
+    call $builtin_exit_code
+    drop
+    drop
+    i32.const 3
+    i64.const 17592186044420
+    call $print
+    i32.const 2
+    i64.const 4294967296
+    call $builtin_exit_code
+    drop
+    drop
+    i32.const 3
+    i64.const 17609365913613
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,21 @@
+;; Generated by touch-grass transpile from Tests/files.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4112))
+  (data (i32.const 4096) "filesTests")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Filesystem builtins fail unless `run` is given --allow-read or --allow-write.
+    i32.const 3
+    i64.const 17592186044421
+    call $print
+    i32.const 3
+    i64.const 17613660880901
+    call $builtin_file_exists
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,74 @@
+;; Generated by touch-grass transpile from Tests/input.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4200))
+  (data (i32.const 4096) "What's your name? Windows line ending: -922337203685477580892233720368547758070079223372036854775808")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.name.t i32) (local $v.name.v i64)
+    (local $v.age.t i32) (local $v.age.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Reading input. Tests/input.stdin is fed to this program's stdin.
+    i32.const 3
+    i64.const 17592186044434
+    call $builtin_read_line
+    local.set $v.name.v
+    local.set $v.name.t
+    local.get $v.name.t
+    local.get $v.name.v
+    call $print
+    i32.const 0
+    i64.const 0
+    call $builtin_read_line
+    call $builtin_parse_number
+    local.set $v.age.v
+    local.set $v.age.t
+    local.get $v.age.t
+    local.get $v.age.v
+    i32.const 2
+    i64.const 1
+    call $add
+    call $print
+    i32.const 3
+    i64.const 17669495455765
+    call $builtin_read_line
+    call $builtin_parse_number
+    call $print
+    i32.const 3
+    i64.const 17759689768980
+    call $builtin_parse_number
+    call $print
+    i32.const 3
+    i64.const 17845589114899
+    call $builtin_parse_number
+    call $print
+    i32.const 3
+    i64.const 17927193493507
+    call $builtin_parse_number
+    call $print
+    i32.const 0
+    i64.const 0
+    call $builtin_read_line
+    call $print
+    i32.const 0
+    i64.const 0
+    call $builtin_read_line
+    call $builtin_len
+    call $print
+    call $builtin_read_all
+    call $print
+    i32.const 0
+    i64.const 0
+    call $builtin_read_line
+    call $print
+    call $builtin_read_all
+    call $print
+    i32.const 3
+    i64.const 17940078395411
+    call $builtin_parse_number
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,117 @@
+;; Generated by touch-grass transpile from Tests/names.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4096))
+  (data (i32.const 4096) "")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.console.t i32) (local $v.console.v i64)
+    (local $v.process.t i32) (local $v.process.v i64)
+    (local $v.ARITY.t i32) (local $v.ARITY.v i64)
+    (local $v.require.t i32) (local $v.require.v i64)
+    (local $v.BigInt.t i32) (local $v.BigInt.v i64)
+    (local $v.String.t i32) (local $v.String.v i64)
+    (local $v.Number.t i32) (local $v.Number.v i64)
+    (local $v.Array.t i32) (local $v.Array.v i64)
+    (local $v.TextDecoder.t i32) (local $v.TextDecoder.v i64)
+    (local $v.let.t i32) (local $v.let.v i64)
+    (local $v.undefined.t i32) (local $v.undefined.v i64)
+    (local $v.main.t i32) (local $v.main.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Variables named like things the generated programs use themselves.
+    i32.const 2
+    i64.const 1
+    local.set $v.console.v
+    local.set $v.console.t
+    i32.const 2
+    i64.const 2
+    local.set $v.process.v
+    local.set $v.process.t
+    i32.const 2
+    i64.const 3
+    local.set $v.ARITY.v
+    local.set $v.ARITY.t
+    i32.const 2
+    i64.const 4
+    local.set $v.require.v
+    local.set $v.require.t
+    i32.const 2
+    i64.const 5
+    local.set $v.BigInt.v
+    local.set $v.BigInt.t
+    i32.const 2
+    i64.const 6
+    local.set $v.String.v
+    local.set $v.String.t
+    i32.const 2
+    i64.const 7
+    local.set $v.Number.v
+    local.set $v.Number.t
+    i32.const 2
+    i64.const 8
+    local.set $v.Array.v
+    local.set $v.Array.t
+    i32.const 2
+    i64.const 9
+    local.set $v.TextDecoder.v
+    local.set $v.TextDecoder.t
+    i32.const 2
+    i64.const 10
+    local.set $v.let.v
+    local.set $v.let.t
+    i32.const 2
+    i64.const 11
+    local.set $v.undefined.v
+    local.set $v.undefined.t
+    i32.const 2
+    i64.const 12
+    local.set $v.main.v
+    local.set $v.main.t
+    local.get $v.console.t
+    local.get $v.console.v
+    local.get $v.process.t
+    local.get $v.process.v
+    call $add
+    local.get $v.ARITY.t
+    local.get $v.ARITY.v
+    call $add
+    local.get $v.require.t
+    local.get $v.require.v
+    call $add
+    local.get $v.BigInt.t
+    local.get $v.BigInt.v
+    call $add
+    call $print
+    local.get $v.String.t
+    local.get $v.String.v
+    i32.const 2
+    i64.const 0
+    call $add
+    call $print
+    local.get $v.Number.t
+    local.get $v.Number.v
+    local.get $v.Array.t
+    local.get $v.Array.v
+    call $add
+    local.get $v.TextDecoder.t
+    local.get $v.TextDecoder.v
+    call $add
+    local.get $v.let.t
+    local.get $v.let.v
+    call $add
+    local.get $v.undefined.t
+    local.get $v.undefined.v
+    call $add
+    local.get $v.main.t
+    local.get $v.main.v
+    call $add
+    call $print
+    local.get $v.args.t
+    local.get $v.args.v
+    call $builtin_len
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,1042 @@
+;; The touch-grass runtime, the same in every program.
+;; The host provides the imports below and calls `main`, which returns
+;; the exit code. On a runtime error it calls `error`, then traps.
+(module
+  (import "touch_grass" "write" (func $write (param i32 i32)))
+  (import "touch_grass" "error" (func $error (param i32 i32)))
+  (import "touch_grass" "arg_count" (func $arg_count (result i32)))
+  (import "touch_grass" "arg_length" (func $arg_length (param i32) (result i32)))
+  (import "touch_grass" "arg_read" (func $arg_read (param i32 i32)))
+  ;; env_length returns -1 when the variable isn't set.
+  (import "touch_grass" "env_length" (func $env_length (param i32 i32) (result i32)))
+  (import "touch_grass" "env_read" (func $env_read (param i32 i32 i32)))
+  ;; read_line returns the length of the next line, or -1 at the end of input;
+  ;; read_all the length of the rest. input_read copies what was just read.
+  (import "touch_grass" "read_line" (func $read_line (result i32)))
+  (import "touch_grass" "read_all" (func $read_all (result i32)))
+  (import "touch_grass" "input_read" (func $input_read (param i32)))
+
+  (global $exit_code (mut i32) (i32.const 0))
+  (global $message_len (mut i32) (i32.const 0))
+  (data (i32.const 1024) "Out of memorynumberstringbooleanlistnull\22falsetrue[, ]\0aInvalid arguments to : envexit_codeExit code  is out of rangelenatIndex  is out of range for a list of  itemsread_lineparse_numberCannot parse \22\22 as a numberNot allowed to  \22read_filereadwrite_filewritelist_dirfile_existsdelete_file")
+
+  ;; Tags: 0 undeclared, 1 null, 2 number, 3 string, 4 boolean, 5 list.
+
+  (func $fail (param $ptr i32) (param $len i32)
+    local.get $ptr
+    local.get $len
+    call $error
+    unreachable)
+
+  (func $alloc (param $size i32) (result i32)
+    (local $ptr i32)
+    global.get $heap
+    local.set $ptr
+    local.get $ptr
+    local.get $size
+    i32.add
+    i32.const 7
+    i32.add
+    i32.const -8
+    i32.and
+    global.set $heap
+    block $done
+      loop $grow
+        global.get $heap
+        memory.size
+        i32.const 16
+        i32.shl
+        i32.le_u
+        br_if $done
+        i32.const 1
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          i32.const 1024
+          i32.const 13
+          call $fail
+        end
+        br $grow
+      end
+    end
+    local.get $ptr)
+
+  ;; Writes the digits of n so they end at offset 32; returns where they start.
+  (func $format_number (param $n i64) (result i32)
+    (local $pos i32) (local $u i64) (local $negative i32)
+    i32.const 32
+    local.set $pos
+    local.get $n
+    i64.const 0
+    i64.lt_s
+    local.set $negative
+    local.get $n
+    local.set $u
+    local.get $negative
+    if
+      i64.const 0
+      local.get $n
+      i64.sub
+      local.set $u
+    end
+    loop $digits
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.set $pos
+      local.get $pos
+      local.get $u
+      i64.const 10
+      i64.rem_u
+      i32.wrap_i64
+      i32.const 48
+      i32.add
+      i32.store8
+      local.get $u
+      i64.const 10
+      i64.div_u
+      local.tee $u
+      i64.const 0
+      i64.ne
+      br_if $digits
+    end
+    local.get $negative
+    if
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.set $pos
+      local.get $pos
+      i32.const 45
+      i32.store8
+    end
+    local.get $pos)
+
+  ;; Runtime error messages are built at offset 32, after the digit scratch.
+  (func $message_append (param $ptr i32) (param $len i32)
+    (local $i i32)
+    block $done
+      loop $copy
+        local.get $i
+        local.get $len
+        i32.ge_u
+        br_if $done
+        global.get $message_len
+        i32.const 992
+        i32.ge_u
+        br_if $done
+        global.get $message_len
+        i32.const 32
+        i32.add
+        local.get $ptr
+        local.get $i
+        i32.add
+        i32.load8_u
+        i32.store8
+        global.get $message_len
+        i32.const 1
+        i32.add
+        global.set $message_len
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $copy
+      end
+    end)
+
+  (func $message_number (param $n i64)
+    (local $ptr i32)
+    local.get $n
+    call $format_number
+    local.set $ptr
+    local.get $ptr
+    i32.const 32
+    local.get $ptr
+    i32.sub
+    call $message_append)
+
+  (func $message_type (param $tag i32)
+    local.get $tag
+    i32.const 2
+    i32.eq
+    if
+      i32.const 1037
+      i32.const 6
+      call $message_append
+      return
+    end
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      i32.const 1043
+      i32.const 6
+      call $message_append
+      return
+    end
+    local.get $tag
+    i32.const 4
+    i32.eq
+    if
+      i32.const 1049
+      i32.const 7
+      call $message_append
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      i32.const 1056
+      i32.const 4
+      call $message_append
+      return
+    end
+    i32.const 1060
+    i32.const 4
+    call $message_append)
+
+  (func $message_fail
+    i32.const 32
+    global.get $message_len
+    call $fail)
+
+  (func $write_number (param $n i64)
+    (local $ptr i32)
+    local.get $n
+    call $format_number
+    local.set $ptr
+    local.get $ptr
+    i32.const 32
+    local.get $ptr
+    i32.sub
+    call $write)
+
+  (func $write_string (param $v i64)
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.get $v
+    i32.wrap_i64
+    call $write)
+
+  ;; Same text as Value's Display: strings inside lists are quoted.
+  (func $display (param $tag i32) (param $v i64) (param $quote i32)
+    (local $list i32) (local $i i32) (local $item i32)
+    local.get $tag
+    i32.const 2
+    i32.eq
+    if
+      local.get $v
+      call $write_number
+      return
+    end
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      local.get $quote
+      if
+        i32.const 1064
+        i32.const 1
+        call $write
+      end
+      local.get $v
+      call $write_string
+      local.get $quote
+      if
+        i32.const 1064
+        i32.const 1
+        call $write
+      end
+      return
+    end
+    local.get $tag
+    i32.const 4
+    i32.eq
+    if
+      local.get $v
+      i64.eqz
+      if
+        i32.const 1065
+        i32.const 5
+        call $write
+      else
+        i32.const 1070
+        i32.const 4
+        call $write
+      end
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      local.get $v
+      i32.wrap_i64
+      local.set $list
+      i32.const 1074
+      i32.const 1
+      call $write
+      block $done
+        loop $items
+          local.get $i
+          local.get $list
+          i32.load
+          i32.ge_u
+          br_if $done
+          local.get $i
+          if
+            i32.const 1075
+            i32.const 2
+            call $write
+          end
+          local.get $list
+          i32.const 8
+          i32.add
+          local.get $i
+          i32.const 16
+          i32.mul
+          i32.add
+          local.set $item
+          local.get $item
+          i32.load
+          local.get $item
+          i64.load offset=8
+          i32.const 1
+          call $display
+          local.get $i
+          i32.const 1
+          i32.add
+          local.set $i
+          br $items
+        end
+      end
+      i32.const 1077
+      i32.const 1
+      call $write
+      return
+    end
+    i32.const 1060
+    i32.const 4
+    call $write)
+
+  (func $print (param $tag i32) (param $v i64)
+    local.get $tag
+    local.get $v
+    i32.const 0
+    call $display
+    i32.const 1078
+    i32.const 1
+    call $write)
+
+  (func $truthy (param $tag i32) (param $v i64) (result i32)
+    local.get $tag
+    i32.const 2
+    i32.eq
+    local.get $tag
+    i32.const 4
+    i32.eq
+    i32.or
+    if
+      local.get $v
+      i64.const 0
+      i64.ne
+      return
+    end
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      local.get $v
+      i32.wrap_i64
+      i32.const 0
+      i32.ne
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      local.get $v
+      i32.wrap_i64
+      i32.load
+      i32.const 0
+      i32.ne
+      return
+    end
+    i32.const 0)
+
+  ;; Reading a variable that was never declared gives null.
+  (func $read (param $tag i32) (param $v i64) (result i32 i64)
+    local.get $tag
+    i32.eqz
+    if
+      i32.const 1
+      i64.const 0
+      return
+    end
+    local.get $tag
+    local.get $v)
+
+  ;; `set` on a variable that was never declared is an error.
+  (func $check_declared (param $tag i32) (param $ptr i32) (param $len i32)
+    local.get $tag
+    i32.eqz
+    if
+      local.get $ptr
+      local.get $len
+      call $fail
+    end)
+
+  ;; Operators only work on numbers; anything else gives null. Arithmetic wraps.
+  (func $numbers (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    i32.const 2
+    i32.eq
+    local.get $b
+    i32.const 2
+    i32.eq
+    i32.and)
+
+  (func $add (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 2
+      local.get $a
+      local.get $b
+      i64.add
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $sub (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 2
+      local.get $a
+      local.get $b
+      i64.sub
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $gt (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 4
+      local.get $a
+      local.get $b
+      i64.gt_s
+      i64.extend_i32_u
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $lt (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 4
+      local.get $a
+      local.get $b
+      i64.lt_s
+      i64.extend_i32_u
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $eq (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 4
+      local.get $a
+      local.get $b
+      i64.eq
+      i64.extend_i32_u
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $args (result i32 i64)
+    (local $count i32) (local $list i32) (local $i i32) (local $len i32) (local $ptr i32) (local $item i32)
+    call $arg_count
+    local.set $count
+    local.get $count
+    i32.const 16
+    i32.mul
+    i32.const 8
+    i32.add
+    call $alloc
+    local.set $list
+    local.get $list
+    local.get $count
+    i32.store
+    block $done
+      loop $each
+        local.get $i
+        local.get $count
+        i32.ge_u
+        br_if $done
+        local.get $i
+        call $arg_length
+        local.set $len
+        local.get $len
+        call $alloc
+        local.set $ptr
+        local.get $i
+        local.get $ptr
+        call $arg_read
+        local.get $list
+        i32.const 8
+        i32.add
+        local.get $i
+        i32.const 16
+        i32.mul
+        i32.add
+        local.set $item
+        local.get $item
+        i32.const 3
+        i32.store
+        local.get $item
+        local.get $ptr
+        i64.extend_i32_u
+        i64.const 32
+        i64.shl
+        local.get $len
+        i64.extend_i32_u
+        i64.or
+        i64.store offset=8
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $each
+      end
+    end
+    i32.const 5
+    local.get $list
+    i64.extend_i32_u)
+
+  (func $utf8_length (param $v i64) (result i64)
+    (local $ptr i32) (local $end i32) (local $count i64)
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.set $ptr
+    local.get $ptr
+    local.get $v
+    i32.wrap_i64
+    i32.add
+    local.set $end
+    block $done
+      loop $bytes
+        local.get $ptr
+        local.get $end
+        i32.ge_u
+        br_if $done
+        local.get $ptr
+        i32.load8_u
+        i32.const 192
+        i32.and
+        i32.const 128
+        i32.ne
+        if
+          local.get $count
+          i64.const 1
+          i64.add
+          local.set $count
+        end
+        local.get $ptr
+        i32.const 1
+        i32.add
+        local.set $ptr
+        br $bytes
+      end
+    end
+    local.get $count)
+
+  (func $invalid_arguments (param $ptr i32) (param $len i32) (param $tag i32)
+    i32.const 0
+    global.set $message_len
+    i32.const 1079
+    i32.const 21
+    call $message_append
+    local.get $ptr
+    local.get $len
+    call $message_append
+    i32.const 1100
+    i32.const 2
+    call $message_append
+    local.get $tag
+    call $message_type)
+
+  (func $builtin_env (param $tag i32) (param $v i64) (result i32 i64)
+    (local $key i32) (local $key_len i32) (local $len i32) (local $ptr i32)
+    local.get $tag
+    i32.const 3
+    i32.ne
+    if
+      i32.const 1102
+      i32.const 3
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.set $key
+    local.get $v
+    i32.wrap_i64
+    local.set $key_len
+    local.get $key
+    local.get $key_len
+    call $env_length
+    local.set $len
+    local.get $len
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      i64.const 0
+      return
+    end
+    local.get $len
+    call $alloc
+    local.set $ptr
+    local.get $key
+    local.get $key_len
+    local.get $ptr
+    call $env_read
+    i32.const 3
+    local.get $ptr
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $len
+    i64.extend_i32_u
+    i64.or)
+
+  (func $builtin_exit_code (param $tag i32) (param $v i64) (result i32 i64)
+    local.get $tag
+    i32.const 2
+    i32.ne
+    if
+      i32.const 1105
+      i32.const 9
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    ;; Codes that don't survive the round trip through i32 are out of range.
+    local.get $v
+    local.get $v
+    i32.wrap_i64
+    i64.extend_i32_s
+    i64.ne
+    if
+      i32.const 0
+      global.set $message_len
+      i32.const 1114
+      i32.const 10
+      call $message_append
+      local.get $v
+      call $message_number
+      i32.const 1124
+      i32.const 16
+      call $message_append
+      call $message_fail
+    end
+    local.get $v
+    i32.wrap_i64
+    global.set $exit_code
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_len (param $tag i32) (param $v i64) (result i32 i64)
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      i32.const 2
+      local.get $v
+      call $utf8_length
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      i32.const 2
+      local.get $v
+      i32.wrap_i64
+      i64.load32_u
+      return
+    end
+    i32.const 1140
+    i32.const 3
+    local.get $tag
+    call $invalid_arguments
+    call $message_fail
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_at (param $list_tag i32) (param $list i64) (param $index_tag i32) (param $index i64) (result i32 i64)
+    (local $len i64) (local $item i32)
+    local.get $list_tag
+    i32.const 5
+    i32.ne
+    local.get $index_tag
+    i32.const 2
+    i32.ne
+    i32.or
+    if
+      i32.const 1143
+      i32.const 2
+      local.get $list_tag
+      call $invalid_arguments
+      i32.const 1075
+      i32.const 2
+      call $message_append
+      local.get $index_tag
+      call $message_type
+      call $message_fail
+    end
+    local.get $list
+    i32.wrap_i64
+    i64.load32_u
+    local.set $len
+    ;; Unsigned, so negative indexes are out of range too.
+    local.get $index
+    local.get $len
+    i64.ge_u
+    if
+      i32.const 0
+      global.set $message_len
+      i32.const 1145
+      i32.const 6
+      call $message_append
+      local.get $index
+      call $message_number
+      i32.const 1151
+      i32.const 31
+      call $message_append
+      local.get $len
+      call $message_number
+      i32.const 1182
+      i32.const 6
+      call $message_append
+      call $message_fail
+    end
+    local.get $list
+    i32.wrap_i64
+    i32.const 8
+    i32.add
+    local.get $index
+    i32.wrap_i64
+    i32.const 16
+    i32.mul
+    i32.add
+    local.set $item
+    local.get $item
+    i32.load
+    local.get $item
+    i64.load offset=8)
+
+  ;; Copies the text the host just read into a new string.
+  (func $input_string (param $len i32) (result i32 i64)
+    (local $ptr i32)
+    local.get $len
+    call $alloc
+    local.set $ptr
+    local.get $ptr
+    call $input_read
+    i32.const 3
+    local.get $ptr
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $len
+    i64.extend_i32_u
+    i64.or)
+
+  ;; The undeclared tag means no prompt was passed.
+  (func $builtin_read_line (param $tag i32) (param $v i64) (result i32 i64)
+    (local $len i32)
+    local.get $tag
+    if
+      local.get $tag
+      i32.const 3
+      i32.ne
+      if
+        i32.const 1188
+        i32.const 9
+        local.get $tag
+        call $invalid_arguments
+        call $message_fail
+      end
+      local.get $v
+      i64.const 32
+      i64.shr_u
+      i32.wrap_i64
+      local.get $v
+      i32.wrap_i64
+      call $write
+    end
+    call $read_line
+    local.set $len
+    local.get $len
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      i64.const 0
+      return
+    end
+    local.get $len
+    call $input_string)
+
+  (func $builtin_read_all (result i32 i64)
+    call $read_all
+    call $input_string)
+
+  ;; Accumulates the digits as a negative number, which has room for i64 min.
+  (func $builtin_parse_number (param $tag i32) (param $v i64) (result i32 i64)
+    (local $ptr i32) (local $end i32) (local $negative i32) (local $byte i32) (local $digit i64) (local $n i64)
+    local.get $tag
+    i32.const 3
+    i32.ne
+    if
+      i32.const 1197
+      i32.const 12
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.tee $ptr
+    local.get $v
+    i32.wrap_i64
+    i32.add
+    local.set $end
+    block $invalid
+      local.get $ptr
+      local.get $end
+      i32.lt_u
+      if
+        local.get $ptr
+        i32.load8_u
+        i32.const 45
+        i32.eq
+        local.set $negative
+      end
+      local.get $ptr
+      local.get $negative
+      i32.add
+      local.tee $ptr
+      local.get $end
+      i32.eq
+      br_if $invalid
+      loop $digits
+        local.get $ptr
+        i32.load8_u
+        i32.const 48
+        i32.sub
+        local.tee $byte
+        i32.const 9
+        i32.gt_u
+        br_if $invalid
+        local.get $byte
+        i64.extend_i32_u
+        local.set $digit
+        ;; n * 10 - digit would go below i64 min.
+        local.get $n
+        i64.const -9223372036854775808
+        local.get $digit
+        i64.add
+        i64.const 10
+        i64.div_s
+        i64.lt_s
+        br_if $invalid
+        local.get $n
+        i64.const 10
+        i64.mul
+        local.get $digit
+        i64.sub
+        local.set $n
+        local.get $ptr
+        i32.const 1
+        i32.add
+        local.tee $ptr
+        local.get $end
+        i32.lt_u
+        br_if $digits
+      end
+      local.get $negative
+      if
+        i32.const 2
+        local.get $n
+        return
+      end
+      local.get $n
+      i64.const -9223372036854775808
+      i64.eq
+      br_if $invalid
+      i32.const 2
+      i64.const 0
+      local.get $n
+      i64.sub
+      return
+    end
+    i32.const 0
+    global.set $message_len
+    i32.const 1209
+    i32.const 14
+    call $message_append
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.get $v
+    i32.wrap_i64
+    call $message_append
+    i32.const 1223
+    i32.const 13
+    call $message_append
+    call $message_fail
+    i32.const 1
+    i64.const 0)
+
+  ;; Generated modules get no filesystem access, like `run` without
+  ;; --allow-read or --allow-write.
+  (func $deny (param $name i32) (param $name_len i32) (param $access i32) (param $access_len i32) (param $tag i32) (param $v i64)
+    local.get $tag
+    i32.const 3
+    i32.ne
+    if
+      local.get $name
+      local.get $name_len
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    i32.const 0
+    global.set $message_len
+    i32.const 1236
+    i32.const 15
+    call $message_append
+    local.get $access
+    local.get $access_len
+    call $message_append
+    i32.const 1251
+    i32.const 2
+    call $message_append
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.get $v
+    i32.wrap_i64
+    call $message_append
+    i32.const 1064
+    i32.const 1
+    call $message_append
+    call $message_fail)
+
+  (func $builtin_read_file (param $tag i32) (param $v i64) (result i32 i64)
+    i32.const 1253
+    i32.const 9
+    i32.const 1262
+    i32.const 4
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_write_file (param $path_tag i32) (param $path i64) (param $text_tag i32) (param $text i64) (result i32 i64)
+    local.get $path_tag
+    i32.const 3
+    i32.ne
+    local.get $text_tag
+    i32.const 3
+    i32.ne
+    i32.or
+    if
+      i32.const 1266
+      i32.const 10
+      local.get $path_tag
+      call $invalid_arguments
+      i32.const 1075
+      i32.const 2
+      call $message_append
+      local.get $text_tag
+      call $message_type
+      call $message_fail
+    end
+    i32.const 1266
+    i32.const 10
+    i32.const 1276
+    i32.const 5
+    local.get $path_tag
+    local.get $path
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_list_dir (param $tag i32) (param $v i64) (result i32 i64)
+    i32.const 1281
+    i32.const 8
+    i32.const 1262
+    i32.const 4
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_file_exists (param $tag i32) (param $v i64) (result i32 i64)
+    i32.const 1289
+    i32.const 11
+    i32.const 1262
+    i32.const 4
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_delete_file (param $tag i32) (param $v i64) (result i32 i64)
+    i32.const 1300
+    i32.const 11
+    i32.const 1276
+    i32.const 5
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+  ;; End of the runtime.
@@ -0,0 +1,29 @@
+;; Generated by touch-grass transpile from Tests/runtime_error.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4144))
+  (data (i32.const 4096) "beforeUndefined variable: nopenever printed")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    (local $v.nope.t i32) (local $v.nope.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    ;; Assigning to an undeclared variable stops the program.
+    i32.const 3
+    i64.const 17592186044422
+    call $print
+    i32.const 2
+    i64.const 1
+    local.get $v.nope.t
+    i32.const 4102
+    i32.const 24
+    call $check_declared
+    local.set $v.nope.v
+    local.set $v.nope.t
+    i32.const 3
+    i64.const 17721035063309
+    call $print
+    global.get $exit_code)
+)
@@ -0,0 +1,16 @@
+;; Generated by touch-grass transpile from Tests/test.tg
+
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4112))
+  (data (i32.const 4096) "Hello World!")
+
+  (func $main (export "main") (result i32)
+    (local $v.args.t i32) (local $v.args.v i64)
+    call $args
+    local.set $v.args.v
+    local.set $v.args.t
+    i32.const 3
+    i64.const 17592186044428
+    call $print
+    global.get $exit_code)
+)
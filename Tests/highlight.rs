@@ -0,0 +1,72 @@
+// The highlighter's token classes, then its ANSI and HTML renderings.
+
+use touch_grass::highlight::{segments, to_ansi, to_html, Style};
+
+/// The styled segments of `source`, without the whitespace between them.
+fn styles(source: &str) -> Vec<(Style, String)> {
+    segments(source).into_iter().filter_map(|(style, text)| Some((style?, text))).collect()
+}
+
+#[test]
+fn token_classes() {
+    let source = "touch grass number x as 12 // twelve\nprint \"hi\" + no_cap - bugatti\n$";
+    assert_eq!(
+        styles(source),
+        [
+            (Style::Keyword, "touch grass".to_string()),
+            (Style::Keyword, "number".to_string()),
+            (Style::Identifier, "x".to_string()),
+            (Style::Keyword, "as".to_string()),
+            (Style::Number, "12".to_string()),
+            (Style::Comment, "// twelve".to_string()),
+            (Style::Keyword, "print".to_string()),
+            (Style::String, "\"hi\"".to_string()),
+            (Style::Operator, "+".to_string()),
+            (Style::Literal, "no_cap".to_string()),
+            (Style::Operator, "-".to_string()),
+            (Style::Literal, "bugatti".to_string()),
+            (Style::Illegal, "$".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn control_flow_keywords() {
+    let source = "go outside if x > 1 then\nprint x\nfr fr instead\nwhile cap then\nfr fr\n";
+    let keywords: Vec<String> = styles(source)
+        .into_iter()
+        .filter(|(style, _)| *style == Style::Keyword)
+        .map(|(_, text)| text)
+        .collect();
+    assert_eq!(keywords, ["go", "outside", "if", "then", "print", "fr fr", "instead", "while", "then", "fr fr"]);
+}
+
+#[test]
+fn segments_keep_the_source_layout() {
+    let source = "  touch grass number x as 1\n\n\tprint x   // done\n";
+    let joined: String = segments(source).into_iter().map(|(_, text)| text).collect();
+    assert_eq!(joined, source);
+}
+
+#[test]
+fn ansi_colors_styled_tokens_only() {
+    assert_eq!(
+        to_ansi("print x + 1"),
+        "\x1b[1;35mprint\x1b[0m x \x1b[36m+\x1b[0m \x1b[33m1\x1b[0m"
+    );
+}
+
+#[test]
+fn html_escapes_source_and_title() {
+    let html = to_html("print \"<b>&\" < 1", "a<b> & \"c\"");
+    assert!(html.contains("<title>a&lt;b&gt; &amp; &quot;c&quot;</title>"), "{}", html);
+    assert!(
+        html.contains(
+            "<pre><code><span class=\"kw\">print</span> \
+             <span class=\"str\">&quot;&lt;b&gt;&amp;&quot;</span> \
+             <span class=\"op\">&lt;</span> <span class=\"num\">1</span></code></pre>"
+        ),
+        "{}",
+        html
+    );
+}
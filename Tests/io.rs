@@ -0,0 +1,89 @@
+// What programs read and print, checked in process with canned input.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use touch_grass::console::{Buffer, Input, Text};
+use touch_grass::{Engine, Value};
+
+/// Runs `source` with `input` as its stdin and returns what it printed.
+fn run(source: &str, input: &str) -> String {
+    let output = Buffer::new();
+    let mut engine = Engine::new();
+    engine.set_output(output.clone());
+    engine.set_input(Text::new(input));
+    engine.run(source).unwrap();
+    output.contents()
+}
+
+#[test]
+fn text_splits_lines() {
+    let mut input = Text::new("one\r\ntwo\nthree");
+    assert_eq!(input.read_line().as_deref(), Some("one"));
+    assert_eq!(input.read_line().as_deref(), Some("two"));
+    assert_eq!(input.read_all(), "three");
+    assert_eq!(input.read_line(), None);
+    assert_eq!(input.read_all(), "");
+}
+
+#[test]
+fn read_line_shows_the_prompt_on_the_output() {
+    let printed = run("touch grass number name as read_line(\"name? \")\nprint name", "Sam\n");
+    assert_eq!(printed, "name? Sam\n");
+}
+
+#[test]
+fn read_line_is_null_at_the_end() {
+    assert_eq!(run("print read_line()\nprint read_line()", "last"), "last\nnull\n");
+    assert_eq!(run("print read_line()", ""), "null\n");
+}
+
+#[test]
+fn read_all_takes_the_rest() {
+    assert_eq!(run("print read_line()\nprint read_all()", "a\nb\nc\n"), "a\nb\nc\n\n");
+}
+
+#[test]
+fn parse_number_reads_input() {
+    let source = "print parse_number(read_line()) + parse_number(read_line()) + parse_number(read_line())";
+    assert_eq!(run(source, "1\n-2\n40\n"), "39\n");
+
+    let mut engine = Engine::new();
+    engine.set_input(Text::new("twelve\n"));
+    let error = engine.eval("parse_number(read_line())").unwrap_err();
+    assert!(error.to_string().contains("Cannot parse \"twelve\" as a number"), "{}", error);
+    assert_eq!(engine.eval("parse_number(\"-7\")").unwrap(), Value::Number(-7));
+}
+
+/// `touch-grass debug` reads its commands from stdin, so the program has to
+/// get its input from somewhere else.
+#[test]
+fn debugger_commands_are_not_program_input() {
+    let dir = std::env::temp_dir();
+    let program = dir.join(format!("touch-grass-io-{}.tg", std::process::id()));
+    let input = dir.join(format!("touch-grass-io-{}.txt", std::process::id()));
+    std::fs::write(&program, "print read_line()\nprint read_line()\n").unwrap();
+    std::fs::write(&input, "from the file\n").unwrap();
+
+    let debug = |extra: &[&str]| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_touch-grass"))
+            .arg("debug")
+            .args(extra)
+            .arg(&program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(b"continue\nnot for the program\n").unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let with_input = debug(&[&format!("--input={}", input.display())]);
+    assert!(with_input.contains("from the file\nnull\nProgram finished."), "{}", with_input);
+    let without = debug(&[]);
+    assert!(without.contains("null\nnull\nProgram finished."), "{}", without);
+
+    let _ = std::fs::remove_file(&program);
+    let _ = std::fs::remove_file(&input);
+}
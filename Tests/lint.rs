@@ -0,0 +1,170 @@
+// Each lint against a program that should trip it and one that shouldn't,
+// then the command line's -A/-D/--config handling.
+
+use std::process::{Command, Output};
+use touch_grass::lexer::tokenize_with_lines;
+use touch_grass::lint::{lint, Config, Level};
+use touch_grass::parser::Parser;
+
+/// The lints `source` trips with the default config, as `(name, line)`.
+fn lints(source: &str) -> Vec<(&'static str, usize)> {
+    let mut parser = Parser::with_lines(tokenize_with_lines(source));
+    let program = parser.parse();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+    lint(&program, &Config::new()).into_iter().map(|d| (d.lint, d.line)).collect()
+}
+
+fn trips(source: &str, name: &str) -> bool {
+    lints(source).iter().any(|(lint, _)| *lint == name)
+}
+
+#[test]
+fn unused_variable() {
+    assert_eq!(lints("touch grass number x as 1\n"), [("unused-variable", 1)]);
+    assert!(!trips("touch grass number x as 1\nprint x\n", "unused-variable"));
+}
+
+#[test]
+fn undeclared_assignment() {
+    assert_eq!(lints("set y to 2\n"), [("undeclared-assignment", 1)]);
+    assert!(!trips("touch grass number y as 1\nset y to 2\nprint y\n", "undeclared-assignment"));
+}
+
+#[test]
+fn undeclared_read() {
+    assert_eq!(lints("print z\n"), [("undeclared-read", 1)]);
+    assert!(!trips("touch grass number z as 1\nprint z\n", "undeclared-read"));
+}
+
+#[test]
+fn infinite_loop() {
+    assert_eq!(lints("while no_cap then\nprint 1\nfr fr\n"), [("infinite-loop", 1)]);
+    let unchanged = "touch grass number n as 1\nwhile n > 0 then\nprint n\nfr fr\n";
+    assert_eq!(lints(unchanged), [("infinite-loop", 2)]);
+
+    let counts_down = "touch grass number n as 3\nwhile n > 0 then\nset n to n - 1\nfr fr\n";
+    assert!(!trips(counts_down, "infinite-loop"));
+    // A call can return something different every time round.
+    let reads = "touch grass number n as 3\nwhile n > len(read_all()) then\nprint n\nfr fr\n";
+    assert!(!trips(reads, "infinite-loop"));
+    assert!(!trips("while len(read_line()) > 0 then\nprint 1\nfr fr\n", "infinite-loop"));
+}
+
+#[test]
+fn self_assignment() {
+    let source = "touch grass number x as 1\nset x to x\nprint x\n";
+    assert_eq!(lints(source), [("self-assignment", 2)]);
+    assert!(!trips("touch grass number x as 1\nset x to x + 1\nprint x\n", "self-assignment"));
+}
+
+#[test]
+fn empty_block() {
+    assert_eq!(lints("go outside if no_cap then\nfr fr\n"), [("empty-block", 1)]);
+    assert!(!trips("go outside if no_cap then\nprint 1\nfr fr\n", "empty-block"));
+}
+
+#[test]
+fn config_sets_levels() {
+    let mut config = Config::new();
+    assert_eq!(config.level("unused-variable"), Level::Warn);
+    config.set("unused-variable", Level::Deny).unwrap();
+    assert_eq!(config.level("unused-variable"), Level::Deny);
+    assert!(config.set("no-such-lint", Level::Allow).unwrap_err().contains("no-such-lint"));
+
+    config.load("# quiet please\nunused-variable = allow\n\nempty-block=deny # loudly\n").unwrap();
+    assert_eq!(config.level("unused-variable"), Level::Allow);
+    assert_eq!(config.level("empty-block"), Level::Deny);
+}
+
+#[test]
+fn config_errors_name_the_line() {
+    let mut config = Config::new();
+    assert!(config.load("unused-variable\n").unwrap_err().starts_with("line 1:"));
+    assert!(config.load("\nunused-variable = loud\n").unwrap_err().starts_with("line 2:"));
+    assert!(config.load("no-such-lint = warn\n").unwrap_err().contains("Unknown lint"));
+}
+
+/// A file in the temp dir that's removed when dropped.
+struct TempFile(std::path::PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("touch-grass-lint-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn run_lint(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_touch-grass")).arg("lint").args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn command_line_levels() {
+    let program = TempFile::new("levels.tg", "touch grass number x as 1\n");
+
+    let output = run_lint(&[program.path()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("line 1: warning: "), "{}", stdout(&output));
+    assert!(stdout(&output).contains("[unused-variable]"));
+
+    let output = run_lint(&["-D", "unused-variable", program.path()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("line 1: error: "), "{}", stdout(&output));
+
+    let output = run_lint(&["-A", "unused-variable", program.path()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    // The last flag for a lint wins.
+    let output = run_lint(&["-D", "unused-variable", "-A", "unused-variable", program.path()]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run_lint(&["-D", "no-such-lint", program.path()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Unknown lint: no-such-lint"), "{}", stderr(&output));
+}
+
+#[test]
+fn command_line_config() {
+    let program = TempFile::new("config.tg", "touch grass number x as 1\nset y to 2\n");
+    let config = TempFile::new("quiet.conf", "unused-variable = allow\nundeclared-assignment = warn\n");
+
+    let output = run_lint(&["--config", config.path(), program.path()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!stdout(&output).contains("[unused-variable]"), "{}", stdout(&output));
+    assert!(stdout(&output).contains("line 2: warning: "), "{}", stdout(&output));
+
+    // Flags after --config override it.
+    let output = run_lint(&["--config", config.path(), "-D", "unused-variable", program.path()]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let bad = TempFile::new("bad.conf", "unused-variable = loud\n");
+    let output = run_lint(&["--config", bad.path(), program.path()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains(&format!("{}: line 1:", bad.path())), "{}", stderr(&output));
+
+    let missing = format!("{}.missing", config.path());
+    let output = run_lint(&["--config", &missing, program.path()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains(&format!("Cannot read config file {}", missing)), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+}
@@ -0,0 +1,229 @@
+// Drives the language server through a scripted session over in-memory
+// streams, the same way an editor would over stdio.
+
+use std::io::Cursor;
+use touch_grass::json::Json;
+use touch_grass::lsp::{read_message, write_message, Server};
+
+const URI: &str = "file:///test.tg";
+const SOURCE: &str = "touch grass number x as 1\nset x to \"grass\"\nprint len(args)\n";
+
+fn frame(body: &str) -> Vec<u8> {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes()
+}
+
+fn request(id: i64, method: &str, params: Json) -> Vec<u8> {
+    let mut out = Vec::new();
+    let message = Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ]);
+    write_message(&mut out, &message).unwrap();
+    out
+}
+
+fn notification(method: &str, params: Json) -> Vec<u8> {
+    let mut out = Vec::new();
+    let message = Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ]);
+    write_message(&mut out, &message).unwrap();
+    out
+}
+
+fn position(line: usize, character: usize) -> Json {
+    Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", URI.into())])),
+        ("position", Json::object(vec![("line", line.into()), ("character", character.into())])),
+    ])
+}
+
+fn document() -> Json {
+    Json::object(vec![("textDocument", Json::object(vec![("uri", URI.into())]))])
+}
+
+/// Runs the server over `input` and returns whether it shut down cleanly and
+/// every message it wrote.
+fn serve(input: Vec<u8>) -> (bool, Vec<Json>) {
+    let mut output = Vec::new();
+    let clean = Server::new().serve(Cursor::new(input), &mut output).unwrap();
+    let mut reader = Cursor::new(output);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    (clean, messages)
+}
+
+fn reply(messages: &[Json], id: i64) -> &Json {
+    messages
+        .iter()
+        .find(|m| m.get("id").and_then(Json::as_i64) == Some(id) && m.get("method").is_none())
+        .unwrap_or_else(|| panic!("no reply to request {}", id))
+}
+
+fn result(messages: &[Json], id: i64) -> &Json {
+    reply(messages, id).get("result").unwrap_or_else(|| panic!("request {} failed", id))
+}
+
+#[test]
+fn scripted_session() {
+    let mut input = Vec::new();
+    input.extend(request(1, "initialize", Json::object(Vec::<(&str, Json)>::new())));
+    input.extend(notification("textDocument/didOpen", Json::object(vec![
+        ("textDocument", Json::object(vec![
+            ("uri", URI.into()),
+            ("languageId", "touch-grass".into()),
+            ("version", 1i64.into()),
+            ("text", SOURCE.into()),
+        ])),
+    ])));
+    input.extend(request(2, "textDocument/hover", position(1, 4)));
+    input.extend(request(3, "textDocument/definition", position(1, 4)));
+    input.extend(request(4, "textDocument/completion", position(2, 0)));
+    input.extend(request(5, "textDocument/documentSymbol", document()));
+    input.extend(notification("textDocument/didChange", Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", URI.into()), ("version", 2i64.into())])),
+        ("contentChanges", Json::Array(vec![Json::object(vec![("text", "print   1\n".into())])])),
+    ])));
+    input.extend(request(6, "textDocument/formatting", document()));
+    input.extend(request(7, "shutdown", Json::Null));
+    input.extend(notification("exit", Json::Null));
+
+    let (clean, messages) = serve(input);
+    assert!(clean);
+
+    let capabilities = result(&messages, 1).get("capabilities").unwrap();
+    assert_eq!(capabilities.get("hoverProvider"), Some(&Json::Bool(true)));
+
+    // Giving the number x a string is reported on its line.
+    let diagnostics: Vec<&Json> = messages
+        .iter()
+        .filter(|m| m.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics"))
+        .collect();
+    assert_eq!(diagnostics.len(), 2);
+    let opened = diagnostics[0].at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+    assert!(!opened.is_empty());
+    assert_eq!(opened[0].at(&["range", "start", "line"]).and_then(Json::as_i64), Some(1));
+    let changed = diagnostics[1].at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+    assert!(changed.is_empty());
+
+    let hover = result(&messages, 2).at(&["contents", "value"]).and_then(Json::as_str).unwrap();
+    assert!(hover.contains("touch grass number x"), "{}", hover);
+
+    let definition = result(&messages, 3);
+    assert_eq!(definition.get("uri").and_then(Json::as_str), Some(URI));
+    assert_eq!(definition.at(&["range", "start", "line"]).and_then(Json::as_i64), Some(0));
+    assert_eq!(definition.at(&["range", "start", "character"]).and_then(Json::as_i64), Some(19));
+
+    let labels: Vec<&str> = result(&messages, 4)
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item.get("label").and_then(Json::as_str))
+        .collect();
+    for label in ["print", "x", "args", "len"] {
+        assert!(labels.contains(&label), "missing completion {}", label);
+    }
+
+    let symbols = result(&messages, 5).as_array().unwrap();
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].get("name").and_then(Json::as_str), Some("x"));
+
+    let edits = result(&messages, 6).as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].get("newText").and_then(Json::as_str), Some("print 1\n"));
+
+    assert_eq!(result(&messages, 7), &Json::Null);
+}
+
+#[test]
+fn unknown_request_is_method_not_found() {
+    let mut input = request(1, "workspace/symbol", Json::Null);
+    input.extend(notification("exit", Json::Null));
+
+    let (clean, messages) = serve(input);
+    assert!(!clean);
+    assert_eq!(reply(&messages, 1).at(&["error", "code"]).and_then(Json::as_i64), Some(-32601));
+}
+
+#[test]
+fn malformed_messages_are_parse_errors() {
+    let mut input = Vec::new();
+    for body in [
+        r#"{"a":"\ud800\u0041"}"#, // high surrogate followed by a non-surrogate
+        r#"{"a":"\ud800"}"#,       // high surrogate on its own
+        r#"{"a":"\udc00"}"#,       // low surrogate on its own
+        r#"{"a":"\ud800\n"}"#,     // high surrogate followed by another escape
+        r#"{"a":"\u12"}"#,         // short escape
+        r#"{"id":1,"#,             // truncated
+    ] {
+        input.extend(frame(body));
+    }
+    // The server keeps going after the bad messages.
+    input.extend(request(1, "initialize", Json::object(Vec::<(&str, Json)>::new())));
+    input.extend(request(2, "shutdown", Json::Null));
+    input.extend(notification("exit", Json::Null));
+
+    let (clean, messages) = serve(input);
+    assert!(clean);
+    let errors: Vec<&Json> = messages.iter().filter(|m| m.get("error").is_some()).collect();
+    assert_eq!(errors.len(), 6);
+    for error in errors {
+        assert_eq!(error.get("id"), Some(&Json::Null));
+        assert_eq!(error.at(&["error", "code"]).and_then(Json::as_i64), Some(-32700));
+    }
+    assert!(result(&messages, 1).get("capabilities").is_some());
+}
+
+#[test]
+fn surrogate_pairs_decode() {
+    let json = Json::parse(r#""\ud83c\udf31 grass""#).unwrap();
+    assert_eq!(json.as_str(), Some("\u{1f331} grass"));
+}
+
+#[test]
+fn columns_count_utf16_code_units() {
+    // The emoji is one char but two UTF-16 code units, which is what editors send.
+    let source = "print \"\u{1f331}\" touch grass number total as 1\nset total to \"\u{1f331}\"\n";
+    let mut input = Vec::new();
+    input.extend(notification("textDocument/didOpen", Json::object(vec![
+        ("textDocument", Json::object(vec![
+            ("uri", URI.into()),
+            ("languageId", "touch-grass".into()),
+            ("version", 1i64.into()),
+            ("text", source.into()),
+        ])),
+    ])));
+    input.extend(request(1, "textDocument/documentSymbol", document()));
+    input.extend(request(2, "textDocument/hover", position(0, 35)));
+    input.extend(request(3, "shutdown", Json::Null));
+    input.extend(notification("exit", Json::Null));
+
+    let (clean, messages) = serve(input);
+    assert!(clean);
+
+    let symbols = result(&messages, 1).as_array().unwrap();
+    assert_eq!(symbols.len(), 1);
+    let range = symbols[0].get("range").unwrap();
+    assert_eq!(range.at(&["start", "character"]).and_then(Json::as_i64), Some(30));
+    assert_eq!(range.at(&["end", "character"]).and_then(Json::as_i64), Some(35));
+
+    // The end of `total` in code units, which counting chars would put past it.
+    let hover = result(&messages, 2).at(&["contents", "value"]).and_then(Json::as_str).unwrap();
+    assert!(hover.contains("touch grass number total"), "{}", hover);
+
+    let diagnostics = messages
+        .iter()
+        .find(|m| m.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics"))
+        .and_then(|m| m.at(&["params", "diagnostics"]))
+        .and_then(Json::as_array)
+        .unwrap();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics[0].at(&["range", "start", "line"]).and_then(Json::as_i64), Some(1));
+    assert_eq!(diagnostics[0].at(&["range", "end", "character"]).and_then(Json::as_i64), Some(17));
+}
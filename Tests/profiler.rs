@@ -0,0 +1,105 @@
+// What the profiler reports for a small loop: how often each line ran, and
+// folded stacks in the `frame;frame count` form flamegraph tools read.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use touch_grass::console::Buffer;
+use touch_grass::interpreter::Interpreter;
+use touch_grass::lexer::tokenize_with_lines;
+use touch_grass::parser::Parser;
+use touch_grass::profiler::Profiler;
+
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+const LOOP: &str = "touch grass number i as 0
+while i < 3 then
+    set i to i + 1
+fr fr
+print i
+";
+
+/// Profiles `source` and returns the report and the folded stacks.
+fn profile(source: &str, name: &str) -> (String, String) {
+    let folded = std::env::temp_dir().join(format!("touch-grass-profile-{}-{}.folded", std::process::id(), name));
+    let program = Parser::with_lines(tokenize_with_lines(source)).parse();
+    let report = Shared::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(Buffer::new()));
+    interpreter.set_hook(Box::new(Profiler::new(Box::new(report.clone()), Some(folded.to_str().unwrap().to_string()))));
+    let _ = interpreter.interpret(&program);
+    let folded_text = std::fs::read_to_string(&folded).unwrap();
+    let _ = std::fs::remove_file(&folded);
+    let report_text = String::from_utf8(report.0.borrow().clone()).unwrap();
+    (report_text, folded_text)
+}
+
+/// The report's rows as `(line, count, code)`, in the order they're listed.
+fn rows(report: &str) -> Vec<(usize, u64, String)> {
+    report
+        .lines()
+        .skip(2)
+        .map(|row| {
+            let fields: Vec<&str> = row.split_whitespace().collect();
+            let code = row.split_once("%  ").unwrap().1.to_string();
+            (fields[0].parse().unwrap(), fields[1].parse().unwrap(), code)
+        })
+        .collect()
+}
+
+/// The folded stacks without their times, checking each line's format.
+fn stacks(folded: &str) -> Vec<String> {
+    folded
+        .lines()
+        .map(|line| {
+            let (stack, time) = line.rsplit_once(' ').unwrap_or_else(|| panic!("no count in {:?}", line));
+            time.parse::<u128>().unwrap_or_else(|_| panic!("bad count in {:?}", line));
+            assert!(stack.split(';').all(|frame| !frame.is_empty()), "empty frame in {:?}", line);
+            stack.to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn counts_each_line() {
+    let (report, _) = profile(LOOP, "counts");
+    assert!(report.starts_with("Profile: 6 statement(s) ran in "), "{}", report);
+    let header: Vec<&str> = report.lines().nth(1).unwrap().split_whitespace().collect();
+    assert_eq!(header, ["line", "count", "total", "self", "self%", "code"]);
+
+    let mut rows = rows(&report);
+    rows.sort();
+    assert_eq!(rows, [
+        (1, 1, "touch grass number i as 0".to_string()),
+        (2, 1, "while i < 3 then".to_string()),
+        (3, 3, "set i to i + 1".to_string()),
+        (5, 1, "print i".to_string()),
+    ]);
+}
+
+#[test]
+fn folded_stacks() {
+    let (_, folded) = profile(LOOP, "folded");
+    assert_eq!(stacks(&folded), ["main;line 1", "main;line 2", "main;line 2;line 3", "main;line 5"]);
+}
+
+/// Statements still running when the program fails are counted up to then.
+#[test]
+fn errors_still_report() {
+    let (report, folded) = profile("go outside if 1 then\n    set nope to 1\nfr fr\n", "error");
+    let mut rows = rows(&report);
+    rows.sort();
+    assert_eq!(rows, [(1, 1, "go outside if 1 then".to_string()), (2, 1, "set nope to 1".to_string())]);
+    assert_eq!(stacks(&folded), ["main;line 1", "main;line 1;line 2"]);
+}
@@ -0,0 +1,153 @@
+// Checks `run`'s sandbox options: filesystem builtins only reach granted
+// directories, and each limit stops a runaway program with its own error, on
+// either engine.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A scratch directory holding `box/`, which tests grant access to, and
+/// `outside.txt` next to it, which they shouldn't reach.
+struct Sandbox(PathBuf);
+
+impl Sandbox {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("touch-grass-sandbox-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("box")).unwrap();
+        std::fs::write(dir.join("outside.txt"), "secret\n").unwrap();
+        Sandbox(dir)
+    }
+
+    /// Runs `program` from inside the sandbox and returns what it printed to
+    /// stdout and stderr, without the final newline.
+    fn run(&self, program: &str, options: &[&str]) -> String {
+        std::fs::write(self.0.join("program.tg"), program).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_touch-grass"))
+            .current_dir(&self.0)
+            .args(["run", "-q"])
+            .args(options)
+            .arg("program.tg")
+            .output()
+            .unwrap();
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        text.trim_end_matches('\n').to_string()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+const LOOP: &str = "touch grass number i as 0
+while no_cap then
+    set i to i + 1
+fr fr
+";
+
+const GROWING: &str = "touch grass number s as \"touch grass\"
+while no_cap then
+    set s to s
+fr fr
+";
+
+const NESTED: &str = "go outside if 1 then
+    go outside if 1 then
+        print 1
+    fr fr
+fr fr
+";
+
+#[test]
+fn reading_is_denied_by_default() {
+    let sandbox = Sandbox::new("read-denied");
+    assert_eq!(sandbox.run("print list_dir(\"box\")", &[]), "Runtime error: Not allowed to read \"box\"");
+}
+
+#[test]
+fn writing_needs_allow_write() {
+    let sandbox = Sandbox::new("write-denied");
+    assert_eq!(
+        sandbox.run("write_file(\"box/a.txt\", \"hi\")", &["--allow-read=box"]),
+        "Runtime error: Not allowed to write \"box/a.txt\""
+    );
+}
+
+#[test]
+fn granted_directory() {
+    let sandbox = Sandbox::new("granted");
+    let program = "print file_exists(\"box/a.txt\")
+write_file(\"box/a.txt\", \"hi\")
+print file_exists(\"box/a.txt\")
+print read_file(\"box/a.txt\")
+print list_dir(\"box\")
+delete_file(\"box/a.txt\")
+print file_exists(\"box/a.txt\")
+";
+    assert_eq!(
+        sandbox.run(program, &["--allow-read=box", "--allow-write=box"]),
+        "false\ntrue\nhi\n[\"a.txt\"]\nfalse"
+    );
+}
+
+#[test]
+fn dot_dot_cant_leave_a_granted_directory() {
+    let sandbox = Sandbox::new("dot-dot");
+    assert_eq!(
+        sandbox.run("print read_file(\"box/../outside.txt\")", &["--allow-read=box"]),
+        "Runtime error: Not allowed to read \"box/../outside.txt\""
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_cant_leave_a_granted_directory() {
+    let sandbox = Sandbox::new("symlink");
+    std::os::unix::fs::symlink("..", sandbox.0.join("box/up")).unwrap();
+    assert_eq!(
+        sandbox.run("print read_file(\"box/up/outside.txt\")", &["--allow-read=box"]),
+        "Runtime error: Not allowed to read \"box/up/outside.txt\""
+    );
+}
+
+#[test]
+fn limits_on_the_tree_engine() {
+    let sandbox = Sandbox::new("tree-limits");
+    assert_eq!(sandbox.run(LOOP, &["--max-steps=1000"]), "Runtime error: Step limit exceeded: ran more than 1000 steps");
+    assert_eq!(sandbox.run(LOOP, &["--max-time=100"]), "Runtime error: Time limit exceeded: ran longer than 100 ms");
+    assert_eq!(
+        sandbox.run(NESTED, &["--max-depth=4"]),
+        "Runtime error: Depth limit exceeded: nested more than 4 levels deep"
+    );
+    // The optimizer inlines both branches, so there's nothing left to nest.
+    assert_eq!(sandbox.run(NESTED, &["--max-depth=4", "-O"]), "1");
+    assert_eq!(
+        sandbox.run(GROWING, &["--max-memory=1000"]),
+        "Runtime error: Memory limit exceeded: allocated more than 1000 bytes"
+    );
+    let options = ["--max-steps=100", "--max-time=1000", "--max-depth=10", "--max-memory=100"];
+    assert_eq!(sandbox.run("print 1 + 2", &options), "3");
+}
+
+#[test]
+fn limits_on_the_vm() {
+    let sandbox = Sandbox::new("vm-limits");
+    assert_eq!(
+        sandbox.run(LOOP, &["--engine=vm", "--max-steps=1000"]),
+        "Runtime error: Step limit exceeded: ran more than 1000 steps"
+    );
+    assert_eq!(
+        sandbox.run(LOOP, &["--engine=vm", "--max-time=100"]),
+        "Runtime error: Time limit exceeded: ran longer than 100 ms"
+    );
+    assert_eq!(
+        sandbox.run(GROWING, &["--engine=vm", "--max-memory=1000"]),
+        "Runtime error: Memory limit exceeded: allocated more than 1000 bytes"
+    );
+    assert_eq!(
+        sandbox.run("print 1", &["--engine=vm", "--max-depth=10"]),
+        "--max-depth needs --engine=tree; bytecode doesn't nest"
+    );
+}
@@ -0,0 +1,150 @@
+// The .tgc loader against damaged and hand-made files. Everything it accepts
+// must be safe to run, so each of these has to be an error, not a panic.
+
+use touch_grass::bytecode::{compile, Chunk, Op};
+use touch_grass::interpreter::Value;
+use touch_grass::lexer::tokenize_with_lines;
+use touch_grass::parser::Parser;
+use touch_grass::tgc::{decode, encode, CompiledFile, FORMAT_VERSION};
+
+const SOURCE: &str = "touch grass number i as 0
+while i < 3 then
+    set i to i + 1
+    print len(\"grass\") + i
+fr fr
+";
+
+fn compiled() -> CompiledFile {
+    let program = Parser::with_lines(tokenize_with_lines(SOURCE)).parse();
+    CompiledFile { source_name: "loop.tg".to_string(), chunk: compile(&program) }
+}
+
+fn error(bytes: &[u8]) -> String {
+    decode(bytes).expect_err("the file should be rejected")
+}
+
+/// Replaces the body and fixes up the checksum, so only the body is checked.
+fn with_body(bytes: &[u8], body: &[u8]) -> Vec<u8> {
+    let checksum = body.iter().fold(0x811c9dc5u32, |hash, b| (hash ^ *b as u32).wrapping_mul(0x01000193));
+    let mut out = bytes[..6].to_vec();
+    out.extend_from_slice(&checksum.to_le_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+fn chunk(constants: Vec<Value>, slots: &[&str], code: Vec<Op>) -> Vec<u8> {
+    let lines = vec![1; code.len()];
+    let chunk = Chunk { constants, slots: slots.iter().map(|s| s.to_string()).collect(), code, lines };
+    encode(&CompiledFile { source_name: "made.tg".to_string(), chunk }).unwrap()
+}
+
+#[test]
+fn round_trip() {
+    let file = compiled();
+    assert_eq!(decode(&encode(&file).unwrap()), Ok(file));
+}
+
+#[test]
+fn truncated_files() {
+    let bytes = encode(&compiled()).unwrap();
+    for len in 0..bytes.len() {
+        decode(&bytes[..len]).expect_err(&format!("accepted the first {} bytes", len));
+    }
+    // Cutting the body short with a matching checksum still fails.
+    let body = &bytes[10..bytes.len() - 1];
+    assert_eq!(error(&with_body(&bytes, body)), "Unexpected end of file");
+}
+
+#[test]
+fn bit_flips() {
+    let bytes = encode(&compiled()).unwrap();
+    for i in 0..bytes.len() {
+        for bit in 0..8 {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 1 << bit;
+            decode(&flipped).expect_err(&format!("accepted a flip of bit {} in byte {}", bit, i));
+        }
+    }
+    let mut flipped = bytes.clone();
+    flipped[0] ^= 1;
+    assert_eq!(error(&flipped), "Not a compiled Touch Grass file");
+    let last = flipped.len() - 1;
+    let mut flipped = bytes;
+    flipped[last] ^= 1;
+    assert_eq!(error(&flipped), "Checksum mismatch; the file is corrupted");
+}
+
+#[test]
+fn wrong_version() {
+    let mut bytes = encode(&compiled()).unwrap();
+    bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    assert!(error(&bytes).starts_with(&format!("Unsupported format version {}", FORMAT_VERSION + 1)));
+}
+
+#[test]
+fn unknown_tags() {
+    let bytes = chunk(vec![Value::Number(1)], &[], vec![Op::Constant(0), Op::Print]);
+    let mut body = bytes[10..].to_vec();
+    // The first constant's tag comes after the name and the constant count.
+    let tag = 4 + "made.tg".len() + 4;
+    body[tag] = 5;
+    assert_eq!(error(&with_body(&bytes, &body)), "Unknown constant tag 5");
+
+    let mut body = bytes[10..].to_vec();
+    // Then the number, the slot count and the instruction count.
+    let opcode = tag + 1 + 8 + 4 + 4;
+    body[opcode] = 99;
+    assert_eq!(error(&with_body(&bytes, &body)), "Unknown opcode 99");
+
+    let mut body = bytes[10..].to_vec();
+    body.push(0);
+    assert_eq!(error(&with_body(&bytes, &body)), "Unexpected data after the line table");
+}
+
+#[test]
+fn bad_operands() {
+    let name = || Value::String("len".to_string());
+    for (code, constants, slots) in [
+        (vec![Op::Constant(1), Op::Print], vec![Value::Null], vec![]),
+        (vec![Op::Load(0), Op::Print], vec![], vec![]),
+        (vec![Op::Constant(0), Op::Declare(1)], vec![Value::Null], vec!["x"]),
+        (vec![Op::Constant(0), Op::Assign(7)], vec![Value::Null], vec!["x"]),
+        (vec![Op::Jump(3)], vec![], vec![]),
+        (vec![Op::Constant(0), Op::JumpIfFalse(9)], vec![Value::Boolean(true)], vec![]),
+        (vec![Op::Call { name: 1, argc: 0 }, Op::Pop], vec![name()], vec![]),
+        (vec![Op::Call { name: 0, argc: 0 }, Op::Pop], vec![Value::Number(3)], vec![]),
+    ] {
+        let listing = format!("{:?}", code);
+        let message = error(&chunk(constants, &slots, code));
+        assert!(message.starts_with("Invalid operand at instruction"), "{}: {}", listing, message);
+    }
+}
+
+#[test]
+fn unbalanced_stacks() {
+    assert_eq!(error(&chunk(vec![], &[], vec![Op::Add])), "Instruction 0 pops an empty stack");
+    let name = Value::String("len".to_string());
+    assert_eq!(
+        error(&chunk(vec![name], &[], vec![Op::Call { name: 0, argc: 1 }])),
+        "Instruction 0 pops an empty stack"
+    );
+    // One path reaches the end with a value left over and the other without.
+    let code = vec![Op::Constant(0), Op::JumpIfFalse(3), Op::Constant(0), Op::Pop];
+    assert!(error(&chunk(vec![Value::Boolean(true)], &[], code)).starts_with("Stack depth at instruction"));
+
+    let mut bytes = chunk(vec![], &[], vec![Op::Jump(1)]);
+    // Drop the one line entry: a count of zero and no lines.
+    let mut body = bytes[10..bytes.len() - 8].to_vec();
+    body.extend_from_slice(&0u32.to_le_bytes());
+    bytes = with_body(&bytes, &body);
+    assert_eq!(error(&bytes), "Line table has 0 entries for 1 instructions");
+}
+
+#[test]
+fn unsupported_constants() {
+    for constant in [Value::List(vec![Value::Number(1)]), Value::Map(Default::default())] {
+        let chunk = Chunk { constants: vec![constant], slots: vec![], code: vec![], lines: vec![] };
+        let message = encode(&CompiledFile { source_name: "made.tg".to_string(), chunk }).unwrap_err();
+        assert!(message.starts_with("Can't store the constant"), "{}", message);
+    }
+}
@@ -0,0 +1,127 @@
+// The exact trace of small programs, in both formats.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use touch_grass::console::Buffer;
+use touch_grass::interpreter::Interpreter;
+use touch_grass::lexer::tokenize_with_lines;
+use touch_grass::parser::Parser;
+use touch_grass::trace::{TraceFormat, Tracer};
+
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `source` under a tracer and returns the trace and the error message,
+/// if it failed.
+fn trace(source: &str, format: TraceFormat) -> (String, Option<String>) {
+    let program = Parser::with_lines(tokenize_with_lines(source)).parse();
+    let out = Shared::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(Buffer::new()));
+    interpreter.set_hook(Box::new(Tracer::new(format, Box::new(out.clone()))));
+    let error = interpreter.interpret(&program).err().map(|e| e.message);
+    let text = String::from_utf8(out.0.borrow().clone()).unwrap();
+    (text, error)
+}
+
+const LOOP: &str = "touch grass number i as 0
+while i < 2 then
+    set i to i + 1
+fr fr
+print i
+";
+
+const FAILS: &str = "touch grass number i as 1
+go outside if i = 1 then
+    set j to i
+fr fr
+print i
+";
+
+#[test]
+fn text_loop() {
+    let (text, error) = trace(LOOP, TraceFormat::Text);
+    assert_eq!(error, None);
+    assert_eq!(text, "\
+[trace] line 1: touch grass number i as 0
+[trace]   i := 0
+[trace] line 2: while i < 2 then
+[trace]     0 < 2 => true
+[trace]   iteration 1
+[trace]   line 3: set i to i + 1
+[trace]     0 + 1 => 1
+[trace]     i := 1
+[trace]     1 < 2 => true
+[trace]   iteration 2
+[trace]   line 3: set i to i + 1
+[trace]     1 + 1 => 2
+[trace]     i := 2
+[trace]     2 < 2 => false
+[trace]   loop done after 2 iteration(s)
+[trace] line 5: print i
+");
+}
+
+#[test]
+fn json_lines_loop() {
+    let (text, error) = trace(LOOP, TraceFormat::JsonLines);
+    assert_eq!(error, None);
+    assert_eq!(text, r#"{"event":"statement","depth":0,"line":1,"code":"touch grass number i as 0"}
+{"event":"write","depth":1,"name":"i","value":0}
+{"event":"statement","depth":0,"line":2,"code":"while i < 2 then"}
+{"event":"binary_op","depth":2,"left":0,"operator":"<","right":2,"result":true}
+{"event":"iteration","depth":1,"n":1}
+{"event":"statement","depth":1,"line":3,"code":"set i to i + 1"}
+{"event":"binary_op","depth":2,"left":0,"operator":"+","right":1,"result":1}
+{"event":"write","depth":2,"name":"i","value":1}
+{"event":"binary_op","depth":2,"left":1,"operator":"<","right":2,"result":true}
+{"event":"iteration","depth":1,"n":2}
+{"event":"statement","depth":1,"line":3,"code":"set i to i + 1"}
+{"event":"binary_op","depth":2,"left":1,"operator":"+","right":1,"result":2}
+{"event":"write","depth":2,"name":"i","value":2}
+{"event":"binary_op","depth":2,"left":2,"operator":"<","right":2,"result":false}
+{"event":"loop_end","depth":1,"iterations":2}
+{"event":"statement","depth":0,"line":5,"code":"print i"}
+"#);
+}
+
+/// A trace ends with the statement that failed; nothing after it runs.
+#[test]
+fn errors_end_the_trace() {
+    let (text, error) = trace(FAILS, TraceFormat::Text);
+    assert_eq!(error.as_deref(), Some("Undefined variable: j"));
+    assert_eq!(text, "\
+[trace] line 1: touch grass number i as 1
+[trace]   i := 1
+[trace] line 2: go outside if i = 1 then
+[trace]   1 = 1 => true
+[trace]   line 3: set j to i
+");
+
+    let (text, error) = trace(FAILS, TraceFormat::JsonLines);
+    assert_eq!(error.as_deref(), Some("Undefined variable: j"));
+    assert_eq!(text, r#"{"event":"statement","depth":0,"line":1,"code":"touch grass number i as 1"}
+{"event":"write","depth":1,"name":"i","value":1}
+{"event":"statement","depth":0,"line":2,"code":"go outside if i = 1 then"}
+{"event":"binary_op","depth":1,"left":1,"operator":"=","right":1,"result":true}
+{"event":"statement","depth":1,"line":3,"code":"set j to i"}
+"#);
+}
+
+#[test]
+fn json_values() {
+    let (text, _) = trace("touch grass number s as \"a\" + 1\n", TraceFormat::JsonLines);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[1], r#"{"event":"binary_op","depth":1,"left":"a","operator":"+","right":1,"result":null}"#);
+}
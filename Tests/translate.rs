@@ -0,0 +1,75 @@
+// Checks `touch-grass translate` against golden files: each
+// Tests/translate/<name>.plain must translate to <name>.tg, which must already
+// be formatted and pass `touch-grass check`. Then checks that programs the
+// checker would reject don't translate.
+//
+// Run with UPDATE_GOLDENS=1 to rewrite the golden files instead.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const BIN: &str = env!("CARGO_BIN_EXE_touch-grass");
+
+fn touch_grass(args: &[&Path]) -> Output {
+    Command::new(BIN).args(args).output().unwrap()
+}
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("touch-grass-translate-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn goldens() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("Tests/translate");
+    let mut checked = 0;
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let plain = entry.unwrap().path();
+        if plain.extension().is_none_or(|e| e != "plain") {
+            continue;
+        }
+        let golden = plain.with_extension("tg");
+        let translated = scratch(&format!("{}.tg", plain.file_stem().unwrap().to_str().unwrap()));
+        let output = touch_grass(&[Path::new("translate"), &plain, Path::new("-o"), &translated]);
+        assert!(output.status.success(), "{}: {}", plain.display(), String::from_utf8_lossy(&output.stderr));
+        let actual = std::fs::read_to_string(&translated).unwrap();
+
+        if std::env::var_os("UPDATE_GOLDENS").is_some() {
+            std::fs::write(&golden, &actual).unwrap();
+        } else {
+            assert_eq!(actual, std::fs::read_to_string(&golden).unwrap(), "{} differs from {}", plain.display(), golden.display());
+        }
+        let formatted = touch_grass(&[Path::new("fmt"), Path::new("--check"), &translated]);
+        assert!(formatted.status.success(), "{}: translation isn't formatted", plain.display());
+        let checked_output = touch_grass(&[Path::new("check"), &translated]);
+        assert!(checked_output.status.success(), "{}: translation doesn't type check", plain.display());
+        let _ = std::fs::remove_file(&translated);
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+/// Translates `plain` and returns the error it fails with.
+fn rejects(name: &str, plain: &str) -> String {
+    let file = scratch(&format!("{}.plain", name));
+    std::fs::write(&file, plain).unwrap();
+    let output = touch_grass(&[Path::new("translate"), &file]);
+    let _ = std::fs::remove_file(&file);
+    assert!(!output.status.success(), "{:?} translated", plain);
+    String::from_utf8_lossy(&output.stderr).trim_end().to_string()
+}
+
+#[test]
+fn programs_the_checker_rejects() {
+    assert_eq!(
+        rejects("declared-string", "let name = 'grass'\n"),
+        "Error: line 1: Variable name is declared as number but given a string"
+    );
+    assert_eq!(
+        rejects("assigned-string", "let n = 1\nn = \"one\"\n"),
+        "Error: line 2: Cannot set n (a number) to a string"
+    );
+    assert_eq!(
+        rejects("undefined-name", "while 0:\n    print missing\n"),
+        "Error: line 2: Undefined variable: missing"
+    );
+}
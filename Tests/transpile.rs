@@ -0,0 +1,184 @@
+// Checks the transpiler against golden files in Tests/golden/<target>/:
+// runtime.<target> holds the runtime every program shares and <name>.<target>
+// the rest of each program's output. Then it runs the generated programs
+// (when the toolchain is installed) and compares their stdout, stderr and
+// exit code with the interpreter's. `transpile` validates the wat output
+// itself, so that's checked even without wat2wasm. A program's stdin is the
+// .stdin file next to it, if there is one.
+//
+// Run with UPDATE_GOLDENS=1 to rewrite the golden files instead.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const BIN: &str = env!("CARGO_BIN_EXE_touch-grass");
+
+fn root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn installed(tool: &str) -> bool {
+    Command::new(tool).arg("--version").output().is_ok_and(|output| output.status.success())
+}
+
+fn stdin_for(program: &Path) -> Stdio {
+    match File::open(program.with_extension("stdin")) {
+        Ok(file) => Stdio::from(file),
+        Err(_) => Stdio::null(),
+    }
+}
+
+/// Splits generated code into the lines between the runtime's markers and
+/// every other line.
+fn split(code: &str) -> (String, String) {
+    let (mut runtime, mut program) = (String::new(), String::new());
+    let mut inside = false;
+    for line in code.lines() {
+        if line.contains("The touch-grass runtime, the same in every program.") {
+            inside = true;
+        }
+        let part = if inside { &mut runtime } else { &mut program };
+        part.push_str(line);
+        part.push('\n');
+        if line.contains("End of the runtime.") {
+            inside = false;
+        }
+    }
+    (runtime, program)
+}
+
+/// Compares `actual` with a golden file, or overwrites it when updating.
+fn check_golden(golden: &Path, actual: &str) -> Result<(), String> {
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(golden, actual).unwrap();
+        return Ok(());
+    }
+    match std::fs::read_to_string(golden) {
+        Ok(expected) if expected == actual => Ok(()),
+        Ok(_) => Err(format!("output differs from {}", golden.display())),
+        Err(e) => Err(format!("can't read {}: {}", golden.display(), e)),
+    }
+}
+
+/// The command that runs a generated program, or `None` if the toolchain for
+/// the target isn't installed.
+fn runner(target: &str, code: &Path) -> Option<Command> {
+    match target {
+        "js" => installed("node").then(|| {
+            let mut command = Command::new("node");
+            command.arg(code);
+            command
+        }),
+        "c" => {
+            if !installed("cc") {
+                return None;
+            }
+            let binary = code.with_extension("bin");
+            let status = Command::new("cc")
+                .args(["-std=c99", "-pedantic", "-Wall", "-Werror", "-o"])
+                .arg(&binary)
+                .arg(code)
+                .status()
+                .unwrap();
+            assert!(status.success(), "{} doesn't compile", code.display());
+            Some(Command::new(binary))
+        }
+        "wat" => {
+            if !installed("wat2wasm") || !installed("node") {
+                return None;
+            }
+            let module = code.with_extension("wasm");
+            let status = Command::new("wat2wasm").arg("-o").arg(&module).arg(code).status().unwrap();
+            assert!(status.success(), "{} doesn't assemble", code.display());
+            let mut command = Command::new("node");
+            command.arg(root().join("Tests/wasm_host.mjs")).arg(module);
+            Some(command)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn same(actual: &Output, expected: &Output) -> Result<(), String> {
+    if actual.status.code() == expected.status.code() && actual.stdout == expected.stdout && actual.stderr == expected.stderr {
+        return Ok(());
+    }
+    Err(format!(
+        "exit code {:?}, expected {:?}\n--- stdout\n{}\n--- expected\n{}\n--- stderr\n{}\n--- expected\n{}",
+        actual.status.code(),
+        expected.status.code(),
+        String::from_utf8_lossy(&actual.stdout),
+        String::from_utf8_lossy(&expected.stdout),
+        String::from_utf8_lossy(&actual.stderr),
+        String::from_utf8_lossy(&expected.stderr),
+    ))
+}
+
+fn check(target: &str, program: &Path, scratch: &Path) -> Result<(), String> {
+    let name = program.file_stem().unwrap().to_str().unwrap();
+    let code = scratch.join(format!("{}.{}", name, target));
+    let output = Command::new(BIN)
+        .current_dir(root())
+        .args(["transpile", "--target", target])
+        .arg(program)
+        .arg("-o")
+        .arg(&code)
+        .output()
+        .unwrap();
+    if !output.status.success() {
+        return Err(format!("transpile failed: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let (runtime, rest) = split(&std::fs::read_to_string(&code).unwrap());
+    let goldens = root().join("Tests/golden").join(target);
+    std::fs::create_dir_all(&goldens).unwrap();
+    check_golden(&goldens.join(format!("runtime.{}", target)), &runtime)?;
+    check_golden(&goldens.join(format!("{}.{}", name, target)), &rest)?;
+
+    let Some(mut run) = runner(target, &code) else {
+        return Ok(());
+    };
+    let expected = Command::new(BIN)
+        .current_dir(root())
+        .args(["run", "-q"])
+        .arg(program)
+        .stdin(stdin_for(program))
+        .output()
+        .unwrap();
+    let actual = run.current_dir(root()).stdin(stdin_for(program)).output().unwrap();
+    same(&actual, &expected)
+}
+
+fn check_target(target: &str) {
+    let scratch = std::env::temp_dir().join(format!("touch-grass-transpile-{}-{}", std::process::id(), target));
+    std::fs::create_dir_all(&scratch).unwrap();
+    let mut programs: Vec<PathBuf> = std::fs::read_dir(root().join("Tests"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "tg"))
+        .map(|path| path.strip_prefix(root()).unwrap().to_path_buf())
+        .collect();
+    programs.sort();
+
+    let failures: Vec<String> = programs
+        .iter()
+        .filter_map(|program| check(target, program, &scratch).err().map(|e| format!("{} ({}): {}", program.display(), target, e)))
+        .collect();
+    let _ = std::fs::remove_dir_all(&scratch);
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn javascript() {
+    check_target("js");
+}
+
+#[test]
+fn c() {
+    check_target("c");
+}
+
+#[test]
+fn wat() {
+    check_target("wat");
+}
@@ -0,0 +1,81 @@
+// Runs a module from `touch-grass transpile --target wat` (assembled to
+// .wasm, e.g. with wat2wasm) the way `touch-grass run` would run the source.
+// Usage: node Tests/wasm_host.mjs <module.wasm> [args...]
+import fs from "node:fs";
+
+const [file, ...args] = process.argv.slice(2);
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+const encodedArgs = args.map((arg) => encoder.encode(arg));
+let memory = null;
+let failed = false;
+// What read_line or read_all returned last, until input_read copies it.
+let input = new Uint8Array(0);
+let inputEnded = false;
+const byte = new Uint8Array(1);
+
+// A byte at a time, so nothing past the line is taken from stdin.
+const readByte = () => {
+  if (inputEnded) return null;
+  try {
+    if (fs.readSync(0, byte, 0, 1, null) === 1) return byte[0];
+  } catch (error) {
+    if (error.code !== "EOF") throw error;
+  }
+  inputEnded = true;
+  return null;
+};
+
+const bytes = (ptr, len) => new Uint8Array(memory.buffer, ptr, len);
+const envValue = (ptr, len) => {
+  const value = process.env[decoder.decode(bytes(ptr, len))];
+  return value === undefined ? null : encoder.encode(value);
+};
+
+const imports = {
+  touch_grass: {
+    write: (ptr, len) => fs.writeSync(1, bytes(ptr, len)),
+    error: (ptr, len) => {
+      failed = true;
+      fs.writeSync(2, `Runtime error: ${decoder.decode(bytes(ptr, len))}\n`);
+    },
+    arg_count: () => encodedArgs.length,
+    arg_length: (i) => encodedArgs[i].length,
+    arg_read: (i, ptr) => bytes(ptr, encodedArgs[i].length).set(encodedArgs[i]),
+    env_length: (ptr, len) => {
+      const value = envValue(ptr, len);
+      return value === null ? -1 : value.length;
+    },
+    env_read: (ptr, len, out) => {
+      const value = envValue(ptr, len);
+      bytes(out, value.length).set(value);
+    },
+    read_line: () => {
+      const line = [];
+      let next = readByte();
+      if (next === null) return -1;
+      for (; next !== null && next !== 10; next = readByte()) line.push(next);
+      if (next === 10 && line[line.length - 1] === 13) line.pop();
+      input = Uint8Array.from(line);
+      return input.length;
+    },
+    read_all: () => {
+      const rest = [];
+      for (let next = readByte(); next !== null; next = readByte()) rest.push(next);
+      input = Uint8Array.from(rest);
+      return input.length;
+    },
+    input_read: (ptr) => bytes(ptr, input.length).set(input),
+  },
+};
+
+const module = new WebAssembly.Module(fs.readFileSync(file));
+const instance = new WebAssembly.Instance(module, imports);
+memory = instance.exports.memory;
+try {
+  process.exitCode = instance.exports.main();
+} catch (error) {
+  // `error` has already reported it; the module traps to stop.
+  if (!failed) throw error;
+  process.exitCode = 3;
+}
@@ -0,0 +1,91 @@
+// The wat validator on small hand-written modules: one it accepts, and one
+// broken in each way it's meant to catch.
+
+use touch_grass::transpile::validate_wat;
+
+/// A module that validates, with `BODY` standing for the body of `$main`.
+const MODULE: &str = r#"(module
+  (import "touch_grass" "write" (func $write (param i32 i32)))
+  (memory (export "memory") 1)
+  (global $count (mut i32) (i32.const 0))
+  (data (i32.const 1024) "grass")
+  (func $double (param $n i64) (result i64)
+    local.get $n
+    local.get $n
+    i64.add)
+  (func $main (export "main") (result i32)
+    (local $x i64)
+BODY)
+)
+"#;
+
+fn module(body: &str) -> String {
+    MODULE.replace("BODY", body)
+}
+
+fn error(text: &str) -> String {
+    validate_wat(text).expect_err("the module should be rejected")
+}
+
+#[test]
+fn accepts_a_valid_module() {
+    let body = "    i64.const 21
+    call $double
+    local.set $x
+    i32.const 1024
+    i32.const 5
+    call $write
+    block $done (result i32)
+      i32.const 0
+    end";
+    assert_eq!(validate_wat(&module(body)), Ok(()));
+}
+
+#[test]
+fn type_mismatch() {
+    assert_eq!(
+        error(&module("    i32.const 1\n    i64.const 2\n    i32.add")),
+        "line 14: `i32.add` expected i32 but found i64"
+    );
+    assert_eq!(error(&module("    i32.const 1\n    call $double\n    drop\n    i32.const 0")), "line 13: `call` expected i64 but found i32");
+    // A function's result is checked against its header.
+    assert_eq!(error(&module("    i64.const 1")), "line 10: `end` expected i32 but found i64");
+    assert_eq!(error(&module("    i32.add")), "line 12: `i32.add` needs more values than are on the stack");
+}
+
+#[test]
+fn unknown_names() {
+    assert_eq!(error(&module("    local.get $y\n    drop\n    i32.const 0")), "line 12: unknown local $y");
+    assert_eq!(error(&module("    call $triple\n    i32.const 0")), "line 12: unknown function $triple");
+    assert_eq!(error(&module("    global.get $total")), "line 12: unknown global $total");
+    assert_eq!(error(&module("    br $nowhere")), "line 12: unknown label $nowhere");
+    assert_eq!(error(&module("    i32.frobnicate")), "line 12: unknown instruction `i32.frobnicate`");
+}
+
+#[test]
+fn data_out_of_bounds() {
+    let text = module("    i32.const 0").replace("(i32.const 1024) \"grass\"", "(i32.const 65534) \"grass\"");
+    assert_eq!(error(&text), "line 5: data ends at byte 65539, past the end of memory (65536 bytes)");
+}
+
+#[test]
+fn wrong_block_result() {
+    let body = "    block $done (result i32)
+      i64.const 0
+    end";
+    assert_eq!(error(&module(body)), "line 14: `end` expected i32 but found i64");
+    let body = "    block $done (result i32)
+    end";
+    assert_eq!(error(&module(body)), "line 13: `end` needs more values than are on the stack");
+    let body = "    block $done
+      i32.const 0
+    end
+    i32.const 0";
+    assert_eq!(error(&module(body)), "line 14: `end` leaves 1 extra value(s) on the stack");
+    let body = "    i32.const 1
+    if (result i32)
+      i32.const 2
+    end";
+    assert_eq!(error(&module(body)), "line 15: an `if` with results needs an `else`");
+}
+
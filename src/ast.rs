@@ -1,7 +1,14 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     Program(Vec<Node>),
 
+    // A statement tagged with the source line it starts on. Only produced when
+    // the parser is given lines; everything else looks straight through it.
+    Statement {
+        line: usize,
+        statement: Box<Node>,
+    },
+
     VarDeclaration {
         name: String,
         var_type: String,
@@ -14,6 +21,13 @@ pub enum Node {
         value: Box<Node>,
     },
 
+    // Only kept when parsing for tools like the formatter. `trailing` comments
+    // shared a line with the code before them.
+    Comment {
+        text: String,
+        trailing: bool,
+    },
+
     Identifier(String),
     StringLiteral(String),
     NumberLiteral(i64),
@@ -22,6 +36,12 @@ pub enum Node {
 
     Print(Box<Node>),
 
+    // Builtin function call: `name(arg, ...)`
+    Call {
+        name: String,
+        args: Vec<Node>,
+    },
+
     GoOutside {
         condition: Box<Node>,
         then_branch: Box<Node>,
@@ -42,7 +62,7 @@ pub enum Node {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Operator {
     GreaterThan,
     LessThan,
@@ -50,3 +70,24 @@ pub enum Operator {
     Plus,
     Minus,
 }
+
+impl Operator {
+    /// The operator as it is written in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::GreaterThan => ">",
+            Operator::LessThan => "<",
+            Operator::Equals => "=",
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+        }
+    }
+
+    /// Binding strength: comparisons bind looser than `+` and `-`.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::GreaterThan | Operator::LessThan | Operator::Equals => 1,
+            Operator::Plus | Operator::Minus => 2,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use crate::console::{Input, Output};
+use crate::interpreter::{RuntimeError, Value};
+use crate::sandbox::Permissions;
+
+/// A function scripts can call with `name(arg, ...)`.
+pub struct Builtin {
+    pub name: &'static str,
+    pub min_args: usize,
+    pub max_args: usize,
+    pub description: &'static str,
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin { name: "env", min_args: 1, max_args: 1, description: "env(name): the environment variable, or null if unset" },
+    Builtin { name: "exit_code", min_args: 1, max_args: 1, description: "exit_code(n): exit the process with n once the program finishes" },
+    Builtin { name: "len", min_args: 1, max_args: 1, description: "len(list_or_string): number of items or characters" },
+    Builtin { name: "at", min_args: 2, max_args: 2, description: "at(list, index): the item at a zero-based index; at(map, key) looks up a key" },
+    Builtin { name: "read_line", min_args: 0, max_args: 1, description: "read_line(prompt?): the next line of input, or null at the end; shows the prompt first" },
+    Builtin { name: "read_all", min_args: 0, max_args: 0, description: "read_all(): all the remaining input" },
+    Builtin { name: "parse_number", min_args: 1, max_args: 1, description: "parse_number(text): the number written in text, e.g. \"-42\"" },
+    Builtin { name: "read_file", min_args: 1, max_args: 1, description: "read_file(path): the contents of a text file; needs read access" },
+    Builtin { name: "write_file", min_args: 2, max_args: 2, description: "write_file(path, text): create or replace a file; needs write access" },
+    Builtin { name: "list_dir", min_args: 1, max_args: 1, description: "list_dir(path): the names in a directory, sorted; needs read access" },
+    Builtin { name: "file_exists", min_args: 1, max_args: 1, description: "file_exists(path): whether a file or directory is there; needs read access" },
+    Builtin { name: "delete_file", min_args: 1, max_args: 1, description: "delete_file(path): remove a file; needs write access" },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+/// Runs a builtin. `exit_code` is where `exit_code(n)` records its request;
+/// the reading builtins use `input`, and `output` for prompts. The
+/// filesystem builtins can only reach what `permissions` grants.
+pub fn call(
+    exit_code: &mut Option<i32>,
+    input: &mut dyn Input,
+    output: &mut dyn Output,
+    permissions: &Permissions,
+    name: &str,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let builtin = lookup(name).ok_or_else(|| RuntimeError::new(format!("Unknown function: {}", name)))?;
+    if args.len() < builtin.min_args || args.len() > builtin.max_args {
+        let expected = if builtin.min_args == builtin.max_args {
+            builtin.min_args.to_string()
+        } else {
+            format!("{} to {}", builtin.min_args, builtin.max_args)
+        };
+        return Err(RuntimeError::new(format!(
+            "{} expects {} argument(s) but got {}",
+            name,
+            expected,
+            args.len()
+        )));
+    }
+
+    match (name, args.as_slice()) {
+        ("env", [Value::String(key)]) => {
+            Ok(env::var(key).map(Value::String).unwrap_or(Value::Null))
+        }
+        ("exit_code", [Value::Number(code)]) => {
+            let code = i32::try_from(*code)
+                .map_err(|_| RuntimeError::new(format!("Exit code {} is out of range", code)))?;
+            *exit_code = Some(code);
+            Ok(Value::Null)
+        }
+        ("len", [Value::List(items)]) => Ok(Value::Number(items.len() as i64)),
+        ("len", [Value::String(s)]) => Ok(Value::Number(s.chars().count() as i64)),
+        ("len", [Value::Map(entries)]) => Ok(Value::Number(entries.len() as i64)),
+        ("at", [Value::List(items), Value::Number(index)]) => {
+            usize::try_from(*index)
+                .ok()
+                .and_then(|i| items.get(i))
+                .cloned()
+                .ok_or_else(|| RuntimeError::new(format!(
+                    "Index {} is out of range for a list of {} items",
+                    index,
+                    items.len()
+                )))
+        }
+        ("at", [Value::Map(entries), Value::String(key)]) => entries
+            .get(key)
+            .cloned()
+            .ok_or_else(|| RuntimeError::new(format!("Key \"{}\" is not in the map", key))),
+        ("read_line", []) => Ok(input.read_line().map(Value::String).unwrap_or(Value::Null)),
+        ("read_line", [Value::String(prompt)]) => {
+            output.write(prompt);
+            Ok(input.read_line().map(Value::String).unwrap_or(Value::Null))
+        }
+        ("read_all", []) => Ok(Value::String(input.read_all())),
+        ("parse_number", [Value::String(text)]) => parse_number(text)
+            .map(Value::Number)
+            .ok_or_else(|| RuntimeError::new(format!("Cannot parse \"{}\" as a number", text))),
+        ("read_file", [Value::String(path)]) => {
+            let text = fs::read_to_string(permissions.check_read(path)?).map_err(|e| fs_error("read", path, e))?;
+            Ok(Value::String(text))
+        }
+        ("write_file", [Value::String(path), Value::String(text)]) => {
+            fs::write(permissions.check_write(path)?, text).map_err(|e| fs_error("write", path, e))?;
+            Ok(Value::Null)
+        }
+        ("list_dir", [Value::String(path)]) => {
+            let entries = fs::read_dir(permissions.check_read(path)?).map_err(|e| fs_error("list", path, e))?;
+            let mut names = Vec::new();
+            for entry in entries {
+                let entry = entry.map_err(|e| fs_error("list", path, e))?;
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+            names.sort();
+            Ok(Value::List(names.into_iter().map(Value::String).collect()))
+        }
+        ("file_exists", [Value::String(path)]) => Ok(Value::Boolean(permissions.check_read(path)?.exists())),
+        ("delete_file", [Value::String(path)]) => {
+            fs::remove_file(permissions.check_write(path)?).map_err(|e| fs_error("delete", path, e))?;
+            Ok(Value::Null)
+        }
+        _ => Err(RuntimeError::new(format!(
+            "Invalid arguments to {}: {}",
+            name,
+            args.iter().map(|a| a.type_name()).collect::<Vec<_>>().join(", ")
+        ))),
+    }
+}
+
+fn fs_error(action: &str, path: &str, error: std::io::Error) -> RuntimeError {
+    RuntimeError::new(format!("Cannot {} \"{}\": {}", action, path, error))
+}
+
+/// An optional minus sign and then digits, nothing else: no spaces or `+`,
+/// so every backend accepts exactly the same text.
+fn parse_number(text: &str) -> Option<i64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
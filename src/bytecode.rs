@@ -0,0 +1,199 @@
+use crate::ast::{Node, Operator};
+use crate::interpreter::Value;
+
+/// One VM instruction. Operands index into the chunk's constants, slots or code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// Push `constants[i]`.
+    Constant(u32),
+    /// Push the variable in slot `i`, or null if it was never declared.
+    Load(u32),
+    /// Pop a value into slot `i`, declaring the variable.
+    Declare(u32),
+    /// Pop a value into slot `i`; fails if the variable was never declared.
+    Assign(u32),
+    /// Pop two values and push the result of the operator.
+    Add,
+    Subtract,
+    Greater,
+    Less,
+    Equal,
+    /// Pop and print a value.
+    Print,
+    /// Pop `argc` arguments and call the builtin named by `constants[name]`.
+    Call { name: u32, argc: u32 },
+    /// Discard the top of the stack.
+    Pop,
+    Jump(u32),
+    /// Pop a value and jump if it isn't truthy.
+    JumpIfFalse(u32),
+}
+
+impl Op {
+    pub fn for_operator(operator: &Operator) -> Op {
+        match operator {
+            Operator::Plus => Op::Add,
+            Operator::Minus => Op::Subtract,
+            Operator::GreaterThan => Op::Greater,
+            Operator::LessThan => Op::Less,
+            Operator::Equals => Op::Equal,
+        }
+    }
+}
+
+/// A compiled program.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chunk {
+    pub constants: Vec<Value>,
+    pub code: Vec<Op>,
+    /// The source line each instruction came from, 0 when unknown.
+    pub lines: Vec<usize>,
+    /// Variable names by slot. Every variable the program mentions gets a slot.
+    pub slots: Vec<String>,
+}
+
+impl Chunk {
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| slot == name)
+    }
+}
+
+/// Compiles a parsed program to bytecode.
+pub fn compile(program: &Node) -> Chunk {
+    let mut compiler = Compiler { chunk: Chunk::default(), line: 0 };
+    // `args` is always defined, so give it a fixed slot the VM can fill in.
+    compiler.slot("args");
+    compiler.statement(program);
+    compiler.chunk
+}
+
+struct Compiler {
+    chunk: Chunk,
+    line: usize,
+}
+
+impl Compiler {
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.lines.push(self.line);
+        self.chunk.code.len() - 1
+    }
+
+    /// Points a jump emitted earlier at the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.chunk.code.len() as u32;
+        match &mut self.chunk.code[at] {
+            Op::Jump(to) | Op::JumpIfFalse(to) => *to = target,
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        let index = match self.chunk.constants.iter().position(|c| *c == value) {
+            Some(index) => index,
+            None => {
+                self.chunk.constants.push(value);
+                self.chunk.constants.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    fn slot(&mut self, name: &str) -> u32 {
+        let index = match self.chunk.slot(name) {
+            Some(index) => index,
+            None => {
+                self.chunk.slots.push(name.to_string());
+                self.chunk.slots.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) | Node::Block(statements) => {
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+            }
+            Node::Statement { line, statement } => {
+                self.line = *line;
+                self.statement(statement);
+            }
+            Node::VarDeclaration { name, value, .. } => {
+                self.expression(value);
+                let slot = self.slot(name);
+                self.emit(Op::Declare(slot));
+            }
+            Node::Assignment { name, value } => {
+                self.expression(value);
+                let slot = self.slot(name);
+                self.emit(Op::Assign(slot));
+            }
+            Node::Print(expr) => {
+                self.expression(expr);
+                self.emit(Op::Print);
+            }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                self.expression(condition);
+                let skip_then = self.emit(Op::JumpIfFalse(0));
+                self.statement(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        let skip_else = self.emit(Op::Jump(0));
+                        self.patch(skip_then);
+                        self.statement(else_branch);
+                        self.patch(skip_else);
+                    }
+                    None => self.patch(skip_then),
+                }
+            }
+            Node::WhileLoop { condition, body } => {
+                let start = self.chunk.code.len() as u32;
+                self.expression(condition);
+                let exit = self.emit(Op::JumpIfFalse(0));
+                self.statement(body);
+                self.emit(Op::Jump(start));
+                self.patch(exit);
+            }
+            Node::Comment { .. } => {}
+            expr => {
+                self.expression(expr);
+                self.emit(Op::Pop);
+            }
+        }
+    }
+
+    /// Emits code that leaves exactly one value on the stack.
+    fn expression(&mut self, node: &Node) {
+        match node {
+            Node::Identifier(name) => {
+                let slot = self.slot(name);
+                self.emit(Op::Load(slot));
+            }
+            Node::NumberLiteral(n) => self.push_constant(Value::Number(*n)),
+            Node::StringLiteral(s) => self.push_constant(Value::String(s.clone())),
+            Node::Boolean(b) => self.push_constant(Value::Boolean(*b)),
+            Node::Bugatti => self.push_constant(Value::String("Bugatti!".to_string())),
+            Node::BinaryOp { left, operator, right } => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(Op::for_operator(operator));
+            }
+            Node::Call { name, args } => {
+                for arg in args {
+                    self.expression(arg);
+                }
+                let name = self.constant(Value::String(name.clone()));
+                self.emit(Op::Call { name, argc: args.len() as u32 });
+            }
+            _ => self.push_constant(Value::Null),
+        }
+    }
+
+    fn push_constant(&mut self, value: Value) {
+        let index = self.constant(value);
+        self.emit(Op::Constant(index));
+    }
+}
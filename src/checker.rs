@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use crate::ast::{Node, Operator};
+use crate::builtins;
+
+/// Static type of an expression as far as the checker can tell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Number,
+    String,
+    Boolean,
+    List,
+    Unknown,
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::Number => write!(f, "number"),
+            Type::String => write!(f, "string"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::List => write!(f, "list"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl Type {
+    /// Maps the type name written after `touch grass` to a type.
+    pub fn from_name(name: &str) -> Type {
+        match name {
+            "number" => Type::Number,
+            _ => Type::Unknown,
+        }
+    }
+
+    fn conflicts_with(self, other: Type) -> bool {
+        self != Type::Unknown && other != Type::Unknown && self != other
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub message: String,
+    pub line: usize, // 0 when the tree has no line information
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+/// Walks a parsed program and reports type errors without running it.
+/// Like the interpreter, there is a single flat scope: a declaration inside a
+/// block is visible to every statement after it.
+pub struct TypeChecker {
+    pub vars: HashMap<String, Type>,
+    errors: Vec<TypeError>,
+    line: usize,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        TypeChecker::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        let mut vars = HashMap::new();
+        // Always defined by the interpreter; holds the command-line arguments.
+        vars.insert("args".to_string(), Type::List);
+        TypeChecker {
+            vars,
+            errors: Vec::new(),
+            line: 0,
+        }
+    }
+
+    pub fn check(&mut self, node: &Node) -> &[TypeError] {
+        self.check_statement(node);
+        &self.errors
+    }
+
+    fn check_statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) | Node::Block(statements) => {
+                for stmt in statements {
+                    self.check_statement(stmt);
+                }
+            }
+            Node::Statement { line, statement } => {
+                self.line = *line;
+                self.check_statement(statement);
+            }
+            Node::VarDeclaration { name, var_type, value } => {
+                let declared = Type::from_name(var_type);
+                let actual = self.infer(value);
+                if declared.conflicts_with(actual) {
+                    self.error(format!(
+                        "Variable {} is declared as {} but given a {}",
+                        name, declared, actual
+                    ));
+                }
+                self.vars.insert(name.clone(), declared);
+            }
+            Node::Assignment { name, value } => {
+                let actual = self.infer(value);
+                match self.vars.get(name) {
+                    Some(declared) if declared.conflicts_with(actual) => {
+                        self.error(format!(
+                            "Cannot set {} (a {}) to a {}",
+                            name, declared, actual
+                        ));
+                    }
+                    Some(_) => {}
+                    None => self.error(format!("Undefined variable: {}", name)),
+                }
+            }
+            Node::Print(expr) => {
+                self.infer(expr);
+            }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                self.infer(condition);
+                self.check_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_statement(else_branch);
+                }
+            }
+            Node::WhileLoop { condition, body } => {
+                self.infer(condition);
+                self.check_statement(body);
+            }
+            expr => {
+                self.infer(expr);
+            }
+        }
+    }
+
+    /// Infers the type of an expression, recording errors along the way.
+    pub fn infer(&mut self, node: &Node) -> Type {
+        match node {
+            Node::NumberLiteral(_) => Type::Number,
+            Node::StringLiteral(_) | Node::Bugatti => Type::String,
+            Node::Boolean(_) => Type::Boolean,
+            Node::Identifier(name) => match self.vars.get(name) {
+                Some(t) => *t,
+                None => {
+                    self.error(format!("Undefined variable: {}", name));
+                    Type::Unknown
+                }
+            },
+            Node::BinaryOp { left, operator, right } => {
+                let left_type = self.infer(left);
+                let right_type = self.infer(right);
+                for operand in [left_type, right_type] {
+                    if Type::Number.conflicts_with(operand) {
+                        self.error(format!(
+                            "Operator {} expects numbers but got a {}",
+                            operator.symbol(),
+                            operand
+                        ));
+                    }
+                }
+                match operator {
+                    Operator::Plus | Operator::Minus => Type::Number,
+                    Operator::GreaterThan | Operator::LessThan | Operator::Equals => Type::Boolean,
+                }
+            }
+            Node::Call { name, args } => {
+                for arg in args {
+                    self.infer(arg);
+                }
+                match builtins::lookup(name) {
+                    Some(builtin) if args.len() < builtin.min_args || args.len() > builtin.max_args => {
+                        self.error(format!(
+                            "{} called with {} argument(s)",
+                            name,
+                            args.len()
+                        ));
+                    }
+                    Some(_) => {}
+                    None => self.error(format!("Unknown function: {}", name)),
+                }
+                match name.as_str() {
+                    "len" | "parse_number" => Type::Number,
+                    "read_all" | "read_file" => Type::String,
+                    "list_dir" => Type::List,
+                    "file_exists" => Type::Boolean,
+                    _ => Type::Unknown,
+                }
+            }
+            _ => Type::Unknown,
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(TypeError { message, line: self.line });
+    }
+}
@@ -0,0 +1,159 @@
+//! Where programs read from and write to. The interpreter and the VM print
+//! through an `Output` and read through an `Input`, so an embedding program
+//! can capture what a script prints or feed it canned input instead of the
+//! terminal.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+
+/// Receives everything a program prints. `print` hands over whole lines,
+/// newline included; prompts arrive without one.
+pub trait Output {
+    fn write(&mut self, text: &str);
+}
+
+/// Any `FnMut(&str)` is an output, for hosts that forward text elsewhere.
+impl<F: FnMut(&str)> Output for F {
+    fn write(&mut self, text: &str) {
+        self(text)
+    }
+}
+
+/// The process's standard output. This is the default.
+pub struct Stdout;
+
+impl Output for Stdout {
+    fn write(&mut self, text: &str) {
+        let mut out = io::stdout().lock();
+        let _ = out.write_all(text.as_bytes());
+        // Stdout is line-buffered; a prompt has to show before we block on input.
+        if !text.ends_with('\n') {
+            let _ = out.flush();
+        }
+    }
+}
+
+/// Collects output in memory. Clones share the same text, so keep one to
+/// read what the program printed:
+///
+/// ```
+/// use touch_grass::console::Buffer;
+/// use touch_grass::Engine;
+///
+/// let buffer = Buffer::new();
+/// let mut engine = Engine::new();
+/// engine.set_output(buffer.clone());
+/// engine.run("print \"hi\"\nprint 1 + 2")?;
+/// assert_eq!(buffer.contents(), "hi\n3\n");
+/// # Ok::<(), touch_grass::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct Buffer {
+    text: Rc<RefCell<String>>,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Buffer::default()
+    }
+
+    /// Everything written so far.
+    pub fn contents(&self) -> String {
+        self.text.borrow().clone()
+    }
+
+    /// Everything written so far, leaving the buffer empty.
+    pub fn take(&self) -> String {
+        std::mem::take(&mut self.text.borrow_mut())
+    }
+}
+
+impl Output for Buffer {
+    fn write(&mut self, text: &str) {
+        self.text.borrow_mut().push_str(text);
+    }
+}
+
+/// Supplies what a program reads.
+pub trait Input {
+    /// The next line without its line ending, or `None` at the end of input.
+    fn read_line(&mut self) -> Option<String>;
+
+    /// Everything left, as it was given. By default this is the remaining
+    /// lines, each ended with a newline.
+    fn read_all(&mut self) -> String {
+        let mut text = String::new();
+        while let Some(line) = self.read_line() {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Any `FnMut() -> Option<String>` is an input that produces one line per call.
+impl<F: FnMut() -> Option<String>> Input for F {
+    fn read_line(&mut self) -> Option<String> {
+        self()
+    }
+}
+
+/// The process's standard input. This is the default.
+pub struct Stdin;
+
+impl Input for Stdin {
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(trim_line_ending(line)),
+        }
+    }
+
+    fn read_all(&mut self) -> String {
+        let mut text = String::new();
+        let _ = io::stdin().lock().read_to_string(&mut text);
+        text
+    }
+}
+
+/// Input from a fixed string, e.g. canned answers in a test.
+pub struct Text {
+    text: String,
+    position: usize,
+}
+
+impl Text {
+    pub fn new(text: impl Into<String>) -> Self {
+        Text { text: text.into(), position: 0 }
+    }
+}
+
+impl Input for Text {
+    fn read_line(&mut self) -> Option<String> {
+        let rest = &self.text[self.position..];
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        self.position += end;
+        Some(trim_line_ending(rest[..end].to_string()))
+    }
+
+    fn read_all(&mut self) -> String {
+        let rest = self.text[self.position..].to_string();
+        self.position = self.text.len();
+        rest
+    }
+}
+
+fn trim_line_ending(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
@@ -0,0 +1,149 @@
+//! Conversions between Rust types and `Value`, for native functions and
+//! globals set from an embedding program. Turning a `Value` back into a Rust
+//! type fails with a `RuntimeError` naming both types, so native functions
+//! can pass it straight back to the script with `?`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use crate::interpreter::{RuntimeError, Value};
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(entries: HashMap<String, T>) -> Self {
+        Value::Map(entries.into_iter().map(|(key, value)| (key, value.into())).collect())
+    }
+}
+
+impl<T: Into<Value>> From<BTreeMap<String, T>> for Value {
+    fn from(entries: BTreeMap<String, T>) -> Self {
+        Value::Map(entries.into_iter().map(|(key, value)| (key, value.into())).collect())
+    }
+}
+
+/// `None` is null.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+fn mismatch(expected: &str, value: &Value) -> RuntimeError {
+    RuntimeError::new(format!("Expected a {} but got a {}", expected, value.type_name()))
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) => Ok(n),
+            other => Err(mismatch("number", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(b) => Ok(b),
+            other => Err(mismatch("boolean", &other)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(mismatch("string", &other)),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = RuntimeError>> TryFrom<Value> for Vec<T> {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::List(items) => items.into_iter().map(T::try_from).collect(),
+            other => Err(mismatch("list", &other)),
+        }
+    }
+}
+
+/// Map keys are strings, and each one is converted to `K` from a string
+/// value, so asking for, say, number keys is an error.
+impl<K, T> TryFrom<Value> for HashMap<K, T>
+where
+    K: TryFrom<Value, Error = RuntimeError> + Eq + Hash,
+    T: TryFrom<Value, Error = RuntimeError>,
+{
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Map(entries) => entries
+                .into_iter()
+                .map(|(key, value)| Ok((K::try_from(Value::String(key))?, T::try_from(value)?)))
+                .collect(),
+            other => Err(mismatch("map", &other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for i64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        i64::try_from(value.clone())
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = RuntimeError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        bool::try_from(value.clone())
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = RuntimeError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        String::try_from(value.clone())
+    }
+}
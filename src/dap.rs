@@ -0,0 +1,455 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use crate::ast::Node;
+use crate::console::Text;
+use crate::debugger::{collect_statement_lines, display, evaluate, StepMode};
+use crate::interpreter::{Hook, Interpreter, RuntimeError};
+use crate::json::Json;
+use crate::lexer;
+use crate::lsp::{read_message, write_message};
+use crate::parser::Parser;
+
+const THREAD_ID: i64 = 1; // programs are single threaded
+const GLOBALS_REFERENCE: i64 = 1;
+
+/// What a request handled while stopped means for the program.
+enum Resume {
+    Stay,
+    Go,
+    Disconnect,
+}
+
+/// The stream to the editor. It is shared with the program's output, which
+/// is sent as events, so it lives outside the session.
+struct Connection {
+    output: Box<dyn Write>,
+    seq: i64,
+}
+
+impl Connection {
+    fn send(&mut self, mut message: Json) {
+        self.seq += 1;
+        if let Json::Object(pairs) = &mut message {
+            pairs.insert(0, ("seq".to_string(), self.seq.into()));
+        }
+        // If the editor went away there's nobody left to tell.
+        let _ = write_message(&mut self.output, &message);
+    }
+
+    fn output(&mut self, category: &str, text: String) {
+        self.send(Json::object(vec![
+            ("type", "event".into()),
+            ("event", "output".into()),
+            ("body", Json::object(vec![
+                ("category", category.into()),
+                ("output", text.into()),
+            ])),
+        ]));
+    }
+}
+
+/// The editor connection plus the debugging state shared between the request
+/// loop and the hook running inside the interpreter.
+struct Session {
+    connection: Rc<RefCell<Connection>>,
+    incoming: Receiver<Json>,
+    program: Option<String>,
+    program_args: Vec<String>,
+    program_input: String,
+    ast: Option<Node>,
+    statement_lines: BTreeSet<usize>,
+    breakpoints: BTreeSet<usize>,
+    mode: StepMode,
+    configured: bool,
+    disconnected: bool,
+}
+
+impl Session {
+    fn send(&mut self, message: Json) {
+        self.connection.borrow_mut().send(message);
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) {
+        self.send(Json::object(vec![
+            ("type", "response".into()),
+            ("request_seq", request.get("seq").cloned().unwrap_or(Json::Null)),
+            ("success", true.into()),
+            ("command", request.get("command").cloned().unwrap_or(Json::Null)),
+            ("body", body),
+        ]));
+    }
+
+    fn respond_error(&mut self, request: &Json, message: String) {
+        self.send(Json::object(vec![
+            ("type", "response".into()),
+            ("request_seq", request.get("seq").cloned().unwrap_or(Json::Null)),
+            ("success", false.into()),
+            ("command", request.get("command").cloned().unwrap_or(Json::Null)),
+            ("message", message.into()),
+        ]));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(Json::object(vec![
+            ("type", "event".into()),
+            ("event", event.into()),
+            ("body", body),
+        ]));
+    }
+
+    fn output(&mut self, category: &str, text: String) {
+        self.connection.borrow_mut().output(category, text);
+    }
+
+    /// Handles requests that make sense whether or not a program is running.
+    /// Returns false if the command isn't one of them.
+    fn handle_common(&mut self, request: &Json) -> bool {
+        let arguments = request.get("arguments").cloned().unwrap_or(Json::Null);
+        match request.get("command").and_then(Json::as_str).unwrap_or("") {
+            "initialize" => {
+                self.respond(request, Json::object(vec![
+                    ("supportsConfigurationDoneRequest", true.into()),
+                    ("supportsEvaluateForHovers", true.into()),
+                ]));
+                self.event("initialized", Json::object(Vec::<(&str, Json)>::new()));
+            }
+            "setBreakpoints" => {
+                let lines: Vec<usize> = arguments
+                    .get("breakpoints")
+                    .and_then(Json::as_array)
+                    .unwrap_or(&[])
+                    .iter()
+                    .filter_map(|bp| bp.get("line").and_then(Json::as_i64))
+                    .map(|line| line as usize)
+                    .collect();
+                self.breakpoints = lines.iter().copied().collect();
+                let breakpoints = lines.iter().map(|&line| {
+                    // Before launch we don't know where statements are yet.
+                    let verified = self.ast.is_none() || self.statement_lines.contains(&line);
+                    Json::object(vec![("verified", verified.into()), ("line", line.into())])
+                }).collect();
+                self.respond(request, Json::object(vec![("breakpoints", Json::Array(breakpoints))]));
+            }
+            "setExceptionBreakpoints" => {
+                self.respond(request, Json::object(vec![("breakpoints", Json::Array(Vec::new()))]));
+            }
+            "threads" => {
+                self.respond(request, Json::object(vec![
+                    ("threads", Json::Array(vec![Json::object(vec![
+                        ("id", THREAD_ID.into()),
+                        ("name", "main".into()),
+                    ])])),
+                ]));
+            }
+            "pause" => {
+                self.mode = StepMode::Pause("pause");
+                self.respond(request, Json::Null);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles a request that arrives while the program is stopped in `interp`.
+    fn handle_stopped(&mut self, request: &Json, interp: &mut Interpreter) -> Resume {
+        if self.handle_common(request) {
+            return Resume::Stay;
+        }
+        let arguments = request.get("arguments").cloned().unwrap_or(Json::Null);
+        let depth = interp.stack.len();
+        match request.get("command").and_then(Json::as_str).unwrap_or("") {
+            "stackTrace" => {
+                let source = self.source();
+                let frames: Vec<Json> = interp.stack.iter().enumerate().rev().map(|(id, frame)| {
+                    Json::object(vec![
+                        ("id", id.into()),
+                        ("name", frame.to_string().into()),
+                        ("line", frame.line.into()),
+                        ("column", 1i64.into()),
+                        ("source", source.clone()),
+                    ])
+                }).collect();
+                let total = frames.len();
+                self.respond(request, Json::object(vec![
+                    ("stackFrames", Json::Array(frames)),
+                    ("totalFrames", total.into()),
+                ]));
+            }
+            "scopes" => {
+                // There is a single flat scope, whichever frame is asked about.
+                self.respond(request, Json::object(vec![
+                    ("scopes", Json::Array(vec![Json::object(vec![
+                        ("name", "Globals".into()),
+                        ("variablesReference", GLOBALS_REFERENCE.into()),
+                        ("expensive", false.into()),
+                    ])])),
+                ]));
+            }
+            "variables" => {
+                let mut names: Vec<&String> = interp.env.keys().collect();
+                names.sort();
+                let variables = names.into_iter().map(|name| {
+                    let value = &interp.env[name];
+                    Json::object(vec![
+                        ("name", name.clone().into()),
+                        ("value", display(value).into()),
+                        ("type", value.type_name().into()),
+                        ("variablesReference", 0i64.into()),
+                    ])
+                }).collect();
+                self.respond(request, Json::object(vec![("variables", Json::Array(variables))]));
+            }
+            "evaluate" => {
+                let expression = arguments.get("expression").and_then(Json::as_str).unwrap_or("");
+                match evaluate(interp, expression) {
+                    Ok(value) => self.respond(request, Json::object(vec![
+                        ("result", display(&value).into()),
+                        ("type", value.type_name().into()),
+                        ("variablesReference", 0i64.into()),
+                    ])),
+                    Err(message) => self.respond_error(request, message),
+                }
+            }
+            "continue" => {
+                self.mode = StepMode::Continue;
+                self.respond(request, Json::object(vec![("allThreadsContinued", true.into())]));
+                return Resume::Go;
+            }
+            "next" => {
+                self.mode = StepMode::StepOver(depth);
+                self.respond(request, Json::Null);
+                return Resume::Go;
+            }
+            "stepIn" => {
+                self.mode = StepMode::StepIn;
+                self.respond(request, Json::Null);
+                return Resume::Go;
+            }
+            "stepOut" => {
+                self.mode = StepMode::StepOut(depth);
+                self.respond(request, Json::Null);
+                return Resume::Go;
+            }
+            "disconnect" | "terminate" => {
+                self.respond(request, Json::Null);
+                return Resume::Disconnect;
+            }
+            command => self.respond_error(request, format!("{} is not supported while stopped", command)),
+        }
+        Resume::Stay
+    }
+
+    fn source(&self) -> Json {
+        let path = self.program.clone().unwrap_or_default();
+        let name = path.rsplit('/').next().unwrap_or("").to_string();
+        Json::object(vec![("name", name.into()), ("path", path.into())])
+    }
+
+    /// Handles `launch`: loads and parses the program, but doesn't start it
+    /// until `configurationDone`.
+    fn launch(&mut self, request: &Json) {
+        let arguments = request.get("arguments").cloned().unwrap_or(Json::Null);
+        let program = match arguments.get("program").and_then(Json::as_str) {
+            Some(program) => program.to_string(),
+            None => return self.respond_error(request, "launch needs a `program` to run".to_string()),
+        };
+        let source = match fs::read_to_string(&program) {
+            Ok(source) => source,
+            Err(e) => return self.respond_error(request, format!("Error reading file {}: {}", program, e)),
+        };
+
+        let mut parser = Parser::with_lines(lexer::tokenize_with_lines(&source));
+        let ast = parser.parse();
+        if !parser.errors().is_empty() {
+            let errors: Vec<String> = parser.errors().iter().map(|e| e.to_string()).collect();
+            return self.respond_error(request, errors.join("\n"));
+        }
+
+        collect_statement_lines(&ast, &mut self.statement_lines);
+        self.ast = Some(ast);
+        self.program = Some(program);
+        self.program_args = arguments
+            .get("args")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|a| a.as_str().map(String::from))
+            .collect();
+        self.program_input = arguments.get("input").and_then(Json::as_str).unwrap_or("").to_string();
+        if arguments.get("stopOnEntry") == Some(&Json::Bool(true)) {
+            self.mode = StepMode::Pause("entry");
+        }
+        self.respond(request, Json::Null);
+    }
+}
+
+/// The interpreter hook: decides whether to stop before a statement and, when
+/// stopped, serves requests until the editor resumes.
+struct DebugHook {
+    session: Rc<RefCell<Session>>,
+}
+
+impl Hook for DebugHook {
+    fn before_statement(&mut self, interp: &mut Interpreter, line: usize, _statement: &Node) -> Result<(), RuntimeError> {
+        let mut session = self.session.borrow_mut();
+
+        // Pick up breakpoint changes and pause requests sent while running.
+        while let Ok(request) = session.incoming.try_recv() {
+            if !session.handle_common(&request) {
+                let command = request.get("command").and_then(Json::as_str).unwrap_or("").to_string();
+                if command == "disconnect" || command == "terminate" {
+                    session.respond(&request, Json::Null);
+                    session.disconnected = true;
+                    return Err(RuntimeError::new("Debugger disconnected"));
+                }
+                session.respond_error(&request, format!("{} is not supported while running", command));
+            }
+        }
+
+        let reason = match session.mode.stop_reason(interp.stack.len(), session.breakpoints.contains(&line)) {
+            Some(reason) => reason,
+            None => return Ok(()),
+        };
+
+        session.event("stopped", Json::object(vec![
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ]));
+        loop {
+            let request = match session.incoming.recv() {
+                Ok(request) => request,
+                Err(_) => Json::object(vec![("command", "disconnect".into())]),
+            };
+            match session.handle_stopped(&request, interp) {
+                Resume::Stay => {}
+                Resume::Go => return Ok(()),
+                Resume::Disconnect => {
+                    session.disconnected = true;
+                    return Err(RuntimeError::new("Debugger disconnected"));
+                }
+            }
+        }
+    }
+}
+
+/// Runs a debug session: configuration requests until `configurationDone`,
+/// then the program under the hook, then requests until `disconnect`.
+/// Returns whether the session ended with a disconnect from the editor.
+pub fn serve(incoming: Receiver<Json>, output: Box<dyn Write>) -> bool {
+    let connection = Rc::new(RefCell::new(Connection { output, seq: 0 }));
+    let session = Rc::new(RefCell::new(Session {
+        connection: Rc::clone(&connection),
+        incoming,
+        program: None,
+        program_args: Vec::new(),
+        program_input: String::new(),
+        ast: None,
+        statement_lines: BTreeSet::new(),
+        breakpoints: BTreeSet::new(),
+        mode: StepMode::Continue,
+        configured: false,
+        disconnected: false,
+    }));
+
+    // Configuration phase.
+    loop {
+        let mut s = session.borrow_mut();
+        if s.configured && s.ast.is_some() {
+            break;
+        }
+        let request = match s.incoming.recv() {
+            Ok(request) => request,
+            Err(_) => return false,
+        };
+        if s.handle_common(&request) {
+            continue;
+        }
+        match request.get("command").and_then(Json::as_str).unwrap_or("") {
+            "launch" => s.launch(&request),
+            "configurationDone" => {
+                s.configured = true;
+                s.respond(&request, Json::Null);
+            }
+            "disconnect" | "terminate" => {
+                s.respond(&request, Json::Null);
+                return true;
+            }
+            command => {
+                let message = format!("{} is not supported before the program starts", command);
+                s.respond_error(&request, message);
+            }
+        }
+    }
+
+    // Run the program with the hook installed.
+    let (ast, args, input) = {
+        let mut s = session.borrow_mut();
+        let input = std::mem::take(&mut s.program_input);
+        (s.ast.take().unwrap_or(Node::Program(Vec::new())), s.program_args.clone(), input)
+    };
+    let mut interp = Interpreter::new();
+    interp.set_args(args);
+    // stdin and stdout carry the protocol. What the program prints goes to the
+    // editor as output events, and it reads the launch request's `input`.
+    interp.set_output(Box::new(move |text: &str| connection.borrow_mut().output("stdout", text.to_string())));
+    interp.set_input(Box::new(Text::new(input)));
+    interp.set_hook(Box::new(DebugHook { session: Rc::clone(&session) }));
+    let result = interp.interpret(&ast);
+
+    let mut s = session.borrow_mut();
+    if s.disconnected {
+        return true;
+    }
+    let exit_code = match result {
+        Ok(()) => interp.exit_code.unwrap_or(0),
+        Err(e) => {
+            s.output("stderr", format!("Runtime error: {}\n", e));
+            3 // same as the CLI's runtime error exit code
+        }
+    };
+    s.event("exited", Json::object(vec![("exitCode", (exit_code as i64).into())]));
+    s.event("terminated", Json::object(Vec::<(&str, Json)>::new()));
+
+    // Wait for the editor to hang up.
+    while let Ok(request) = s.incoming.recv() {
+        if s.handle_common(&request) {
+            continue;
+        }
+        match request.get("command").and_then(Json::as_str).unwrap_or("") {
+            "disconnect" | "terminate" => {
+                s.respond(&request, Json::Null);
+                return true;
+            }
+            command => {
+                let message = format!("{} is not supported after the program exited", command);
+                s.respond_error(&request, message);
+            }
+        }
+    }
+    false
+}
+
+/// Runs a debug session over a reader/writer pair. Requests are read on a
+/// separate thread so `pause` can arrive while the program is running.
+pub fn serve_streams<R: BufRead + Send + 'static>(mut input: R, output: Box<dyn Write>) -> bool {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    serve(receiver, output)
+}
+
+/// Runs a debug session over stdin/stdout.
+pub fn run_stdio() -> bool {
+    serve_streams(BufReader::new(io::stdin()), Box::new(io::stdout()))
+}
@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+use crate::ast::Node;
+use crate::interpreter::{Hook, Interpreter, RuntimeError, Value};
+use crate::lexer;
+use crate::parser::Parser;
+
+/// When a debugger should next stop, decided before each statement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+    Continue,
+    StepIn,
+    StepOver(usize), // stop at the next statement at this frame depth or shallower
+    StepOut(usize),  // stop at the next statement shallower than this depth
+    Pause(&'static str),
+}
+
+impl StepMode {
+    /// Why to stop before a statement at frame `depth`, if at all.
+    pub fn stop_reason(self, depth: usize, at_breakpoint: bool) -> Option<&'static str> {
+        match self {
+            StepMode::Pause(reason) => Some(reason),
+            _ if at_breakpoint => Some("breakpoint"),
+            StepMode::StepIn => Some("step"),
+            StepMode::StepOver(d) if depth <= d => Some("step"),
+            StepMode::StepOut(d) if depth < d => Some("step"),
+            _ => None,
+        }
+    }
+}
+
+/// Shows a value the way a debugger would: strings quoted.
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        _ => value.to_string(),
+    }
+}
+
+/// Parses and evaluates an expression typed by the user in the current scope.
+pub fn evaluate(interp: &mut Interpreter, expression: &str) -> Result<Value, String> {
+    let mut parser = Parser::new(lexer::tokenize(expression));
+    let expr = parser.parse_standalone_expression();
+    match expr {
+        Some(expr) if parser.errors().is_empty() => interp.evaluate_expression(&expr).map_err(|e| e.to_string()),
+        _ => Err(parser
+            .errors()
+            .first()
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| "Expected an expression".to_string())),
+    }
+}
+
+/// Every line a statement starts on, i.e. where a breakpoint can hit.
+pub fn collect_statement_lines(node: &Node, lines: &mut BTreeSet<usize>) {
+    match node {
+        Node::Program(statements) | Node::Block(statements) => {
+            for stmt in statements {
+                collect_statement_lines(stmt, lines);
+            }
+        }
+        Node::Statement { line, statement } => {
+            lines.insert(*line);
+            collect_statement_lines(statement, lines);
+        }
+        Node::GoOutside { then_branch, else_branch, .. } => {
+            collect_statement_lines(then_branch, lines);
+            if let Some(else_branch) = else_branch {
+                collect_statement_lines(else_branch, lines);
+            }
+        }
+        Node::WhileLoop { body, .. } => collect_statement_lines(body, lines),
+        _ => {}
+    }
+}
+
+/// The error used to stop the program when the user quits the debugger.
+pub const STOPPED: &str = "Stopped by the debugger";
+
+const HELP: &str = "Commands:
+    s, step            run to the next statement, entering loops and branches
+    n, next            run to the next statement in this block
+    o, out             run until the current block finishes
+    c, continue        run until a breakpoint or watch triggers
+    b, break <line>    set a breakpoint
+    d, delete <line>   remove a breakpoint
+    breakpoints        list breakpoints
+    p, print <expr>    evaluate an expression in the current scope
+    w, watch <name>    show a variable at every stop and stop when it changes
+    unwatch <name>     stop watching a variable
+    v, vars            show all variables
+    bt, where          show the block stack
+    l, list            show the source around the current line
+    q, quit            stop the program
+    h, help            show this message";
+
+/// The interactive `touch-grass debug` session, reading commands from stdin.
+pub struct CliDebugger {
+    source_lines: Vec<String>,
+    statement_lines: BTreeSet<usize>,
+    breakpoints: BTreeSet<usize>,
+    watches: BTreeMap<String, String>, // name -> last value shown
+    mode: StepMode,
+}
+
+impl CliDebugger {
+    pub fn new(source: &str, ast: &Node) -> Self {
+        let mut statement_lines = BTreeSet::new();
+        collect_statement_lines(ast, &mut statement_lines);
+        CliDebugger {
+            source_lines: source.lines().map(String::from).collect(),
+            statement_lines,
+            breakpoints: BTreeSet::new(),
+            watches: BTreeMap::new(),
+            mode: StepMode::Pause("entry"),
+        }
+    }
+
+    fn watch_value(interp: &Interpreter, name: &str) -> String {
+        interp.env.get(name).map_or_else(|| "<undefined>".to_string(), display)
+    }
+
+    fn show_line(&self, line: usize) {
+        if let Some(text) = self.source_lines.get(line.wrapping_sub(1)) {
+            println!("{:>4} -> {}", line, text);
+        }
+    }
+
+    fn list(&self, line: usize) {
+        let first = line.saturating_sub(3).max(1);
+        let last = (line + 3).min(self.source_lines.len());
+        for n in first..=last {
+            let marker = if n == line { "->" } else if self.breakpoints.contains(&n) { " *" } else { "  " };
+            println!("{:>4} {} {}", n, marker, self.source_lines[n - 1]);
+        }
+    }
+
+    /// Runs one command. Returns `Some` when the program should resume (or
+    /// stop, on quit) and `None` to keep reading commands.
+    fn command(&mut self, input: &str, interp: &mut Interpreter, line: usize) -> Option<Result<(), RuntimeError>> {
+        let input = input.trim();
+        let (command, rest) = match input.split_once(char::is_whitespace) {
+            Some((command, rest)) => (command, rest.trim()),
+            None => (input, ""),
+        };
+        let depth = interp.stack.len();
+
+        match command {
+            "s" | "step" => self.mode = StepMode::StepIn,
+            "n" | "next" => self.mode = StepMode::StepOver(depth),
+            "o" | "out" => self.mode = StepMode::StepOut(depth),
+            "c" | "continue" => self.mode = StepMode::Continue,
+            "q" | "quit" => return Some(Err(RuntimeError::new(STOPPED))),
+            "b" | "break" => match rest.parse::<usize>() {
+                Ok(n) if self.statement_lines.contains(&n) => {
+                    self.breakpoints.insert(n);
+                    println!("Breakpoint set at line {}", n);
+                }
+                Ok(n) => println!("No statement starts on line {}", n),
+                Err(_) => println!("Usage: break <line>"),
+            },
+            "d" | "delete" => match rest.parse::<usize>() {
+                Ok(n) if self.breakpoints.remove(&n) => println!("Breakpoint at line {} removed", n),
+                Ok(n) => println!("No breakpoint at line {}", n),
+                Err(_) => println!("Usage: delete <line>"),
+            },
+            "breakpoints" => {
+                if self.breakpoints.is_empty() {
+                    println!("No breakpoints");
+                }
+                for n in &self.breakpoints {
+                    self.show_line(*n);
+                }
+            }
+            "p" | "print" => match evaluate(interp, rest) {
+                Ok(value) => println!("{}", display(&value)),
+                Err(e) => println!("Error: {}", e),
+            },
+            "w" | "watch" if !rest.is_empty() => {
+                let value = Self::watch_value(interp, rest);
+                println!("Watching {} = {}", rest, value);
+                self.watches.insert(rest.to_string(), value);
+            }
+            "unwatch" => {
+                if self.watches.remove(rest).is_none() {
+                    println!("Not watching {}", rest);
+                }
+            }
+            "v" | "vars" => {
+                let mut names: Vec<&String> = interp.env.keys().collect();
+                names.sort();
+                for name in names {
+                    println!("{} = {}", name, display(&interp.env[name]));
+                }
+            }
+            "bt" | "where" => {
+                for (i, frame) in interp.stack.iter().enumerate().rev() {
+                    println!("#{} {} at line {}", interp.stack.len() - 1 - i, frame, frame.line);
+                }
+            }
+            "l" | "list" => self.list(line),
+            "h" | "help" => println!("{}", HELP),
+            "" => {}
+            _ => println!("Unknown command: {} (try `help`)", input),
+        }
+
+        match command {
+            "s" | "step" | "n" | "next" | "o" | "out" | "c" | "continue" => Some(Ok(())),
+            _ => None,
+        }
+    }
+}
+
+impl Hook for CliDebugger {
+    fn before_statement(&mut self, interp: &mut Interpreter, line: usize, _statement: &Node) -> Result<(), RuntimeError> {
+        let mut changed = Vec::new();
+        for (name, last) in self.watches.iter_mut() {
+            let now = Self::watch_value(interp, name);
+            if *last != now {
+                changed.push(format!("{}: {} -> {}", name, last, now));
+                *last = now;
+            }
+        }
+
+        let reason = if changed.is_empty() {
+            self.mode.stop_reason(interp.stack.len(), self.breakpoints.contains(&line))
+        } else {
+            Some("watch")
+        };
+        let reason = match reason {
+            Some(reason) => reason,
+            None => return Ok(()),
+        };
+
+        match reason {
+            "breakpoint" => println!("Breakpoint hit at line {}", line),
+            "watch" => {
+                for change in changed {
+                    println!("Watch {}", change);
+                }
+            }
+            _ => {}
+        }
+        self.show_line(line);
+        for (name, value) in &self.watches {
+            println!("    {} = {}", name, value);
+        }
+
+        let stdin = io::stdin();
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().unwrap();
+            let mut input = String::new();
+            match stdin.lock().read_line(&mut input) {
+                Ok(0) | Err(_) => return Err(RuntimeError::new(STOPPED)),
+                Ok(_) => {}
+            }
+            if let Some(result) = self.command(&input, interp, line) {
+                return result;
+            }
+        }
+    }
+}
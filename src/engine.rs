@@ -0,0 +1,246 @@
+use crate::ast::Node;
+use crate::console::{Input, Output};
+use crate::interpreter::{ErrorKind, Frame, Hook, Interpreter, RuntimeError, Value};
+use crate::lexer;
+use crate::parser::{ParseError, Parser};
+use crate::sandbox::{Interrupt, LimitKind, Limits, Permissions};
+
+/// Why running or evaluating source failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The source didn't parse; nothing ran.
+    Parse(Vec<ParseError>),
+    /// The program stopped with a runtime error. `line` is the statement that
+    /// failed, or 0 when it isn't known (e.g. in `eval`).
+    Runtime { message: String, line: usize },
+    /// The program ran into one of the limits set with `set_limits`.
+    Limit { kind: LimitKind, message: String, line: usize },
+    /// The program was stopped through `interrupt_handle`.
+    Interrupted { line: usize },
+}
+
+impl Error {
+    fn runtime(error: RuntimeError, line: usize) -> Error {
+        match error.kind {
+            ErrorKind::Program => Error::Runtime { message: error.message, line },
+            ErrorKind::Limit(kind) => Error::Limit { kind, message: error.message, line },
+            ErrorKind::Interrupted => Error::Interrupted { line },
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            Error::Runtime { message, line } | Error::Limit { message, line, .. } if *line > 0 => {
+                write!(f, "line {}: {}", line, message)
+            }
+            Error::Runtime { message, .. } | Error::Limit { message, .. } => write!(f, "{}", message),
+            Error::Interrupted { line } if *line > 0 => write!(f, "line {}: Interrupted", line),
+            Error::Interrupted { .. } => write!(f, "Interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Runs Touch Grass code from Rust. Globals live as long as the engine, so
+/// each `run` or `eval` sees what earlier ones left behind, like lines typed
+/// into the REPL.
+///
+/// ```
+/// use touch_grass::{Engine, Value};
+///
+/// let mut engine = Engine::new();
+/// engine.set_global("limit", 3);
+/// engine.run("touch grass number i as 0\nwhile i < limit then\n    set i to i + 1\nfr fr")?;
+/// assert_eq!(engine.global("i"), Some(&Value::Number(3)));
+/// assert_eq!(engine.eval("i + 1")?, Value::Number(4));
+/// # Ok::<(), touch_grass::Error>(())
+/// ```
+pub struct Engine {
+    interp: Interpreter,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    /// An engine with no globals except an empty `args` list.
+    pub fn new() -> Self {
+        Engine { interp: Interpreter::new() }
+    }
+
+    /// Sets the `args` list programs see.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.interp.set_args(args);
+    }
+
+    /// Observes and steers every program the engine runs from now on.
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.interp.set_hook(hook);
+    }
+
+    /// Sends what programs print to `output` instead of stdout, e.g. a
+    /// `console::Buffer` or a closure taking `&str`.
+    pub fn set_output(&mut self, output: impl Output + 'static) {
+        self.interp.set_output(Box::new(output));
+    }
+
+    /// Makes programs read from `input` instead of stdin, e.g. a
+    /// `console::Text` or a closure returning one line per call.
+    pub fn set_input(&mut self, input: impl Input + 'static) {
+        self.interp.set_input(Box::new(input));
+    }
+
+    /// Lets programs use the filesystem builtins within the granted
+    /// directories. Without this, every such call is a runtime error.
+    ///
+    /// ```
+    /// use touch_grass::sandbox::Permissions;
+    /// use touch_grass::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    /// assert!(engine.eval("file_exists(\".\")").is_err());
+    /// let mut permissions = Permissions::new();
+    /// permissions.allow_read(".").unwrap();
+    /// engine.set_permissions(permissions);
+    /// assert!(engine.eval("file_exists(\".\")").is_ok());
+    /// ```
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.interp.set_permissions(permissions);
+    }
+
+    /// Caps the work each `run` or `eval` may do, for programs that can't
+    /// be trusted to finish. A program that hits one fails with `Error::Limit`.
+    ///
+    /// ```
+    /// use touch_grass::sandbox::{LimitKind, Limits};
+    /// use touch_grass::{Engine, Error};
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.set_limits(Limits { max_steps: Some(1000), ..Limits::default() });
+    /// let error = engine.run("while 1 then\n    print 1\nfr fr").unwrap_err();
+    /// assert!(matches!(error, Error::Limit { kind: LimitKind::Steps, .. }));
+    /// assert!(error.to_string().contains("Step limit exceeded"));
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interp.set_limits(limits);
+    }
+
+    /// A handle that stops the running program from another thread. The run
+    /// fails with `Error::Interrupted`.
+    ///
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    /// use touch_grass::{Engine, Error};
+    ///
+    /// let mut engine = Engine::new();
+    /// let interrupt = engine.interrupt_handle();
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(50));
+    ///     interrupt.interrupt();
+    /// });
+    /// let result = engine.run("touch grass number i as 0\nwhile no_cap then\n    set i to i + 1\nfr fr");
+    /// assert!(matches!(result, Err(Error::Interrupted { .. })));
+    /// ```
+    pub fn interrupt_handle(&self) -> Interrupt {
+        self.interp.interrupt_handle()
+    }
+
+    /// Listens to `interrupt` instead of the engine's own handle, so one
+    /// handle can stop whichever of several engines is running.
+    pub fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.interp.set_interrupt(interrupt);
+    }
+
+    /// Parses and runs a program.
+    pub fn run(&mut self, source: &str) -> Result<(), Error> {
+        let mut parser = Parser::with_lines(lexer::tokenize_with_lines(source));
+        let program = parser.parse();
+        if !parser.errors().is_empty() {
+            return Err(Error::Parse(parser.errors().to_vec()));
+        }
+        self.run_program(&program)
+    }
+
+    /// Runs a program that's already parsed.
+    pub fn run_program(&mut self, program: &Node) -> Result<(), Error> {
+        self.interp
+            .interpret(program)
+            .map_err(|e| Error::runtime(e, self.interp.stack.last().map_or(0, |frame| frame.line)))
+    }
+
+    /// Evaluates a single expression, e.g. `len(args) + 1`, against the globals.
+    pub fn eval(&mut self, expression: &str) -> Result<Value, Error> {
+        let mut parser = Parser::with_lines(lexer::tokenize_with_lines(expression));
+        let expr = parser.parse_standalone_expression();
+        match expr {
+            Some(expr) if parser.errors().is_empty() => {
+                self.interp.evaluate_expression(&expr).map_err(|e| Error::runtime(e, 0))
+            }
+            _ => Err(Error::Parse(parser.errors().to_vec())),
+        }
+    }
+
+    /// The value of a global, if it has been declared.
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.interp.env.get(name)
+    }
+
+    /// Declares a global, or changes it if it exists.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        self.interp.env.insert(name.to_string(), value.into());
+    }
+
+    /// Makes a Rust function callable from scripts as `name(args...)`,
+    /// in place of any builtin with that name.
+    ///
+    /// ```
+    /// use touch_grass::{Engine, Value};
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.register("double", |args: &[Value]| {
+    ///     let n = i64::try_from(args.first().unwrap_or(&Value::Null))?;
+    ///     Ok(Value::from(n * 2))
+    /// });
+    /// assert_eq!(engine.eval("double(21)")?, Value::Number(42));
+    /// assert!(engine.eval("double(\"x\")").is_err());
+    /// # Ok::<(), touch_grass::Error>(())
+    /// ```
+    pub fn register<F>(&mut self, name: &str, function: F)
+    where
+        F: FnMut(&[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        self.interp.register(name, function);
+    }
+
+    /// Every global and its value, in no particular order.
+    pub fn globals(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.interp.env.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Where the last run was, innermost block last. After a runtime error
+    /// this is where it happened.
+    pub fn stack(&self) -> &[Frame] {
+        &self.interp.stack
+    }
+
+    /// What the program asked to exit with via `exit_code(n)`, if anything.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.interp.exit_code
+    }
+}
@@ -0,0 +1,140 @@
+use crate::ast::{Node, Operator};
+use crate::lexer;
+use crate::parser::Parser;
+
+const INDENT: &str = "    ";
+
+/// Formats source text, keeping its comments. Fails with the parse errors if
+/// the source doesn't parse, and refuses to return output that would parse to
+/// a different program than the input.
+pub fn format_source(source: &str) -> Result<String, Vec<String>> {
+    let mut parser = Parser::with_lines(lexer::tokenize_with_lines(source));
+    let ast = parser.parse();
+    if !parser.errors().is_empty() {
+        return Err(parser.errors().iter().map(|e| e.to_string()).collect());
+    }
+
+    let formatted = format_program(&ast);
+    if parse_code(&formatted) != parse_code(source) {
+        return Err(vec!["Formatting would change the meaning of the program; leaving it alone".to_string()]);
+    }
+    Ok(formatted)
+}
+
+/// Parses just the code, ignoring comments, for comparing two sources.
+fn parse_code(source: &str) -> Node {
+    Parser::new(lexer::tokenize(source)).parse()
+}
+
+/// Turns a parsed program back into canonical source: one statement per line,
+/// block bodies indented by four spaces, single spaces around operators.
+pub fn format_program(node: &Node) -> String {
+    let mut out = String::new();
+    format_statement(node, 0, &mut out);
+    out
+}
+
+fn format_statement(node: &Node, depth: usize, out: &mut String) {
+    match node {
+        Node::Program(statements) | Node::Block(statements) => {
+            for stmt in statements {
+                format_statement(stmt, depth, out);
+            }
+        }
+        Node::Statement { statement, .. } => format_statement(statement, depth, out),
+        Node::GoOutside { then_branch, else_branch, .. } => {
+            line(out, depth, &statement_header(node));
+            format_statement(then_branch, depth + 1, out);
+            if let Some(else_branch) = else_branch {
+                line(out, depth, "fr fr instead");
+                format_statement(else_branch, depth + 1, out);
+            }
+            line(out, depth, "fr fr");
+        }
+        Node::WhileLoop { body, .. } => {
+            line(out, depth, &statement_header(node));
+            format_statement(body, depth + 1, out);
+            line(out, depth, "fr fr");
+        }
+        Node::Comment { text, trailing } => {
+            if *trailing && out.ends_with('\n') {
+                out.pop();
+                out.push(' ');
+                out.push_str(text);
+                out.push('\n');
+            } else {
+                line(out, depth, text);
+            }
+        }
+        other => line(out, depth, &statement_header(other)),
+    }
+}
+
+/// The first line of a statement as it would be formatted, e.g.
+/// `while i < 3 then` for a loop. Used to label statements in traces.
+pub fn statement_header(node: &Node) -> String {
+    match node {
+        Node::Statement { statement, .. } => statement_header(statement),
+        Node::VarDeclaration { name, var_type, value } => {
+            format!("touch grass {} {} as {}", var_type, name, format_expression(value))
+        }
+        Node::Assignment { name, value } => format!("set {} to {}", name, format_expression(value)),
+        Node::Print(expr) => format!("print {}", format_expression(expr)),
+        Node::GoOutside { condition, .. } => format!("go outside if {} then", format_expression(condition)),
+        Node::WhileLoop { condition, .. } => format!("while {} then", format_expression(condition)),
+        Node::Comment { text, .. } => text.clone(),
+        expr => format_expression(expr),
+    }
+}
+
+/// Formats an expression on a single line, adding parentheses only where the
+/// tree can't be expressed by precedence and left-associativity alone.
+pub fn format_expression(node: &Node) -> String {
+    match node {
+        Node::Identifier(name) => name.clone(),
+        Node::StringLiteral(s) => format!("\"{}\"", s),
+        // Source has no negative literals, but constant folding makes them.
+        Node::NumberLiteral(n) if *n < 0 => match n.checked_neg() {
+            Some(positive) => format!("0 - {}", positive),
+            None => format!("0 - {} - 1", i64::MAX),
+        },
+        Node::NumberLiteral(n) => n.to_string(),
+        Node::Boolean(true) => "no_cap".to_string(),
+        Node::Boolean(false) => "cap".to_string(),
+        Node::Bugatti => "bugatti".to_string(),
+        Node::BinaryOp { left, operator, right } => {
+            let precedence = operator.precedence();
+            let left = match operator_precedence(left) {
+                Some(inner) if inner < precedence => format!("({})", format_expression(left)),
+                _ => format_expression(left),
+            };
+            let right = match operator_precedence(right) {
+                Some(inner) if inner <= precedence => format!("({})", format_expression(right)),
+                _ => format_expression(right),
+            };
+            format!("{} {} {}", left, operator.symbol(), right)
+        }
+        Node::Call { name, args } => {
+            let args: Vec<String> = args.iter().map(format_expression).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        _ => String::new(),
+    }
+}
+
+/// The precedence of the operator an expression is printed with, if any.
+fn operator_precedence(node: &Node) -> Option<u8> {
+    match node {
+        Node::BinaryOp { operator, .. } => Some(operator.precedence()),
+        Node::NumberLiteral(n) if *n < 0 => Some(Operator::Minus.precedence()),
+        _ => None,
+    }
+}
+
+fn line(out: &mut String, depth: usize, text: &str) {
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+    out.push_str(text);
+    out.push('\n');
+}
@@ -0,0 +1,177 @@
+use crate::lexer::Lexer;
+use crate::token::Token;
+
+/// The highlighting class a token falls into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Keyword,
+    String,
+    Number,
+    Literal, // no_cap, cap, bugatti
+    Operator,
+    Identifier,
+    Comment,
+    Illegal,
+}
+
+impl Style {
+    fn ansi(self) -> &'static str {
+        match self {
+            Style::Keyword => "\x1b[1;35m",
+            Style::String => "\x1b[32m",
+            Style::Number => "\x1b[33m",
+            Style::Literal => "\x1b[1;33m",
+            Style::Operator => "\x1b[36m",
+            Style::Identifier => "",
+            Style::Comment => "\x1b[2;37m",
+            Style::Illegal => "\x1b[1;37;41m",
+        }
+    }
+
+    fn css_class(self) -> &'static str {
+        match self {
+            Style::Keyword => "kw",
+            Style::String => "str",
+            Style::Number => "num",
+            Style::Literal => "lit",
+            Style::Operator => "op",
+            Style::Identifier => "id",
+            Style::Comment => "cm",
+            Style::Illegal => "err",
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+pub fn classify(token: &Token) -> Style {
+    match token {
+        Token::Print
+        | Token::TouchGrass
+        | Token::As
+        | Token::NumberType
+        | Token::Go
+        | Token::Outside
+        | Token::If
+        | Token::Then
+        | Token::Instead
+        | Token::FrFr
+        | Token::Set
+        | Token::To
+        | Token::While => Style::Keyword,
+        Token::NoCap | Token::Cap | Token::Bugatti => Style::Literal,
+        Token::String(_) => Style::String,
+        Token::Number(_) => Style::Number,
+        Token::GreaterThan
+        | Token::LessThan
+        | Token::Equals
+        | Token::Plus
+        | Token::Minus
+        | Token::LeftParen
+        | Token::RightParen
+        | Token::Comma => Style::Operator,
+        Token::Identifier(_) | Token::EOF => Style::Identifier,
+        Token::Comment(_) => Style::Comment,
+        Token::Illegal(_) => Style::Illegal,
+    }
+}
+
+/// Splits the source into (style, text) segments. Text between tokens
+/// (whitespace) comes back with `None` so the output preserves the layout exactly.
+pub fn segments(source: &str) -> Vec<(Option<Style>, String)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lexer = Lexer::new(source.to_string());
+    let mut segments = Vec::new();
+    let mut last = 0;
+
+    loop {
+        let (token, span) = lexer.next_token_with_span();
+        if token == Token::EOF {
+            break;
+        }
+        if span.start > last {
+            segments.push((None, chars[last..span.start].iter().collect()));
+        }
+        segments.push((Some(classify(&token)), chars[span.clone()].iter().collect()));
+        last = span.end;
+    }
+    if last < chars.len() {
+        segments.push((None, chars[last..].iter().collect()));
+    }
+
+    segments
+}
+
+/// Renders the source with ANSI color escapes for a terminal.
+pub fn to_ansi(source: &str) -> String {
+    let mut out = String::new();
+    for (style, text) in segments(source) {
+        match style {
+            Some(style) if !style.ansi().is_empty() => {
+                out.push_str(style.ansi());
+                out.push_str(&text);
+                out.push_str(ANSI_RESET);
+            }
+            _ => out.push_str(&text),
+        }
+    }
+    out
+}
+
+/// Renders the source as a standalone HTML document.
+pub fn to_html(source: &str, title: &str) -> String {
+    let mut body = String::new();
+    for (style, text) in segments(source) {
+        match style {
+            Some(style) => {
+                body.push_str(&format!(
+                    "<span class=\"{}\">{}</span>",
+                    style.css_class(),
+                    escape_html(&text)
+                ));
+            }
+            None => body.push_str(&escape_html(&text)),
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>
+body {{ background: #1e1f1c; color: #e8e8e3; }}
+pre {{ font-family: monospace; font-size: 14px; line-height: 1.4; }}
+.kw {{ color: #c678dd; font-weight: bold; }}
+.str {{ color: #98c379; }}
+.num {{ color: #e5c07b; }}
+.lit {{ color: #d19a66; font-weight: bold; }}
+.op {{ color: #56b6c2; }}
+.cm {{ color: #7f848e; font-style: italic; }}
+.err {{ color: #ffffff; background: #e06c75; }}
+</style>
+</head>
+<body>
+<pre><code>{}</code></pre>
+</body>
+</html>
+",
+        escape_html(title),
+        body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -1,154 +1,496 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use crate::ast::{Node, Operator};
+use crate::builtins;
+use crate::console::{Input, Output, Stdin, Stdout};
+use crate::sandbox::{Interrupt, LimitKind, Limits, Meter, Permissions};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Number(i64),
     String(String),
     Boolean(bool),
+    List(Vec<Value>),
+    /// Scripts can't write one; they come from native functions and globals
+    /// set by an embedding program.
+    Map(BTreeMap<String, Value>),
     Null,
 }
 
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Null => "null",
+        }
+    }
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Boolean(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match item {
+                        Value::String(s) => write!(f, "\"{}\"", s)?,
+                        _ => write!(f, "{}", item)?,
+                    }
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match value {
+                        Value::String(s) => write!(f, "\"{}\": \"{}\"", key, s)?,
+                        _ => write!(f, "\"{}\": {}", key, value)?,
+                    }
+                }
+                write!(f, "}}")
+            }
             Value::Null => write!(f, "null"),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub kind: ErrorKind,
+}
+
+/// Why a program stopped with a `RuntimeError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorKind {
+    /// The program did something wrong, e.g. used an undefined variable.
+    #[default]
+    Program,
+    /// The program ran into one of the sandbox's `Limits`.
+    Limit(LimitKind),
+    /// The program was stopped through its `Interrupt`.
+    Interrupted,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        RuntimeError { message: message.into(), kind: ErrorKind::Program }
+    }
+
+    pub fn limit(kind: LimitKind, message: impl Into<String>) -> Self {
+        RuntimeError { message: message.into(), kind: ErrorKind::Limit(kind) }
+    }
+
+    pub fn interrupted() -> Self {
+        RuntimeError { message: "Interrupted".to_string(), kind: ErrorKind::Interrupted }
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// One level of the block stack: the program itself, or the body of a loop or
+/// branch being run. `line` is the statement currently executing in it.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// `main`, `while`, `go outside` or `instead`.
+    pub kind: &'static str,
+    /// The line of the statement that opened the block; 0 for `main`.
+    pub opened_at: usize,
+    pub line: usize,
+}
+
+/// How stack traces show a frame, e.g. `while (line 3)`.
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.opened_at > 0 {
+            write!(f, "{} (line {})", self.kind, self.opened_at)
+        } else {
+            write!(f, "{}", self.kind)
+        }
+    }
+}
+
+/// Something that happened while running, reported to `Hook::event`.
+pub enum Event<'a> {
+    BinaryOp { left: &'a Value, operator: &'a Operator, right: &'a Value, result: &'a Value },
+    VariableWrite { name: &'a str, value: &'a Value },
+    /// A loop is about to run its body for the n-th time (starting at 1).
+    LoopIteration(usize),
+    /// A loop finished after running its body n times.
+    LoopEnd(usize),
+}
+
+/// Lets tools observe and steer execution. The hook is taken out of the
+/// interpreter while it runs, so it can freely inspect and evaluate in `interp`.
+pub trait Hook {
+    /// Called before each statement that has a source line. Blocking here
+    /// pauses the program; returning an error stops it.
+    fn before_statement(&mut self, interp: &mut Interpreter, line: usize, statement: &Node) -> Result<(), RuntimeError>;
+
+    /// Called after a statement that has a source line finished without error.
+    fn after_statement(&mut self, _line: usize) {}
+
+    /// Called once when the program stops, whether it finished or failed.
+    fn finish(&mut self) {}
+
+    /// Called as values are computed and stored. `depth` is the number of
+    /// frames on the stack.
+    fn event(&mut self, _depth: usize, _event: Event) {}
+}
+
+/// A Rust function scripts call like a builtin, e.g. `lookup("key")`.
+pub type NativeFunction = Box<dyn FnMut(&[Value]) -> Result<Value, RuntimeError>>;
+
 pub struct Interpreter {
     pub env: HashMap<String, Value>,
+    /// Set by the `exit_code` builtin; the CLI exits with it after the program finishes.
+    pub exit_code: Option<i32>,
+    pub stack: Vec<Frame>,
+    hook: Option<Box<dyn Hook>>,
+    natives: HashMap<String, NativeFunction>,
+    output: Box<dyn Output>,
+    input: Box<dyn Input>,
+    permissions: Permissions,
+    meter: Meter,
+    interrupt: Interrupt,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Interpreter {
+        let mut interp = Interpreter {
             env: HashMap::new(),
+            exit_code: None,
+            stack: Vec::new(),
+            hook: None,
+            natives: HashMap::new(),
+            output: Box::new(Stdout),
+            input: Box::new(Stdin),
+            permissions: Permissions::new(),
+            meter: Meter::default(),
+            interrupt: Interrupt::new(),
+        };
+        interp.set_args(Vec::new());
+        interp
+    }
+
+    /// Exposes command-line arguments to the program as the `args` list.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        let args = args.into_iter().map(Value::String).collect();
+        self.env.insert("args".to_string(), Value::List(args));
+    }
+
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.hook = Some(hook);
+    }
+
+    /// Sends `print` and prompts somewhere other than stdout.
+    pub fn set_output(&mut self, output: Box<dyn Output>) {
+        self.output = output;
+    }
+
+    /// Reads from somewhere other than stdin.
+    pub fn set_input(&mut self, input: Box<dyn Input>) {
+        self.input = input;
+    }
+
+    /// What the filesystem builtins may reach. Nothing, until this is called.
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.permissions = permissions;
+    }
+
+    /// Caps how much work each run may do. There are none by default.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    /// A handle that stops the running program from another thread.
+    pub fn interrupt_handle(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    /// Listens to `interrupt` instead of the interpreter's own handle, e.g.
+    /// to stop several interpreters with one.
+    pub fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt = interrupt;
+    }
+
+    /// What the program reads from.
+    pub fn input(&mut self) -> &mut dyn Input {
+        self.input.as_mut()
+    }
+
+    /// Makes a Rust function callable from scripts as `name(args...)`. It
+    /// takes the place of a builtin with the same name.
+    pub fn register<F>(&mut self, name: &str, function: F)
+    where
+        F: FnMut(&[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        self.natives.insert(name.to_string(), Box::new(function));
+    }
+
+    /// Evaluates a standalone expression against the current variables.
+    pub fn evaluate_expression(&mut self, node: &Node) -> Result<Value, RuntimeError> {
+        let started = self.meter.begin();
+        let result = self.evaluate(node);
+        if started {
+            self.meter.end();
+        }
+        result
+    }
+
+    pub fn interpret(&mut self, node: &Node) -> Result<(), RuntimeError> {
+        self.stack = vec![Frame { kind: "main", opened_at: 0, line: 0 }];
+        let started = self.meter.begin();
+        let result = self.run_program(node);
+        if started {
+            self.meter.end();
+        }
+        if let Some(hook) = self.hook.as_mut() {
+            hook.finish();
         }
+        result
     }
 
-    pub fn interpret(&mut self, node: Node) {
+    fn run_program(&mut self, node: &Node) -> Result<(), RuntimeError> {
         match node {
             Node::Program(statements) => {
                 for stmt in statements {
-                    self.execute(stmt);
+                    self.execute(stmt)?;
                 }
             }
-            _ => { self.execute(node); }
+            _ => { self.execute(node)?; }
         }
+        Ok(())
     }
 
-    fn execute(&mut self, node: Node) -> Value {
-        match node {
+    fn execute(&mut self, node: &Node) -> Result<Value, RuntimeError> {
+        self.interrupt.check()?;
+        self.meter.enter()?;
+        let result = self.execute_node(node);
+        self.meter.leave();
+        result
+    }
+
+    fn execute_node(&mut self, node: &Node) -> Result<Value, RuntimeError> {
+        let value = match node {
+            Node::Statement { line, statement } => {
+                if let Some(frame) = self.stack.last_mut() {
+                    frame.line = *line;
+                }
+                if let Some(mut hook) = self.hook.take() {
+                    let result = hook.before_statement(self, *line, statement);
+                    self.hook = Some(hook);
+                    result?;
+                }
+                let value = self.execute(statement)?;
+                if let Some(hook) = self.hook.as_mut() {
+                    hook.after_statement(*line);
+                }
+                value
+            }
             Node::VarDeclaration { name, var_type: _, value } => {
-                let val = self.evaluate(*value);
-                self.env.insert(name.clone(), val.clone());
-                val
+                let val = self.evaluate(value)?;
+                self.notify(Event::VariableWrite { name, value: &val });
+                // Redeclaring reuses the entry, so a loop body doesn't allocate a new key.
+                match self.env.get_mut(name) {
+                    Some(slot) => *slot = val,
+                    None => {
+                        self.env.insert(name.clone(), val);
+                    }
+                }
+                Value::Null
             }
             Node::Assignment { name, value } => {
-                let val = self.evaluate(*value);
-                if self.env.contains_key(&name) {
-                    self.env.insert(name, val.clone());
-                } else {
-                    eprintln!("Undefined variable: {}", name);
+                let val = self.evaluate(value)?;
+                if !self.env.contains_key(name) {
+                    return Err(RuntimeError::new(format!("Undefined variable: {}", name)));
                 }
-                val
+                self.notify(Event::VariableWrite { name, value: &val });
+                if let Some(slot) = self.env.get_mut(name) {
+                    *slot = val;
+                }
+                Value::Null
             }
             Node::Print(expr) => {
-                let val = self.evaluate(*expr);
-                println!("{}", val);
+                let val = self.evaluate(expr)?;
+                self.output.write(&format!("{}\n", val));
                 val
             }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                let cond_value = self.evaluate(condition)?;
+                if is_truthy(&cond_value) {
+                    self.execute_in_frame("go outside", then_branch)?
+                } else if let Some(else_branch) = else_branch {
+                    self.execute_in_frame("instead", else_branch)?
+                } else {
+                    Value::Null
+                }
+            }
             Node::WhileLoop { condition, body } => {
+                self.push_frame("while");
+                let mut iterations = 0;
                 loop {
                     // Evaluate the condition and store the result
-                    let cond_value = self.evaluate((*condition).clone());
+                    let cond_value = self.evaluate(condition)?;
                     // If the condition is false, break out of the loop
-                    if !self.is_truthy(cond_value) {
+                    if !is_truthy(&cond_value) {
                         break;
                     }
+                    iterations += 1;
+                    self.notify(Event::LoopIteration(iterations));
                     // Execute the body of the loop
-                    self.execute((*body).clone());
+                    self.execute(body)?;
                 }
+                self.notify(Event::LoopEnd(iterations));
+                self.stack.pop();
                 Value::Null
             }
             Node::Block(statements) => {
                 let mut last = Value::Null;
                 for stmt in statements {
-                    last = self.execute(stmt);
+                    last = self.execute(stmt)?;
                 }
                 last
             }
             Node::BinaryOp { left, operator, right } => {
-                let left_val = self.evaluate(*left);
-                let right_val = self.evaluate(*right);
-                self.evaluate_binary_op(left_val, operator, right_val)
+                let left_val = self.evaluate(left)?;
+                let right_val = self.evaluate(right)?;
+                if self.hook.is_some() {
+                    let result = binary_op(left_val.clone(), operator, right_val.clone());
+                    self.notify(Event::BinaryOp { left: &left_val, operator, right: &right_val, result: &result });
+                    result
+                } else {
+                    binary_op(left_val, operator, right_val)
+                }
+            }
+            Node::Call { name, args } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.evaluate(arg)?);
+                }
+                match self.natives.get_mut(name) {
+                    Some(native) => native(&values)?,
+                    None => builtins::call(
+                        &mut self.exit_code,
+                        self.input.as_mut(),
+                        self.output.as_mut(),
+                        &self.permissions,
+                        name,
+                        values,
+                    )?,
+                }
             }
             Node::Identifier(name) => {
-                self.env.get(&name).cloned().unwrap_or(Value::Null)
+                self.env.get(name).cloned().unwrap_or(Value::Null)
             }
-            Node::NumberLiteral(n) => Value::Number(n),
-            Node::StringLiteral(s) => Value::String(s),
-            Node::Boolean(b) => Value::Boolean(b),
+            Node::NumberLiteral(n) => Value::Number(*n),
+            Node::StringLiteral(s) => Value::String(s.clone()),
+            Node::Boolean(b) => Value::Boolean(*b),
             Node::Bugatti => Value::String("Bugatti!".to_string()),
             _ => Value::Null,
+        };
+        Ok(value)
+    }
+
+    fn notify(&mut self, event: Event) {
+        let depth = self.stack.len();
+        if let Some(hook) = self.hook.as_mut() {
+            hook.event(depth, event);
         }
     }
 
-    fn evaluate(&mut self, node: Node) -> Value {
-        self.execute(node)
+    /// Runs a block as its own frame. On error the frame stays on the stack so
+    /// it shows where things went wrong.
+    fn execute_in_frame(&mut self, kind: &'static str, block: &Node) -> Result<Value, RuntimeError> {
+        self.push_frame(kind);
+        let value = self.execute(block)?;
+        self.stack.pop();
+        Ok(value)
     }
 
-    fn evaluate_binary_op(&self, left: Value, operator: Operator, right: Value) -> Value {
-        match operator {
-            Operator::Plus => {
-                if let (Value::Number(a), Value::Number(b)) = (left, right) {
-                    Value::Number(a + b)
-                } else {
-                    Value::Null
-                }
+    fn push_frame(&mut self, kind: &'static str) {
+        let line = self.stack.last().map_or(0, |frame| frame.line);
+        self.stack.push(Frame { kind, opened_at: line, line });
+    }
+
+    fn evaluate(&mut self, node: &Node) -> Result<Value, RuntimeError> {
+        let value = self.execute(node)?;
+        self.meter.allocate(&value)?;
+        Ok(value)
+    }
+}
+
+/// Applies an operator. Only numbers are supported; anything else gives null.
+/// Arithmetic wraps around on overflow, like the generated JS and C.
+pub fn binary_op(left: Value, operator: &Operator, right: Value) -> Value {
+    match operator {
+        Operator::Plus => {
+            if let (Value::Number(a), Value::Number(b)) = (left, right) {
+                Value::Number(a.wrapping_add(b))
+            } else {
+                Value::Null
             }
-            Operator::Minus => {
-                if let (Value::Number(a), Value::Number(b)) = (left, right) {
-                    Value::Number(a - b)
-                } else {
-                    Value::Null
-                }
+        }
+        Operator::Minus => {
+            if let (Value::Number(a), Value::Number(b)) = (left, right) {
+                Value::Number(a.wrapping_sub(b))
+            } else {
+                Value::Null
             }
-            Operator::GreaterThan => {
-                if let (Value::Number(a), Value::Number(b)) = (left, right) {
-                    Value::Boolean(a > b)
-                } else {
-                    Value::Null
-                }
+        }
+        Operator::GreaterThan => {
+            if let (Value::Number(a), Value::Number(b)) = (left, right) {
+                Value::Boolean(a > b)
+            } else {
+                Value::Null
             }
-            Operator::LessThan => {
-                if let (Value::Number(a), Value::Number(b)) = (left, right) {
-                    Value::Boolean(a < b)
-                } else {
-                    Value::Null
-                }
+        }
+        Operator::LessThan => {
+            if let (Value::Number(a), Value::Number(b)) = (left, right) {
+                Value::Boolean(a < b)
+            } else {
+                Value::Null
             }
-            Operator::Equals => {
-                if let (Value::Number(a), Value::Number(b)) = (left, right) {
-                    Value::Boolean(a == b)
-                } else {
-                    Value::Null
-                }
+        }
+        Operator::Equals => {
+            if let (Value::Number(a), Value::Number(b)) = (left, right) {
+                Value::Boolean(a == b)
+            } else {
+                Value::Null
             }
         }
     }
+}
 
-    fn is_truthy(&self, value: Value) -> bool {
-        match value {
-            Value::Boolean(b) => b,
-            Value::Null => false,
-            Value::Number(n) => n != 0,
-            Value::String(s) => !s.is_empty(),
-        }
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Boolean(b) => *b,
+        Value::Null => false,
+        Value::Number(n) => *n != 0,
+        Value::String(s) => !s.is_empty(),
+        Value::List(items) => !items.is_empty(),
+        Value::Map(entries) => !entries.is_empty(),
     }
 }
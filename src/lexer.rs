@@ -1,12 +1,11 @@
 use crate::token::Token;
-use std::time::SystemTime;
+use std::ops::Range;
 
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
     read_position: usize,
     ch: char,
-    start_time: SystemTime,
 }
 
 impl Lexer {
@@ -16,7 +15,6 @@ impl Lexer {
             position: 0,
             read_position: 0,
             ch: '\0',
-            start_time: SystemTime::now(),
         };
         lexer.read_char();
         lexer
@@ -32,6 +30,19 @@ impl Lexer {
         self.read_position += 1;
     }
 
+    fn peek_char(&self) -> char {
+        self.input.get(self.read_position).copied().unwrap_or('\0')
+    }
+
+    /// Like `next_token`, but also returns the range of chars in the input the token covers.
+    pub fn next_token_with_span(&mut self) -> (Token, Range<usize>) {
+        self.skip_whitespace();
+        let start = self.position.min(self.input.len());
+        let tok = self.next_token();
+        let end = self.position.min(self.input.len());
+        (tok, start..end)
+    }
+
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
 
@@ -47,6 +58,12 @@ impl Lexer {
             '=' => { self.read_char(); Token::Equals },
             '+' => { self.read_char(); Token::Plus },
             '-' => { self.read_char(); Token::Minus },
+            '(' => { self.read_char(); Token::LeftParen },
+            ')' => { self.read_char(); Token::RightParen },
+            ',' => { self.read_char(); Token::Comma },
+            '/' if self.peek_char() == '/' => Token::Comment(self.read_line()),
+            // A shebang line lets scripts be executed directly.
+            '#' if self.position == 0 && self.peek_char() == '!' => Token::Comment(self.read_line()),
             _ => {
                 if self.ch.is_alphabetic() {
                     let word = self.read_word();
@@ -130,9 +147,57 @@ impl Lexer {
         num_str.parse().unwrap_or(0)
     }
 
+    fn read_line(&mut self) -> String {
+        let position = self.position;
+        while self.ch != '\n' && self.ch != '\0' {
+            self.read_char();
+        }
+        let line: String = self.input[position..self.position].iter().collect();
+        line.trim_end().to_string()
+    }
+
     fn skip_whitespace(&mut self) {
         while self.ch.is_whitespace() {
             self.read_char();
         }
     }
 }
+
+/// Lexes the whole source into the token list the parser expects:
+/// comments are dropped and the trailing EOF is not included.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut tokens = Vec::new();
+
+    loop {
+        match lexer.next_token() {
+            Token::EOF => break,
+            Token::Comment(_) => continue,
+            token => tokens.push(token),
+        }
+    }
+
+    tokens
+}
+
+/// Lexes the whole source keeping comments, pairing each token with the
+/// 1-based line it starts on. Used by tools that need to reproduce the source.
+pub fn tokenize_with_lines(source: &str) -> Vec<(Token, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lexer = Lexer::new(source.to_string());
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut counted = 0;
+
+    loop {
+        let (token, span) = lexer.next_token_with_span();
+        if token == Token::EOF {
+            break;
+        }
+        line += chars[counted..span.start].iter().filter(|&&c| c == '\n').count();
+        counted = span.start;
+        tokens.push((token, line));
+    }
+
+    tokens
+}
@@ -0,0 +1,34 @@
+//! Touch Grass as a library. [`Engine`] is the way to run programs from
+//! Rust; the modules below are what the `touch-grass` command-line tool is
+//! built from, and are public for tools that need the pieces.
+
+pub mod token;
+pub mod lexer;
+pub mod parser;
+pub mod ast;
+pub mod interpreter;
+pub mod highlight;
+pub mod checker;
+pub mod formatter;
+pub mod builtins;
+pub mod json;
+pub mod lsp;
+pub mod dap;
+pub mod debugger;
+pub mod trace;
+pub mod profiler;
+pub mod bytecode;
+pub mod vm;
+pub mod tgc;
+pub mod optimizer;
+pub mod lint;
+pub mod transpile;
+pub mod translate;
+pub mod console;
+pub mod sandbox;
+mod convert;
+mod engine;
+
+pub use engine::{Engine, Error};
+pub use interpreter::{NativeFunction, RuntimeError, Value};
+pub use parser::ParseError;
@@ -0,0 +1,318 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::ast::Node;
+use crate::formatter::format_expression;
+use crate::interpreter::{is_truthy, Value};
+
+/// How seriously to take a lint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Allow => "allow",
+            Level::Warn => "warn",
+            Level::Deny => "deny",
+        }
+    }
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warning"),
+            Level::Deny => write!(f, "error"),
+        }
+    }
+}
+
+pub struct Lint {
+    pub name: &'static str,
+    pub default: Level,
+    pub description: &'static str,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint { name: "unused-variable", default: Level::Warn, description: "a variable is declared but never read" },
+    Lint { name: "undeclared-assignment", default: Level::Deny, description: "`set` on a variable that hasn't been declared; fails at runtime" },
+    Lint { name: "undeclared-read", default: Level::Warn, description: "reading a variable that hasn't been declared; it is null" },
+    Lint { name: "infinite-loop", default: Level::Warn, description: "a `while` loop whose condition can never become false" },
+    Lint { name: "self-assignment", default: Level::Warn, description: "`set x to x` does nothing" },
+    Lint { name: "empty-block", default: Level::Warn, description: "a block with no statements between its header and `fr fr`" },
+];
+
+/// Which level each lint runs at. Starts from the defaults in `LINTS`.
+pub struct Config {
+    levels: HashMap<&'static str, Level>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config { levels: LINTS.iter().map(|lint| (lint.name, lint.default)).collect() }
+    }
+
+    pub fn set(&mut self, name: &str, level: Level) -> Result<(), String> {
+        let lint = LINTS.iter().find(|lint| lint.name == name).ok_or_else(|| format!("Unknown lint: {}", name))?;
+        self.levels.insert(lint.name, level);
+        Ok(())
+    }
+
+    pub fn level(&self, name: &str) -> Level {
+        self.levels.get(name).copied().unwrap_or(Level::Allow)
+    }
+
+    /// Applies a config file: one `lint-name = allow|warn|deny` per line,
+    /// with `#` starting a comment.
+    pub fn load(&mut self, text: &str) -> Result<(), String> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, level) = line.split_once('=').ok_or_else(|| format!("line {}: expected `lint-name = level`", i + 1))?;
+            let level = Level::from_name(level.trim())
+                .ok_or_else(|| format!("line {}: level must be allow, warn or deny", i + 1))?;
+            self.set(name.trim(), level).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub lint: &'static str,
+    pub level: Level,
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: ", self.line)?;
+        }
+        write!(f, "{}: {} [{}]", self.level, self.message, self.lint)
+    }
+}
+
+/// Runs every lint that isn't allowed over a program, in source order.
+pub fn lint(program: &Node, config: &Config) -> Vec<Diagnostic> {
+    let mut linter = Linter { config, diagnostics: Vec::new(), declared: HashMap::new(), order: Vec::new(), line: 0 };
+    linter.statement(program);
+
+    for name in std::mem::take(&mut linter.order) {
+        let (line, read) = linter.declared[&name];
+        if !read {
+            linter.report("unused-variable", line, format!("`{}` is declared but never read", name));
+        }
+    }
+    linter.diagnostics.sort_by_key(|d| d.line);
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    config: &'a Config,
+    diagnostics: Vec<Diagnostic>,
+    /// Declared variables: the line of the declaration and whether anything reads it.
+    declared: HashMap<String, (usize, bool)>,
+    order: Vec<String>,
+    line: usize,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, lint: &'static str, line: usize, message: String) {
+        let level = self.config.level(lint);
+        if level != Level::Allow {
+            self.diagnostics.push(Diagnostic { lint, level, line, message });
+        }
+    }
+
+    /// Like the interpreter there is one flat scope, so "declared" means
+    /// declared anywhere earlier in the file. `args` always is.
+    fn is_declared(&self, name: &str) -> bool {
+        name == "args" || self.declared.contains_key(name)
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) | Node::Block(statements) => {
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+            }
+            Node::Statement { line, statement } => {
+                self.line = *line;
+                self.statement(statement);
+            }
+            Node::VarDeclaration { name, value, .. } => {
+                self.expression(value);
+                if !self.declared.contains_key(name) {
+                    self.declared.insert(name.clone(), (self.line, false));
+                    self.order.push(name.clone());
+                }
+            }
+            Node::Assignment { name, value } => {
+                self.expression(value);
+                if !self.is_declared(name) {
+                    self.report("undeclared-assignment", self.line, format!("`{}` is assigned but was never declared with `touch grass`", name));
+                }
+                if matches!(value.as_ref(), Node::Identifier(other) if other == name) {
+                    self.report("self-assignment", self.line, format!("`set {} to {}` has no effect", name, name));
+                }
+            }
+            Node::Print(expr) => self.expression(expr),
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                let line = self.line;
+                self.expression(condition);
+                self.check_empty(then_branch, line, "go outside");
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_empty(else_branch, line, "instead");
+                    self.statement(else_branch);
+                }
+            }
+            Node::WhileLoop { condition, body } => {
+                let line = self.line;
+                self.expression(condition);
+                self.check_empty(body, line, "while");
+                self.check_loop_exit(condition, body, line);
+                self.statement(body);
+            }
+            Node::Comment { .. } => {}
+            expr => self.expression(expr),
+        }
+    }
+
+    fn expression(&mut self, node: &Node) {
+        match node {
+            Node::Identifier(name) => match self.declared.get_mut(name) {
+                Some((_, read)) => *read = true,
+                None if name == "args" => {}
+                None => self.report("undeclared-read", self.line, format!("`{}` is read but was never declared; it will be null", name)),
+            },
+            Node::BinaryOp { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Node::Call { args, .. } => {
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_empty(&mut self, block: &Node, line: usize, kind: &str) {
+        let empty = match block {
+            Node::Block(statements) => statements.iter().all(|stmt| matches!(stmt, Node::Comment { .. })),
+            _ => false,
+        };
+        if empty {
+            self.report("empty-block", line, format!("this `{}` block is empty", kind));
+        }
+    }
+
+    /// A loop can only stop if its condition is false at some point: either
+    /// it's not constant, or the body changes a variable it reads. A call
+    /// in the condition, e.g. `len(read_all())`, may return something new
+    /// every time, so it counts as a change too.
+    fn check_loop_exit(&mut self, condition: &Node, body: &Node, line: usize) {
+        if contains_call(condition) {
+            return;
+        }
+        let mut reads = BTreeSet::new();
+        collect_reads(condition, &mut reads);
+        let never_ends = if reads.is_empty() {
+            constant_truthy(condition)
+        } else {
+            let mut writes = BTreeSet::new();
+            collect_writes(body, &mut writes);
+            reads.is_disjoint(&writes)
+        };
+        if never_ends {
+            let condition = format_expression(condition);
+            self.report("infinite-loop", line, format!("`while {}` never stops: nothing in the loop can make the condition false", condition));
+        }
+    }
+}
+
+/// Whether a condition is a literal that is always true, like `no_cap`.
+fn constant_truthy(node: &Node) -> bool {
+    let value = match node {
+        Node::Boolean(b) => Value::Boolean(*b),
+        Node::NumberLiteral(n) => Value::Number(*n),
+        Node::StringLiteral(s) => Value::String(s.clone()),
+        Node::Bugatti => return true,
+        _ => return false,
+    };
+    is_truthy(&value)
+}
+
+fn contains_call(node: &Node) -> bool {
+    match node {
+        Node::Call { .. } => true,
+        Node::BinaryOp { left, right, .. } => contains_call(left) || contains_call(right),
+        _ => false,
+    }
+}
+
+fn collect_reads(node: &Node, reads: &mut BTreeSet<String>) {
+    match node {
+        Node::Identifier(name) => {
+            reads.insert(name.clone());
+        }
+        Node::BinaryOp { left, right, .. } => {
+            collect_reads(left, reads);
+            collect_reads(right, reads);
+        }
+        Node::Call { args, .. } => {
+            for arg in args {
+                collect_reads(arg, reads);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_writes(node: &Node, writes: &mut BTreeSet<String>) {
+    match node {
+        Node::Program(statements) | Node::Block(statements) => {
+            for stmt in statements {
+                collect_writes(stmt, writes);
+            }
+        }
+        Node::Statement { statement, .. } => collect_writes(statement, writes),
+        Node::VarDeclaration { name, .. } | Node::Assignment { name, .. } => {
+            writes.insert(name.clone());
+        }
+        Node::GoOutside { then_branch, else_branch, .. } => {
+            collect_writes(then_branch, writes);
+            if let Some(else_branch) = else_branch {
+                collect_writes(else_branch, writes);
+            }
+        }
+        Node::WhileLoop { body, .. } => collect_writes(body, writes),
+        _ => {}
+    }
+}
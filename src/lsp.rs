@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use crate::builtins;
+use crate::checker::{Type, TypeChecker};
+use crate::formatter;
+use crate::json::Json;
+use crate::lexer::{self, Lexer};
+use crate::parser::Parser;
+use crate::token::Token;
+
+// LSP enum values we use.
+const SEVERITY_ERROR: i64 = 1;
+const COMPLETION_FUNCTION: i64 = 3;
+const COMPLETION_VARIABLE: i64 = 6;
+const COMPLETION_KEYWORD: i64 = 14;
+const SYMBOL_VARIABLE: i64 = 13;
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const KEYWORDS: &[&str] = &[
+    "touch grass", "number", "as", "set", "to", "print", "go outside if", "then", "instead",
+    "while", "fr fr", "no_cap", "cap", "bugatti",
+];
+
+/// Reads one `Content-Length` framed message, as used by both LSP and DAP.
+/// Returns `None` at end of input.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    let text = String::from_utf8_lossy(&body);
+    Json::parse(&text)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// A token with its 0-based line and column range, in UTF-16 code units as
+/// LSP positions are.
+struct SpannedToken {
+    token: Token,
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+/// A variable declared with `touch grass`.
+struct Declaration {
+    name: String,
+    var_type: String,
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Everything the server knows about one open document.
+struct Analysis {
+    tokens: Vec<SpannedToken>,
+    declarations: Vec<Declaration>,
+    types: HashMap<String, Type>,
+    diagnostics: Vec<(usize, String)>, // (0-based line, message)
+}
+
+impl Analysis {
+    fn new(text: &str) -> Analysis {
+        let tokens = spanned_tokens(text);
+
+        let mut declarations = Vec::new();
+        for window in tokens.windows(3) {
+            if let [first, ty, name] = window {
+                if let (Token::TouchGrass, Token::NumberType, Token::Identifier(n)) = (&first.token, &ty.token, &name.token) {
+                    declarations.push(Declaration {
+                        name: n.clone(),
+                        var_type: "number".to_string(),
+                        line: name.line,
+                        start: name.start,
+                        end: name.end,
+                    });
+                }
+            }
+        }
+
+        let mut parser = Parser::with_lines(lexer::tokenize_with_lines(text));
+        let ast = parser.parse();
+        let mut diagnostics: Vec<(usize, String)> = parser
+            .errors()
+            .iter()
+            .map(|e| (e.line.saturating_sub(1), e.message.clone()))
+            .collect();
+
+        // Type errors on a tree that didn't parse are mostly noise.
+        let mut checker = TypeChecker::new();
+        if diagnostics.is_empty() {
+            for error in checker.check(&ast) {
+                diagnostics.push((error.line.saturating_sub(1), error.message.clone()));
+            }
+        } else {
+            checker.check(&ast);
+        }
+
+        Analysis {
+            tokens,
+            declarations,
+            types: checker.vars,
+            diagnostics,
+        }
+    }
+
+    fn token_at(&self, line: usize, character: usize) -> Option<&SpannedToken> {
+        self.tokens
+            .iter()
+            .find(|t| t.line == line && t.start <= character && character <= t.end)
+    }
+
+    fn identifier_at(&self, line: usize, character: usize) -> Option<&str> {
+        match self.token_at(line, character) {
+            Some(SpannedToken { token: Token::Identifier(name), .. }) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn declaration(&self, name: &str) -> Option<&Declaration> {
+        self.declarations.iter().find(|d| d.name == name)
+    }
+}
+
+fn spanned_tokens(text: &str) -> Vec<SpannedToken> {
+    let chars: Vec<char> = text.chars().collect();
+    // Offset of the first char of every line, and the UTF-16 column of every
+    // char (plus one past the end), since lexer spans count chars.
+    let mut line_starts = vec![0];
+    let mut columns = Vec::with_capacity(chars.len() + 1);
+    let mut column = 0;
+    for (i, c) in chars.iter().enumerate() {
+        columns.push(column);
+        column += c.len_utf16();
+        if *c == '\n' {
+            line_starts.push(i + 1);
+            column = 0;
+        }
+    }
+    columns.push(column);
+    let position = |offset: usize| {
+        let line = line_starts.partition_point(|&start| start <= offset) - 1;
+        (line, columns[offset])
+    };
+
+    let mut lexer = Lexer::new(text.to_string());
+    let mut tokens = Vec::new();
+    loop {
+        let (token, span) = lexer.next_token_with_span();
+        if token == Token::EOF {
+            break;
+        }
+        let (line, start) = position(span.start);
+        let (end_line, end) = position(span.end);
+        let end = if end_line == line { end } else { start + 1 };
+        tokens.push(SpannedToken { token, line, start, end });
+    }
+    tokens
+}
+
+fn error_reply(id: Json, code: i64, message: String) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        ("error", Json::object(vec![
+            ("code", code.into()),
+            ("message", message.into()),
+        ])),
+    ])
+}
+
+fn range(line: usize, start: usize, end: usize) -> Json {
+    Json::object(vec![
+        ("start", Json::object(vec![("line", line.into()), ("character", start.into())])),
+        ("end", Json::object(vec![("line", line.into()), ("character", end.into())])),
+    ])
+}
+
+/// A language server speaking JSON-RPC over any reader/writer pair, so it can
+/// be driven by an editor over stdio or by a scripted client.
+pub struct Server {
+    documents: HashMap<String, String>,
+    shutdown_requested: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            shutdown_requested: false,
+        }
+    }
+
+    /// Serves until `exit` or end of input. Returns whether the client shut
+    /// down cleanly (sent `shutdown` before `exit`).
+    pub fn serve<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> io::Result<bool> {
+        loop {
+            let message = match read_message(&mut input) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                // The body was read in full, so the next message is still framed correctly.
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    write_message(&mut output, &error_reply(Json::Null, PARSE_ERROR, e.to_string()))?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let method = message.get("method").and_then(Json::as_str).unwrap_or("").to_string();
+            if method == "exit" {
+                return Ok(self.shutdown_requested);
+            }
+
+            let params = message.get("params").cloned().unwrap_or(Json::Null);
+            match message.get("id") {
+                Some(id) => {
+                    let reply = match self.handle_request(&method, &params) {
+                        Some(result) => Json::object(vec![
+                            ("jsonrpc", "2.0".into()),
+                            ("id", id.clone()),
+                            ("result", result),
+                        ]),
+                        None => error_reply(id.clone(), METHOD_NOT_FOUND, format!("Unhandled method {}", method)),
+                    };
+                    write_message(&mut output, &reply)?;
+                }
+                None => {
+                    for notification in self.handle_notification(&method, &params) {
+                        write_message(&mut output, &notification)?;
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the result for a request, or `None` if we don't support it.
+    fn handle_request(&mut self, method: &str, params: &Json) -> Option<Json> {
+        let uri = params.at(&["textDocument", "uri"]).and_then(Json::as_str).unwrap_or("");
+        let line = params.at(&["position", "line"]).and_then(Json::as_i64).unwrap_or(0) as usize;
+        let character = params.at(&["position", "character"]).and_then(Json::as_i64).unwrap_or(0) as usize;
+        let text = self.documents.get(uri).cloned().unwrap_or_default();
+
+        let result = match method {
+            "initialize" => Json::object(vec![
+                ("capabilities", Json::object(vec![
+                    ("positionEncoding", "utf-16".into()), // the default; columns count UTF-16 code units
+                    ("textDocumentSync", 1i64.into()), // full document on every change
+                    ("hoverProvider", true.into()),
+                    ("definitionProvider", true.into()),
+                    ("completionProvider", Json::object(Vec::<(&str, Json)>::new())),
+                    ("documentSymbolProvider", true.into()),
+                    ("documentFormattingProvider", true.into()),
+                ])),
+                ("serverInfo", Json::object(vec![
+                    ("name", "touch-grass".into()),
+                ])),
+            ]),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Json::Null
+            }
+            "textDocument/hover" => hover(&Analysis::new(&text), line, character),
+            "textDocument/definition" => {
+                let analysis = Analysis::new(&text);
+                match analysis.identifier_at(line, character).and_then(|name| analysis.declaration(name)) {
+                    Some(decl) => Json::object(vec![
+                        ("uri", uri.into()),
+                        ("range", range(decl.line, decl.start, decl.end)),
+                    ]),
+                    None => Json::Null,
+                }
+            }
+            "textDocument/completion" => completion(&Analysis::new(&text), line),
+            "textDocument/documentSymbol" => {
+                let analysis = Analysis::new(&text);
+                Json::Array(analysis.declarations.iter().map(|decl| {
+                    Json::object(vec![
+                        ("name", decl.name.clone().into()),
+                        ("detail", decl.var_type.clone().into()),
+                        ("kind", SYMBOL_VARIABLE.into()),
+                        ("range", range(decl.line, decl.start, decl.end)),
+                        ("selectionRange", range(decl.line, decl.start, decl.end)),
+                    ])
+                }).collect())
+            }
+            "textDocument/formatting" => match formatter::format_source(&text) {
+                Ok(formatted) if formatted != text => {
+                    let lines = text.lines().count() + 1;
+                    Json::Array(vec![Json::object(vec![
+                        ("range", Json::object(vec![
+                            ("start", Json::object(vec![("line", 0usize.into()), ("character", 0usize.into())])),
+                            ("end", Json::object(vec![("line", lines.into()), ("character", 0usize.into())])),
+                        ])),
+                        ("newText", formatted.into()),
+                    ])])
+                }
+                // Nothing to change, or the document doesn't parse.
+                _ => Json::Array(Vec::new()),
+            },
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// Handles a notification and returns any notifications to send back.
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.at(&["textDocument", "uri"]).and_then(Json::as_str).unwrap_or("").to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.at(&["textDocument", "text"]).and_then(Json::as_str).unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                // We only advertise full sync, so the last change is the whole document.
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn diagnostics(&self, uri: &str) -> Json {
+        let text = self.documents.get(uri).map(String::as_str).unwrap_or("");
+        let line_lengths: Vec<usize> = text.lines().map(|l| l.encode_utf16().count()).collect();
+        let analysis = Analysis::new(text);
+
+        let diagnostics = analysis.diagnostics.iter().map(|(line, message)| {
+            let length = line_lengths.get(*line).copied().unwrap_or(0);
+            Json::object(vec![
+                ("range", range(*line, 0, length)),
+                ("severity", SEVERITY_ERROR.into()),
+                ("source", "touch-grass".into()),
+                ("message", message.clone().into()),
+            ])
+        }).collect();
+
+        Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", Json::object(vec![
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ])),
+        ])
+    }
+}
+
+fn hover(analysis: &Analysis, line: usize, character: usize) -> Json {
+    let name = match analysis.identifier_at(line, character) {
+        Some(name) => name,
+        None => return Json::Null,
+    };
+
+    let contents = if let Some(decl) = analysis.declaration(name) {
+        let actual = analysis.types.get(name).copied().unwrap_or(Type::Unknown);
+        format!("```touch-grass\ntouch grass {} {}\n```\n{}: {}", decl.var_type, name, name, actual)
+    } else if let Some(builtin) = builtins::lookup(name) {
+        format!("```touch-grass\n{}\n```", builtin.description)
+    } else if let Some(t) = analysis.types.get(name) {
+        format!("{}: {}", name, t)
+    } else {
+        return Json::Null;
+    };
+
+    Json::object(vec![
+        ("contents", Json::object(vec![
+            ("kind", "markdown".into()),
+            ("value", contents.into()),
+        ])),
+    ])
+}
+
+fn completion(analysis: &Analysis, line: usize) -> Json {
+    let mut items = Vec::new();
+    for keyword in KEYWORDS {
+        items.push(Json::object(vec![
+            ("label", (*keyword).into()),
+            ("kind", COMPLETION_KEYWORD.into()),
+        ]));
+    }
+
+    // Declarations are visible from their line on; scope is flat.
+    let mut seen = vec!["args"];
+    items.push(Json::object(vec![
+        ("label", "args".into()),
+        ("kind", COMPLETION_VARIABLE.into()),
+        ("detail", "list".into()),
+    ]));
+    for decl in &analysis.declarations {
+        if decl.line <= line && !seen.contains(&decl.name.as_str()) {
+            seen.push(&decl.name);
+            items.push(Json::object(vec![
+                ("label", decl.name.clone().into()),
+                ("kind", COMPLETION_VARIABLE.into()),
+                ("detail", decl.var_type.clone().into()),
+            ]));
+        }
+    }
+
+    for builtin in builtins::BUILTINS {
+        items.push(Json::object(vec![
+            ("label", builtin.name.into()),
+            ("kind", COMPLETION_FUNCTION.into()),
+            ("detail", builtin.description.into()),
+        ]));
+    }
+
+    Json::Array(items)
+}
+
+/// Runs the server on stdin/stdout.
+pub fn run_stdio() -> bool {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match Server::new().serve(stdin.lock(), stdout.lock()) {
+        Ok(clean) => clean,
+        Err(e) => {
+            eprintln!("touch-grass lsp: {}", e);
+            false
+        }
+    }
+}
@@ -1,72 +1,972 @@
-mod token;
-mod lexer;
-mod parser;
-mod ast;
-mod interpreter;
-
 use std::env;
 use std::fs;
-use std::io::{self, Write};
-use interpreter::Interpreter;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process;
+use std::time::Duration;
+use touch_grass::ast::Node;
+use touch_grass::interpreter::{ErrorKind, Hook};
+use touch_grass::sandbox::{Limits, Permissions};
+use touch_grass::{
+    bytecode, checker, console, dap, debugger, formatter, highlight, lexer, lint, lsp, optimizer, parser, profiler, tgc,
+    token, trace, translate, transpile, vm, Engine, Error,
+};
 
-/// Processes a source string: lexes, parses, then interprets the code.
-fn run_source(source: String) {
-    let mut lexer = lexer::Lexer::new(source);
-    let mut tokens = Vec::new();
+const VERSION: &str = "0.3.0";
 
-    loop {
-        let token = lexer.next_token();
-        if token == token::Token::EOF {
-            break;
+// Process exit codes.
+const EXIT_OK: i32 = 0;
+const EXIT_USAGE: i32 = 1; // bad arguments or unreadable input
+const EXIT_PARSE_ERROR: i32 = 2; // the program did not lex, parse or type check
+const EXIT_RUNTIME_ERROR: i32 = 3; // the program failed while running
+const EXIT_UNFORMATTED: i32 = 1; // `fmt --check` found files to reformat
+const EXIT_LINT_DENIED: i32 = 1; // `lint` found problems set to deny
+const EXIT_INVALID_OUTPUT: i32 = 1; // `transpile` wrote a module that doesn't validate
+const EXIT_INTERRUPTED: i32 = 130; // stopped with Ctrl-C, as shells report SIGINT
+
+const USAGE: &str = "Touch Grass - a programming language for those who need to touch grass
+
+Usage:
+    touch-grass                       start the REPL
+    touch-grass <file.tg> [args...]   run a file (same as `run`)
+    touch-grass run [options] <file.tg> [args...]
+                                      run a file. Extra arguments are available
+                                      to the program as the `args` list.
+                                      Ctrl-C stops it and shows where it was
+        -q, --quiet                   don't print the \"Running file\" banner
+        --engine=tree|vm              run with the tree-walking interpreter
+                                      (default) or compile to bytecode first
+        -O, --optimize                fold constants and remove dead branches
+                                      before running. Traces, profiles and
+                                      limits then see the optimized program:
+                                      a branch that is always taken is no
+                                      longer a block of its own
+        --dump-optimized              optimize, and print what changed and the
+                                      optimized program to stderr
+        --trace[=json]                log every step to stderr, as JSON lines
+                                      with --trace=json
+        --profile                     print the hottest lines to stderr when
+                                      the program stops
+        --folded=<out>                profile and write folded stacks for
+                                      flamegraph tools
+        --allow-read=<dir>            let the program read files and list
+                                      directories under <dir>; repeatable
+        --allow-write=<dir>           let the program create, change and
+                                      delete files under <dir>; repeatable.
+                                      Without these, filesystem builtins fail
+        --max-steps=<n>               stop the program after n steps: syntax
+                                      nodes on the tree engine, instructions
+                                      on the VM
+        --max-time=<ms>               stop the program after ms milliseconds
+        --max-depth=<n>               stop the program if it nests more than n
+                                      levels deep; tree engine only
+        --max-memory=<bytes>          stop the program once its values have
+                                      taken up this many bytes in total
+    touch-grass compile [-O] <file.tg> [-o <file.tgc>]
+                                      compile to a bytecode file, which `run`
+                                      executes on the VM; -O optimizes first
+    touch-grass disasm <file.tgc|file.tg>
+                                      list the bytecode with source lines
+    touch-grass transpile --target <js|c|wat> <file.tg> [-o <out>]
+                                      translate to a standalone program in
+                                      another language; wat modules import
+                                      their output and args from the host
+    touch-grass translate <file> [-o <file.tg>]
+                                      rewrite plain syntax (`let x = 5`,
+                                      `if x > 1:`, `while`, `print`) as
+                                      Touch Grass
+    touch-grass debug [--input=<file>] <file.tg> [args...]
+                                      run a file in the interactive debugger.
+                                      Debugger commands are read from stdin,
+                                      so the program reads <file> instead, or
+                                      nothing without --input
+    touch-grass check <file.tg>       lex, parse and type check without running
+    touch-grass lint [options] <file.tg>...
+                                      warn about likely mistakes: unused or
+                                      undeclared variables, loops that never
+                                      stop, self-assignments, empty blocks
+        -A, -W, -D <lint>             allow, warn about or deny a lint
+        --config <file>               read `lint-name = allow|warn|deny` lines
+        --list                        list the lints and their defaults
+    touch-grass tokens <file.tg>      print the token stream
+    touch-grass ast <file.tg>         print the parsed syntax tree
+    touch-grass fmt [--check | --write] <file.tg>...
+                                      print files in canonical formatting;
+                                      --write rewrites them in place and
+                                      --check lists the ones that differ
+    touch-grass highlight <file.tg> [--html]
+                                      print the file with syntax highlighting
+    touch-grass lsp                   start the language server on stdio
+    touch-grass dap                   start the debug adapter on stdio
+
+Use `-` as the file name to read from stdin.
+
+Options:
+    -h, --help       show this message
+    -V, --version    show the version";
+
+/// Reads a source file, or stdin when the path is `-`.
+fn read_source(path: &str) -> Result<String, i32> {
+    let result = if path == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents).map(|_| contents)
+    } else {
+        fs::read_to_string(path)
+    };
+
+    result.map_err(|e| {
+        eprintln!("Error reading file {}: {}", path, e);
+        EXIT_USAGE
+    })
+}
+
+/// Lexes and parses a source string, printing any parse errors.
+fn parse_source(source: &str) -> Result<Node, i32> {
+    let mut parser = parser::Parser::with_lines(lexer::tokenize_with_lines(source));
+    let ast = parser.parse();
+
+    if parser.errors().is_empty() {
+        Ok(ast)
+    } else {
+        for error in parser.errors() {
+            eprintln!("Error: {}", error);
         }
-        tokens.push(token);
+        Err(EXIT_PARSE_ERROR)
     }
+}
 
-    let mut parser = parser::Parser::new(tokens);
-    let ast = parser.parse();
-    
-    // Instead of printing the AST, run the interpreter.
-    let mut interp = Interpreter::new();
-    interp.interpret(ast);
+/// Runs a parsed program on the tree-walking interpreter.
+fn run_program(
+    ast: &Node,
+    script_args: Vec<String>,
+    permissions: Permissions,
+    limits: Limits,
+    hook: Option<Box<dyn Hook>>,
+) -> i32 {
+    let mut engine = Engine::new();
+    engine.set_args(script_args);
+    engine.set_permissions(permissions);
+    engine.set_limits(limits);
+    if let Some(hook) = hook {
+        engine.set_hook(hook);
+    }
+    engine.set_interrupt(ctrl_c::interrupt());
+    let result = ctrl_c::while_running(|| engine.run_program(ast));
+    report(&engine, result)
+}
+
+/// Prints how a run went and returns the exit code for it.
+fn report(engine: &Engine, result: Result<(), Error>) -> i32 {
+    match result {
+        Ok(()) => engine.exit_code().unwrap_or(EXIT_OK),
+        Err(Error::Parse(errors)) => {
+            for error in errors {
+                eprintln!("Error: {}", error);
+            }
+            EXIT_PARSE_ERROR
+        }
+        Err(Error::Interrupted { .. }) => {
+            eprintln!("\nyou touched grass: stopped by Ctrl-C");
+            for frame in engine.stack().iter().rev() {
+                if frame.line > 0 {
+                    eprintln!("    in {} at line {}", frame, frame.line);
+                } else {
+                    eprintln!("    in {}", frame);
+                }
+            }
+            EXIT_INTERRUPTED
+        }
+        Err(Error::Runtime { message, .. } | Error::Limit { message, .. }) => {
+            eprintln!("Runtime error: {}", message);
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+/// Optimizes a program, printing what changed and the result to stderr if `dump` is set.
+fn optimize(ast: Node, dump: bool) -> Node {
+    let (ast, changes) = optimizer::optimize(ast);
+    if dump {
+        for change in &changes {
+            eprintln!("optimized {}", change);
+        }
+        eprintln!("--- optimized program ({} change(s)) ---", changes.len());
+        eprint!("{}", formatter::format_program(&ast));
+        eprintln!("---");
+    }
+    ast
+}
+
+fn run_chunk(chunk: &bytecode::Chunk, script_args: Vec<String>, permissions: Permissions, limits: Limits) -> i32 {
+    let mut vm = vm::Vm::new(chunk);
+    vm.set_args(chunk, script_args);
+    vm.set_permissions(permissions);
+    vm.set_limits(limits);
+    vm.set_interrupt(ctrl_c::interrupt());
+    match ctrl_c::while_running(|| vm.run(chunk)) {
+        Ok(()) => vm.exit_code.unwrap_or(EXIT_OK),
+        // The VM has no block stack to show, only the line it was on.
+        Err(e) if e.kind == ErrorKind::Interrupted => {
+            eprintln!("\nyou touched grass: stopped by Ctrl-C");
+            match vm.line(chunk) {
+                0 => {}
+                line => eprintln!("    at line {}", line),
+            }
+            EXIT_INTERRUPTED
+        }
+        Err(e) => {
+            eprintln!("Runtime error: {}", e);
+            EXIT_RUNTIME_ERROR
+        }
+    }
 }
 
 /// Runs the interactive REPL.
 fn run_repl() {
-    println!("Touch Grass Programming Language v0.3.0");
+    println!("Touch Grass Programming Language v{}", VERSION);
     println!("Because you clearly need to...");
 
+    // Only repaint the input line when we're actually talking to a terminal.
+    let colorize = io::stdin().is_terminal() && io::stdout().is_terminal();
+    // One engine for the session, so variables carry over between lines.
+    let mut engine = Engine::new();
+    engine.set_interrupt(ctrl_c::interrupt());
+
     loop {
         let mut input = String::new();
         print!("🌱 >> ");
         io::stdout().flush().unwrap();
 
         match io::stdin().read_line(&mut input) {
+            Ok(0) => break,
             Ok(_) => {
                 if input.trim() == "exit" {
                     println!("Finally... touch grass my friend!");
                     break;
                 }
-                run_source(input);
+                if colorize && !input.trim().is_empty() {
+                    // Move back up over the echoed line and redraw it highlighted.
+                    print!("\x1b[1A\r\x1b[2K🌱 >> {}", highlight::to_ansi(&input));
+                    io::stdout().flush().unwrap();
+                }
+                let result = ctrl_c::while_running(|| engine.run(&input));
+                report(&engine, result);
             }
             Err(error) => println!("Error: {}", error),
         }
     }
 }
 
-/// Entry point: if a file path is provided, run that file; otherwise, start the REPL.
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// Options come before the file name; everything after it is passed to the script.
+fn cmd_run(args: &[String]) -> i32 {
+    let mut quiet = false;
+    let mut trace = None;
+    let mut profile = false;
+    let mut folded = None;
+    let mut use_vm = false;
+    let mut optimize_ast = false;
+    let mut dump_optimized = false;
+    let mut permissions = Permissions::new();
+    let mut limits = Limits::default();
+    let mut rest = args;
+    let filename = loop {
+        match rest.split_first() {
+            Some((arg, tail)) if arg == "-q" || arg == "--quiet" => {
+                quiet = true;
+                rest = tail;
+            }
+            Some((arg, tail)) if arg == "--trace" || arg == "--trace=text" => {
+                trace = Some(trace::TraceFormat::Text);
+                rest = tail;
+            }
+            Some((arg, tail)) if arg == "--trace=json" => {
+                trace = Some(trace::TraceFormat::JsonLines);
+                rest = tail;
+            }
+            Some((arg, tail)) if arg == "--profile" => {
+                profile = true;
+                rest = tail;
+            }
+            Some((arg, tail)) if arg.starts_with("--folded=") => {
+                profile = true;
+                folded = Some(arg["--folded=".len()..].to_string());
+                rest = tail;
+            }
+            Some((arg, tail)) if arg == "-O" || arg == "--optimize" => {
+                optimize_ast = true;
+                rest = tail;
+            }
+            Some((arg, tail)) if arg == "--dump-optimized" => {
+                optimize_ast = true;
+                dump_optimized = true;
+                rest = tail;
+            }
+            Some((arg, tail)) if arg == "--engine=tree" || arg == "--engine=vm" => {
+                use_vm = arg == "--engine=vm";
+                rest = tail;
+            }
+            Some((arg, tail)) if arg.starts_with("--allow-read=") || arg.starts_with("--allow-write=") => {
+                let (option, dir) = arg.split_once('=').unwrap_or_default();
+                let granted = if option == "--allow-read" {
+                    permissions.allow_read(dir)
+                } else {
+                    permissions.allow_write(dir)
+                };
+                if let Err(e) = granted {
+                    eprintln!("{}: can't use {}: {}", option, dir, e);
+                    return EXIT_USAGE;
+                }
+                rest = tail;
+            }
+            Some((arg, tail)) if arg.starts_with("--max-") => {
+                let (option, value) = arg.split_once('=').unwrap_or((arg, ""));
+                let Ok(n) = value.parse::<u64>() else {
+                    eprintln!("{} needs a whole number, e.g. {}=1000", option, option);
+                    return EXIT_USAGE;
+                };
+                match option {
+                    "--max-steps" => limits.max_steps = Some(n),
+                    "--max-time" => limits.max_time = Some(Duration::from_millis(n)),
+                    "--max-depth" => limits.max_depth = Some(n as usize),
+                    "--max-memory" => limits.max_memory = Some(n as usize),
+                    _ => {
+                        eprintln!("Unknown option for run: {}", arg);
+                        return EXIT_USAGE;
+                    }
+                }
+                rest = tail;
+            }
+            Some((arg, _)) if arg.starts_with('-') && arg != "-" => {
+                eprintln!("Unknown option for run: {}", arg);
+                return EXIT_USAGE;
+            }
+            Some((filename, tail)) => {
+                rest = tail;
+                break filename;
+            }
+            None => return usage_error("run [options] <file.tg> [args...]"),
+        }
+    };
+    if profile && trace.is_some() {
+        eprintln!("--trace and --profile can't be used together");
+        return EXIT_USAGE;
+    }
+    let compiled = filename.ends_with(".tgc");
+    if (use_vm || compiled) && (profile || trace.is_some()) {
+        eprintln!("--trace and --profile need --engine=tree");
+        return EXIT_USAGE;
+    }
+    if (use_vm || compiled) && limits.max_depth.is_some() {
+        eprintln!("--max-depth needs --engine=tree; bytecode doesn't nest");
+        return EXIT_USAGE;
+    }
+
+    if compiled {
+        let file = match load_compiled(filename) {
+            Ok(file) => file,
+            Err(code) => return code,
+        };
+        if !quiet {
+            println!("Running file: {}", filename);
+        }
+        return run_chunk(&file.chunk, rest.to_vec(), permissions, limits);
+    }
+
+    let contents = match read_source(filename) {
+        Ok(contents) => contents,
+        Err(code) => return code,
+    };
+    // Scripts started through a `#!` line shouldn't get the banner mixed into their output.
+    if !quiet && !contents.starts_with("#!") {
+        println!("Running file: {}", filename);
+    }
+    let mut ast = match parse_source(&contents) {
+        Ok(ast) => ast,
+        Err(code) => return code,
+    };
+    if optimize_ast {
+        ast = optimize(ast, dump_optimized);
+    }
+    if use_vm {
+        return run_chunk(&bytecode::compile(&ast), rest.to_vec(), permissions, limits);
+    }
+    let hook: Option<Box<dyn Hook>> = match trace {
+        Some(format) => Some(Box::new(trace::Tracer::new(format, Box::new(io::stderr())))),
+        None if profile => Some(Box::new(profiler::Profiler::new(Box::new(io::stderr()), folded))),
+        None => None,
+    };
+    run_program(&ast, rest.to_vec(), permissions, limits, hook)
+}
+
+/// Reads and validates a `.tgc` file.
+fn load_compiled(path: &str) -> Result<tgc::CompiledFile, i32> {
+    let bytes = fs::read(path).map_err(|e| {
+        eprintln!("Error reading file {}: {}", path, e);
+        EXIT_USAGE
+    })?;
+    tgc::decode(&bytes).map_err(|e| {
+        eprintln!("Error: {}: {}", path, e);
+        EXIT_PARSE_ERROR
+    })
+}
+
+/// Compiles a `.tg` file to bytecode, written next to it unless `-o` says otherwise.
+fn cmd_compile(args: &[String]) -> i32 {
+    let mut filename = None;
+    let mut output = None;
+    let mut optimize_ast = false;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match rest.next() {
+                Some(path) => output = Some(path.clone()),
+                None => return usage_error("compile [-O] <file.tg> [-o <file.tgc>]"),
+            },
+            "-O" | "--optimize" => optimize_ast = true,
+            _ if arg == "-" || !arg.starts_with('-') => filename = Some(arg),
+            _ => {
+                eprintln!("Unknown option for compile: {}", arg);
+                return EXIT_USAGE;
+            }
+        }
+    }
+    let filename = match filename {
+        Some(filename) => filename,
+        None => return usage_error("compile [-O] <file.tg> [-o <file.tgc>]"),
+    };
+    let output = match output {
+        Some(output) => output,
+        None if filename == "-" => return usage_error("compile - -o <file.tgc>"),
+        None => format!("{}.tgc", filename.strip_suffix(".tg").unwrap_or(filename)),
+    };
 
-    if args.len() > 1 {
-        let filename = &args[1];
-        match fs::read_to_string(filename) {
-            Ok(contents) => {
-                println!("Running file: {}", filename);
-                run_source(contents);
+    let mut ast = match read_source(filename).and_then(|source| parse_source(&source)) {
+        Ok(ast) => ast,
+        Err(code) => return code,
+    };
+    if optimize_ast {
+        ast = optimize(ast, false);
+    }
+    let file = tgc::CompiledFile { source_name: filename.clone(), chunk: bytecode::compile(&ast) };
+    let bytes = match tgc::encode(&file) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return EXIT_USAGE;
+        }
+    };
+    match fs::write(&output, bytes) {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error writing file {}: {}", output, e);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// Prints the bytecode of a `.tgc` file, or of a `.tg` file compiled on the fly.
+fn cmd_disasm(args: &[String]) -> i32 {
+    let filename = match input_arg(args) {
+        Some(filename) => filename,
+        None => return usage_error("disasm <file.tgc|file.tg>"),
+    };
+    let (file, source) = if filename.ends_with(".tgc") {
+        let file = match load_compiled(filename) {
+            Ok(file) => file,
+            Err(code) => return code,
+        };
+        // The source is only there to annotate the listing; it may have moved.
+        let source = fs::read_to_string(&file.source_name).ok();
+        (file, source)
+    } else {
+        let source = match read_source(filename) {
+            Ok(source) => source,
+            Err(code) => return code,
+        };
+        let ast = match parse_source(&source) {
+            Ok(ast) => ast,
+            Err(code) => return code,
+        };
+        (tgc::CompiledFile { source_name: filename.clone(), chunk: bytecode::compile(&ast) }, Some(source))
+    };
+    print!("{}", tgc::disassemble(&file, source.as_deref()));
+    EXIT_OK
+}
+
+/// Translates a program to another language, printing it or writing it to `-o`.
+fn cmd_transpile(args: &[String]) -> i32 {
+    const USAGE: &str = "transpile --target <js|c|wat> <file.tg> [-o <out>]";
+    let mut target = None;
+    let mut filename = None;
+    let mut output = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-t" | "--target" => match rest.next().map(|name| (name, transpile::Target::from_name(name))) {
+                Some((_, Some(t))) => target = Some(t),
+                Some((name, None)) => {
+                    eprintln!("Unknown target: {}", name);
+                    return EXIT_USAGE;
+                }
+                None => return usage_error(USAGE),
             },
-            Err(e) => eprintln!("Error reading file {}: {}", filename, e),
+            "-o" | "--output" => match rest.next() {
+                Some(path) => output = Some(path),
+                None => return usage_error(USAGE),
+            },
+            _ if arg == "-" || !arg.starts_with('-') => filename = Some(arg),
+            _ => {
+                eprintln!("Unknown option for transpile: {}", arg);
+                return EXIT_USAGE;
+            }
+        }
+    }
+    let (target, filename) = match (target, filename) {
+        (Some(target), Some(filename)) => (target, filename),
+        _ => return usage_error(USAGE),
+    };
+
+    let ast = match read_source(filename).and_then(|source| parse_source(&source)) {
+        Ok(ast) => ast,
+        Err(code) => return code,
+    };
+    let code = transpile::transpile(&ast, target, filename);
+    if target == transpile::Target::Wat {
+        if let Err(e) = transpile::validate_wat(&code) {
+            eprintln!("Generated module doesn't validate: {}", e);
+            return EXIT_INVALID_OUTPUT;
+        }
+    }
+    match output {
+        Some(path) => match fs::write(path, code) {
+            Ok(()) => EXIT_OK,
+            Err(e) => {
+                eprintln!("Error writing file {}: {}", path, e);
+                EXIT_USAGE
+            }
+        },
+        None => {
+            print!("{}", code);
+            EXIT_OK
+        }
+    }
+}
+
+/// Rewrites a file in the plain syntax as Touch Grass, printing it or writing it to `-o`.
+fn cmd_translate(args: &[String]) -> i32 {
+    const USAGE: &str = "translate <file> [-o <file.tg>]";
+    let mut filename = None;
+    let mut output = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match rest.next() {
+                Some(path) => output = Some(path),
+                None => return usage_error(USAGE),
+            },
+            _ if arg == "-" || !arg.starts_with('-') => filename = Some(arg),
+            _ => {
+                eprintln!("Unknown option for translate: {}", arg);
+                return EXIT_USAGE;
+            }
+        }
+    }
+    let Some(filename) = filename else { return usage_error(USAGE) };
+
+    let source = match read_source(filename) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+    let code = match translate::translate(&source) {
+        Ok(code) => code,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("Error: {}", error);
+            }
+            return EXIT_PARSE_ERROR;
         }
+    };
+    match output {
+        Some(path) => match fs::write(path, code) {
+            Ok(()) => EXIT_OK,
+            Err(e) => {
+                eprintln!("Error writing file {}: {}", path, e);
+                EXIT_USAGE
+            }
+        },
+        None => {
+            print!("{}", code);
+            EXIT_OK
+        }
+    }
+}
+
+/// Runs a file under the command-line debugger, stopped before the first statement.
+fn cmd_debug(args: &[String]) -> i32 {
+    let (input, args) = match args.split_first() {
+        Some((arg, rest)) if arg.starts_with("--input=") => (Some(&arg["--input=".len()..]), rest),
+        _ => (None, args),
+    };
+    let (filename, script_args) = match args.split_first() {
+        Some((filename, rest)) if filename == "-" || !filename.starts_with('-') => (filename, rest),
+        _ => return usage_error("debug [--input=<file>] <file.tg> [args...]"),
+    };
+    let source = match read_source(filename) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+    let input = match input {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Error reading file {}: {}", path, e);
+                return EXIT_USAGE;
+            }
+        },
+        None => String::new(),
+    };
+    let ast = match parse_source(&source) {
+        Ok(ast) => ast,
+        Err(code) => return code,
+    };
+
+    println!("Debugging {}. Type `help` for commands.", filename);
+    let mut engine = Engine::new();
+    engine.set_args(script_args.to_vec());
+    // The debugger's commands come from stdin; the program mustn't read them.
+    engine.set_input(console::Text::new(input));
+    engine.set_hook(Box::new(debugger::CliDebugger::new(&source, &ast)));
+    match engine.run_program(&ast) {
+        Ok(()) => {
+            println!("Program finished.");
+            engine.exit_code().unwrap_or(EXIT_OK)
+        }
+        Err(Error::Runtime { message, .. }) if message == debugger::STOPPED => {
+            println!("Program stopped.");
+            EXIT_OK
+        }
+        result => report(&engine, result),
+    }
+}
+
+fn cmd_check(args: &[String]) -> i32 {
+    let filename = match input_arg(args) {
+        Some(filename) => filename,
+        None => return usage_error("check <file.tg>"),
+    };
+    let ast = match read_source(filename).and_then(|source| parse_source(&source)) {
+        Ok(ast) => ast,
+        Err(code) => return code,
+    };
+
+    let mut checker = checker::TypeChecker::new();
+    let errors = checker.check(&ast);
+    if errors.is_empty() {
+        println!("{}: no problems found, go touch grass", filename);
+        EXIT_OK
     } else {
-        run_repl();
+        for error in errors {
+            eprintln!("Type error: {}", error);
+        }
+        EXIT_PARSE_ERROR
+    }
+}
+
+/// Lints files. Fails if any problem is set to deny; warnings are only printed.
+fn cmd_lint(args: &[String]) -> i32 {
+    const USAGE: &str = "lint [-A|-W|-D <lint>]... [--config <file>] <file.tg>...";
+    let mut config = lint::Config::new();
+    let mut files = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let level = match arg.as_str() {
+            "-A" | "--allow" => Some(lint::Level::Allow),
+            "-W" | "--warn" => Some(lint::Level::Warn),
+            "-D" | "--deny" => Some(lint::Level::Deny),
+            _ => None,
+        };
+        let result = match (level, arg.as_str()) {
+            (Some(level), _) => match rest.next() {
+                Some(name) => config.set(name, level),
+                None => return usage_error(USAGE),
+            },
+            (None, "--config") => match rest.next() {
+                Some(path) => fs::read_to_string(path)
+                    .map_err(|e| format!("Cannot read config file {}: {}", path, e))
+                    .and_then(|text| config.load(&text).map_err(|e| format!("{}: {}", path, e))),
+                None => return usage_error(USAGE),
+            },
+            (None, "--list") => {
+                for lint in lint::LINTS {
+                    println!("{:<24}{:<9}{}", lint.name, lint.default.name(), lint.description);
+                }
+                return EXIT_OK;
+            }
+            (None, name) if name == "-" || !name.starts_with('-') => {
+                files.push(arg);
+                Ok(())
+            }
+            (None, name) => Err(format!("Unknown option for lint: {}", name)),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            return EXIT_USAGE;
+        }
+    }
+    if files.is_empty() {
+        return usage_error(USAGE);
+    }
+
+    let mut code = EXIT_OK;
+    for filename in files {
+        let ast = match read_source(filename).and_then(|source| parse_source(&source)) {
+            Ok(ast) => ast,
+            Err(err) => {
+                code = code.max(err);
+                continue;
+            }
+        };
+        for diagnostic in lint::lint(&ast, &config) {
+            println!("{}: {}", filename, diagnostic);
+            if diagnostic.level == lint::Level::Deny {
+                code = code.max(EXIT_LINT_DENIED);
+            }
+        }
+    }
+    code
+}
+
+fn cmd_tokens(args: &[String]) -> i32 {
+    let filename = match input_arg(args) {
+        Some(filename) => filename,
+        None => return usage_error("tokens <file.tg>"),
+    };
+    let source = match read_source(filename) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+
+    let chars: Vec<char> = source.chars().collect();
+    let mut lexer = lexer::Lexer::new(source.clone());
+    loop {
+        let (token, span) = lexer.next_token_with_span();
+        let line = chars[..span.start].iter().filter(|&&c| c == '\n').count() + 1;
+        let column = span.start - chars[..span.start].iter().rposition(|&c| c == '\n').map_or(0, |p| p + 1) + 1;
+        println!("{}:{}\t{:?}", line, column, token);
+        if token == token::Token::EOF {
+            break;
+        }
+    }
+    EXIT_OK
+}
+
+fn cmd_ast(args: &[String]) -> i32 {
+    let filename = match input_arg(args) {
+        Some(filename) => filename,
+        None => return usage_error("ast <file.tg>"),
+    };
+    match read_source(filename).and_then(|source| parse_source(&source)) {
+        Ok(ast) => {
+            println!("{:#?}", ast);
+            EXIT_OK
+        }
+        Err(code) => code,
+    }
+}
+
+/// Formats files. Prints to stdout by default; `--write` rewrites the files
+/// in place and `--check` only reports which files aren't formatted.
+fn cmd_fmt(args: &[String]) -> i32 {
+    let check = args.iter().any(|a| a == "--check");
+    let write = args.iter().any(|a| a == "-w" || a == "--write");
+    let files: Vec<&String> = args.iter().filter(|a| *a == "-" || !a.starts_with('-')).collect();
+    if files.is_empty() || (check && write) {
+        return usage_error("fmt [--check | --write] <file.tg>...");
+    }
+
+    let mut code = EXIT_OK;
+    let mut unformatted = 0;
+    for filename in files {
+        let source = match read_source(filename) {
+            Ok(source) => source,
+            Err(err) => {
+                code = code.max(err);
+                continue;
+            }
+        };
+        let formatted = match formatter::format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("Error: {}: {}", filename, error);
+                }
+                code = code.max(EXIT_PARSE_ERROR);
+                continue;
+            }
+        };
+
+        if check {
+            if formatted != source {
+                println!("Would reformat: {}", filename);
+                unformatted += 1;
+            }
+        } else if write && filename != "-" {
+            if formatted != source {
+                if let Err(e) = fs::write(filename, &formatted) {
+                    eprintln!("Error writing file {}: {}", filename, e);
+                    code = code.max(EXIT_USAGE);
+                }
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+
+    if check && unformatted > 0 {
+        eprintln!("{} file(s) need formatting, run `touch-grass fmt --write`", unformatted);
+        code = code.max(EXIT_UNFORMATTED);
+    }
+    code
+}
+
+/// Prints a `.tg` file with syntax highlighting, as ANSI escapes or a standalone HTML page.
+fn cmd_highlight(args: &[String]) -> i32 {
+    let html = args.iter().any(|a| a == "--html");
+    let filename = match input_arg(args) {
+        Some(filename) => filename,
+        None => return usage_error("highlight <file.tg> [--html]"),
+    };
+
+    match read_source(filename) {
+        Ok(contents) => {
+            if html {
+                print!("{}", highlight::to_html(&contents, filename));
+            } else {
+                print!("{}", highlight::to_ansi(&contents));
+            }
+            EXIT_OK
+        }
+        Err(code) => code,
+    }
+}
+
+/// The first positional argument; `-` counts as a file name (stdin).
+fn input_arg(args: &[String]) -> Option<&String> {
+    args.iter().find(|a| *a == "-" || !a.starts_with('-'))
+}
+
+fn usage_error(usage: &str) -> i32 {
+    eprintln!("Usage: touch-grass {}", usage);
+    EXIT_USAGE
+}
+
+/// Ctrl-C stops the running program instead of killing the process, so it
+/// can say where it was. Every engine the CLI runs listens to the same
+/// `interrupt()`. Between programs, e.g. at the REPL prompt, or on a second
+/// Ctrl-C before the program notices (say, while it waits for input), Ctrl-C
+/// quits straight away as usual.
+#[cfg(unix)]
+mod ctrl_c {
+    use std::os::raw::c_int;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::OnceLock;
+    use touch_grass::sandbox::Interrupt;
+
+    const SIGINT: c_int = 2;
+    static INTERRUPT: OnceLock<Interrupt> = OnceLock::new();
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn _exit(status: c_int) -> !;
+    }
+
+    // Only atomics and _exit in here: nothing else is safe in a signal handler.
+    extern "C" fn on_sigint(_: c_int) {
+        match INTERRUPT.get() {
+            Some(interrupt) if RUNNING.load(Ordering::Relaxed) && !interrupt.is_interrupted() => interrupt.interrupt(),
+            _ => unsafe { _exit(super::EXIT_INTERRUPTED) },
+        }
+    }
+
+    /// The interrupt Ctrl-C triggers, shared by every engine.
+    pub fn interrupt() -> Interrupt {
+        INTERRUPT
+            .get_or_init(|| {
+                unsafe {
+                    signal(SIGINT, on_sigint);
+                }
+                Interrupt::new()
+            })
+            .clone()
+    }
+
+    /// Runs a program, letting Ctrl-C interrupt it instead of quitting.
+    pub fn while_running<T>(run: impl FnOnce() -> T) -> T {
+        let interrupt = interrupt();
+        interrupt.clear();
+        RUNNING.store(true, Ordering::Relaxed);
+        let result = run();
+        RUNNING.store(false, Ordering::Relaxed);
+        interrupt.clear();
+        result
+    }
+}
+
+#[cfg(not(unix))]
+mod ctrl_c {
+    use touch_grass::sandbox::Interrupt;
+
+    /// Ctrl-C keeps its default behaviour here, so nothing triggers this.
+    pub fn interrupt() -> Interrupt {
+        Interrupt::new()
+    }
+
+    pub fn while_running<T>(run: impl FnOnce() -> T) -> T {
+        run()
     }
 }
+
+/// Entry point: dispatches to a subcommand; a bare file path runs it and no arguments start the REPL.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let code = match args.get(1).map(String::as_str) {
+        None | Some("repl") => {
+            run_repl();
+            EXIT_OK
+        }
+        Some("-h") | Some("--help") | Some("help") => {
+            println!("{}", USAGE);
+            EXIT_OK
+        }
+        Some("-V") | Some("--version") => {
+            println!("touch-grass {}", VERSION);
+            EXIT_OK
+        }
+        Some("run") => cmd_run(&args[2..]),
+        Some("compile") => cmd_compile(&args[2..]),
+        Some("disasm") => cmd_disasm(&args[2..]),
+        Some("transpile") => cmd_transpile(&args[2..]),
+        Some("translate") => cmd_translate(&args[2..]),
+        Some("debug") => cmd_debug(&args[2..]),
+        Some("check") => cmd_check(&args[2..]),
+        Some("lint") => cmd_lint(&args[2..]),
+        Some("tokens") => cmd_tokens(&args[2..]),
+        Some("ast") => cmd_ast(&args[2..]),
+        Some("fmt") => cmd_fmt(&args[2..]),
+        Some("highlight") => cmd_highlight(&args[2..]),
+        Some("lsp") => {
+            if lsp::run_stdio() { EXIT_OK } else { EXIT_USAGE }
+        }
+        Some("dap") => {
+            if dap::run_stdio() { EXIT_OK } else { EXIT_USAGE }
+        }
+        Some(arg) if arg.starts_with('-') && arg != "-" => {
+            eprintln!("Unknown option: {}\n\n{}", arg, USAGE);
+            EXIT_USAGE
+        }
+        Some(_) => cmd_run(&args[1..]),
+    };
+
+    process::exit(code);
+}
@@ -0,0 +1,210 @@
+use crate::ast::{Node, Operator};
+use crate::formatter::format_expression;
+use crate::interpreter::{binary_op, is_truthy, Value};
+
+/// Something the optimizer rewrote, for `--dump-optimized`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub pass: &'static str,
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: ", self.line)?;
+        }
+        write!(f, "{} ({})", self.message, self.pass)
+    }
+}
+
+type Pass = fn(Node, &mut Optimizer) -> Node;
+
+/// The passes, in the order they run. Folding goes first so branch removal
+/// sees conditions like `1 < 2` as the constant they are.
+const PASSES: &[(&str, Pass)] = &[
+    ("constant folding", fold_constants),
+    ("dead branch elimination", remove_dead_branches),
+];
+
+/// Runs every pass over a program. The result behaves exactly like the input,
+/// but it is a smaller tree: it takes fewer steps and nests less deeply, so
+/// traces and `--max-steps`/`--max-depth` count differently than without it.
+pub fn optimize(program: Node) -> (Node, Vec<Change>) {
+    let mut optimizer = Optimizer { pass: "", line: 0, changes: Vec::new() };
+    let mut program = program;
+    for (name, pass) in PASSES {
+        optimizer.pass = name;
+        program = pass(program, &mut optimizer);
+    }
+    (program, optimizer.changes)
+}
+
+struct Optimizer {
+    pass: &'static str,
+    line: usize,
+    changes: Vec<Change>,
+}
+
+impl Optimizer {
+    fn record(&mut self, message: String) {
+        self.changes.push(Change { pass: self.pass, line: self.line, message });
+    }
+}
+
+/// Rebuilds a statement-level node, applying `expr` to every expression in it.
+fn map_statement(node: Node, opt: &mut Optimizer, expr: &mut dyn FnMut(Node, &mut Optimizer) -> Node) -> Node {
+    match node {
+        Node::Program(statements) => Node::Program(map_statements(statements, opt, expr)),
+        Node::Block(statements) => Node::Block(map_statements(statements, opt, expr)),
+        Node::Statement { line, statement } => {
+            opt.line = line;
+            Node::Statement { line, statement: Box::new(map_statement(*statement, opt, expr)) }
+        }
+        Node::VarDeclaration { name, var_type, value } => {
+            Node::VarDeclaration { name, var_type, value: Box::new(expr(*value, opt)) }
+        }
+        Node::Assignment { name, value } => Node::Assignment { name, value: Box::new(expr(*value, opt)) },
+        Node::Print(value) => Node::Print(Box::new(expr(*value, opt))),
+        Node::GoOutside { condition, then_branch, else_branch } => {
+            let condition = Box::new(expr(*condition, opt));
+            let then_branch = Box::new(map_statement(*then_branch, opt, expr));
+            let else_branch = else_branch.map(|branch| Box::new(map_statement(*branch, opt, expr)));
+            Node::GoOutside { condition, then_branch, else_branch }
+        }
+        Node::WhileLoop { condition, body } => {
+            let condition = Box::new(expr(*condition, opt));
+            Node::WhileLoop { condition, body: Box::new(map_statement(*body, opt, expr)) }
+        }
+        Node::Comment { .. } => node,
+        other => expr(other, opt),
+    }
+}
+
+fn map_statements(statements: Vec<Node>, opt: &mut Optimizer, expr: &mut dyn FnMut(Node, &mut Optimizer) -> Node) -> Vec<Node> {
+    statements.into_iter().map(|stmt| map_statement(stmt, opt, expr)).collect()
+}
+
+/// The value of an expression that doesn't depend on anything at runtime.
+fn constant_value(node: &Node) -> Option<Value> {
+    match node {
+        Node::NumberLiteral(n) => Some(Value::Number(*n)),
+        Node::StringLiteral(s) => Some(Value::String(s.clone())),
+        Node::Boolean(b) => Some(Value::Boolean(*b)),
+        Node::Bugatti => Some(Value::String("Bugatti!".to_string())),
+        _ => None,
+    }
+}
+
+fn literal(value: Value) -> Option<Node> {
+    match value {
+        Value::Number(n) => Some(Node::NumberLiteral(n)),
+        Value::Boolean(b) => Some(Node::Boolean(b)),
+        Value::String(s) => Some(Node::StringLiteral(s)),
+        // There's no way to write null or a list in source.
+        Value::List(_) | Value::Map(_) | Value::Null => None,
+    }
+}
+
+/// Replaces operators on constants with their result, e.g. `1 < 2` with `no_cap`.
+fn fold_constants(program: Node, opt: &mut Optimizer) -> Node {
+    map_statement(program, opt, &mut fold_expression)
+}
+
+fn fold_expression(node: Node, opt: &mut Optimizer) -> Node {
+    match node {
+        Node::BinaryOp { left, operator, right } => {
+            let left = fold_expression(*left, opt);
+            let right = fold_expression(*right, opt);
+            if let Some(folded) = fold(&left, &operator, &right) {
+                let original = Node::BinaryOp { left: Box::new(left), operator, right: Box::new(right) };
+                let (before, after) = (format_expression(&original), format_expression(&folded));
+                // Negative numbers print as the subtraction they were folded from.
+                if before != after {
+                    opt.record(format!("folded `{}` to `{}`", before, after));
+                }
+                return folded;
+            }
+            Node::BinaryOp { left: Box::new(left), operator, right: Box::new(right) }
+        }
+        Node::Call { name, args } => {
+            Node::Call { name, args: args.into_iter().map(|arg| fold_expression(arg, opt)).collect() }
+        }
+        other => other,
+    }
+}
+
+fn fold(left: &Node, operator: &Operator, right: &Node) -> Option<Node> {
+    let (left, right) = (constant_value(left)?, constant_value(right)?);
+    // The same operation as at runtime, so overflow wraps here too.
+    literal(binary_op(left, operator, right))
+}
+
+/// Replaces `go outside` with the branch a constant condition picks, and
+/// drops `while` loops whose condition is constantly false.
+fn remove_dead_branches(program: Node, opt: &mut Optimizer) -> Node {
+    match program {
+        Node::Program(statements) => Node::Program(prune_statements(statements, opt)),
+        Node::Block(statements) => Node::Block(prune_statements(statements, opt)),
+        other => other,
+    }
+}
+
+fn prune_statements(statements: Vec<Node>, opt: &mut Optimizer) -> Vec<Node> {
+    let mut out = Vec::with_capacity(statements.len());
+    let mut removed = false;
+    for stmt in statements {
+        // A trailing comment belongs to the statement before it.
+        if removed && matches!(stmt, Node::Comment { trailing: true, .. }) {
+            continue;
+        }
+        let before = out.len();
+        prune_statement(stmt, opt, &mut out);
+        removed = out.len() == before;
+    }
+    out
+}
+
+/// Pushes what's left of `stmt` onto `out`: itself, the statements of the
+/// branch it always takes, or nothing.
+fn prune_statement(stmt: Node, opt: &mut Optimizer, out: &mut Vec<Node>) {
+    let (line, inner) = match stmt {
+        Node::Statement { line, statement } => (line, *statement),
+        other => (0, other),
+    };
+    opt.line = line;
+    let wrap = |node: Node| if line > 0 { Node::Statement { line, statement: Box::new(node) } } else { node };
+
+    match inner {
+        Node::GoOutside { condition, then_branch, else_branch } => match constant_value(&condition) {
+            Some(value) => {
+                let taken = if is_truthy(&value) {
+                    opt.record(format!("`{}` is always true; kept only the first branch", format_expression(&condition)));
+                    Some(*then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    opt.record(format!("`{}` is always false; kept only the `instead` branch", format_expression(&condition)));
+                    Some(*else_branch)
+                } else {
+                    opt.record(format!("`{}` is always false; removed the block", format_expression(&condition)));
+                    None
+                };
+                if let Some(Node::Block(statements)) = taken {
+                    out.extend(prune_statements(statements, opt));
+                }
+            }
+            None => {
+                let then_branch = Box::new(remove_dead_branches(*then_branch, opt));
+                let else_branch = else_branch.map(|branch| Box::new(remove_dead_branches(*branch, opt)));
+                out.push(wrap(Node::GoOutside { condition, then_branch, else_branch }));
+            }
+        },
+        Node::WhileLoop { condition, body } => match constant_value(&condition) {
+            Some(value) if !is_truthy(&value) => {
+                opt.record(format!("removed `while {}`, which never runs", format_expression(&condition)));
+            }
+            _ => out.push(wrap(Node::WhileLoop { condition, body: Box::new(remove_dead_branches(*body, opt)) })),
+        },
+        other => out.push(wrap(other)),
+    }
+}
@@ -1,41 +1,155 @@
 use crate::token::Token;
 use crate::ast::{Node, Operator};
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize, // 0 when the parser wasn't given lines
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+/// A comment pulled out of the token stream, waiting to be placed in front of
+/// the statement that starts at token index `before`.
+struct PendingComment {
+    before: usize,
+    text: String,
+    trailing: bool,
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
+    lines: Vec<usize>,
     current: usize,
+    errors: Vec<ParseError>,
+    comments: Vec<PendingComment>,
+    next_comment: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        Parser::with_lines(tokens.into_iter().map(|t| (t, 0)).collect())
+    }
+
+    /// Builds a parser from tokens paired with their source lines (see
+    /// `lexer::tokenize_with_lines`). Statements are wrapped in
+    /// `Node::Statement` to remember their line, and comments become
+    /// `Node::Comment` statements placed between the statements they sat between.
+    pub fn with_lines(tokens: Vec<(Token, usize)>) -> Self {
+        let mut code = Vec::new();
+        let mut lines = Vec::new();
+        let mut comments = Vec::new();
+        let mut last_line = None;
+
+        for (token, line) in tokens {
+            match token {
+                Token::Comment(text) => comments.push(PendingComment {
+                    before: code.len(),
+                    text,
+                    trailing: line > 0 && last_line == Some(line),
+                }),
+                token => {
+                    code.push(token);
+                    lines.push(line);
+                    last_line = Some(line);
+                }
+            }
+        }
+
         Parser {
-            tokens,
+            tokens: code,
+            lines,
             current: 0,
+            errors: Vec::new(),
+            comments,
+            next_comment: 0,
         }
     }
 
+    /// Errors collected during `parse`. The parser recovers and keeps going,
+    /// so the returned tree is only trustworthy when this is empty.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
     pub fn parse(&mut self) -> Node {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
+            self.take_comments(&mut statements);
+            let start = self.current;
             if let Some(stmt) = self.parse_statement() {
                 statements.push(stmt);
             } else {
+                if self.current == start {
+                    self.error(format!("Unexpected token {:?}", self.tokens[start]));
+                }
                 // Skip token on error to avoid infinite loops
                 self.advance();
             }
         }
+        self.take_comments(&mut statements);
 
         Node::Program(statements)
     }
 
+    /// Parses the tokens as a single expression, e.g. one typed into a debugger.
+    pub fn parse_standalone_expression(&mut self) -> Option<Node> {
+        let expr = self.parse_expression();
+        if !self.is_at_end() {
+            let message = format!("Unexpected token {:?} after expression", self.tokens[self.current]);
+            self.error(message);
+            return None;
+        }
+        expr
+    }
+
+    /// Emits every comment that appeared before the current token.
+    fn take_comments(&mut self, statements: &mut Vec<Node>) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.before > self.current {
+                break;
+            }
+            statements.push(Node::Comment {
+                text: comment.text.clone(),
+                trailing: comment.trailing,
+            });
+            self.next_comment += 1;
+        }
+    }
+
     fn parse_statement(&mut self) -> Option<Node> {
+        let line = self.current_line();
+        let statement = self.parse_bare_statement()?;
+        if line > 0 {
+            Some(Node::Statement {
+                line,
+                statement: Box::new(statement),
+            })
+        } else {
+            Some(statement)
+        }
+    }
+
+    fn parse_bare_statement(&mut self) -> Option<Node> {
         match self.peek_token() {
             Some(Token::TouchGrass) => self.parse_var_declaration(),
             Some(Token::Print) => self.parse_print_statement(),
             Some(Token::Go) => self.parse_go_outside(),
             Some(Token::Set) => self.parse_assignment(),
             Some(Token::While) => self.parse_while_loop(),
+            // A call on its own line, e.g. `exit_code(1)`
+            Some(Token::Identifier(_)) if self.tokens.get(self.current + 1) == Some(&Token::LeftParen) => {
+                self.parse_expression()
+            }
             _ => None,
         }
     }
@@ -47,7 +161,7 @@ impl Parser {
             self.advance();
             "number".to_string()
         } else {
-            eprintln!("Error: Expected number type after touch grass");
+            self.error("Expected number type after touch grass".to_string());
             return None;
         };
 
@@ -58,7 +172,7 @@ impl Parser {
                 name
             }
             _ => {
-                eprintln!("Error: Expected identifier for variable name");
+                self.error("Expected identifier for variable name".to_string());
                 return None;
             }
         };
@@ -66,7 +180,7 @@ impl Parser {
         if let Some(Token::As) = self.peek_token() {
             self.advance();
         } else {
-            eprintln!("Error: Expected 'as' after variable name");
+            self.error("Expected 'as' after variable name".to_string());
             return None;
         }
 
@@ -91,14 +205,14 @@ impl Parser {
         if let Some(Token::Outside) = self.peek_token() {
             self.advance();
         } else {
-            eprintln!("Error: Expected 'outside' after 'go'");
+            self.error("Expected 'outside' after 'go'".to_string());
             return None;
         }
 
         if let Some(Token::If) = self.peek_token() {
             self.advance();
         } else {
-            eprintln!("Error: Expected 'if' after 'go outside'");
+            self.error("Expected 'if' after 'go outside'".to_string());
             return None;
         }
 
@@ -107,7 +221,7 @@ impl Parser {
         if let Some(Token::Then) = self.peek_token() {
             self.advance();
         } else {
-            eprintln!("Error: Expected 'then' after condition");
+            self.error("Expected 'then' after condition".to_string());
             return None;
         }
 
@@ -137,7 +251,7 @@ impl Parser {
                 n
             },
             _ => {
-                eprintln!("Error: Expected identifier after 'set'");
+                self.error("Expected identifier after 'set'".to_string());
                 return None;
             }
         };
@@ -145,7 +259,7 @@ impl Parser {
         if let Some(Token::To) = self.peek_token() {
             self.advance(); // consume 'to'
         } else {
-            eprintln!("Error: Expected 'to' after identifier in assignment");
+            self.error("Expected 'to' after identifier in assignment".to_string());
             return None;
         }
 
@@ -164,7 +278,7 @@ impl Parser {
         if let Some(Token::Then) = self.peek_token() {
             self.advance(); // consume 'then'
         } else {
-            eprintln!("Error: Expected 'then' after while condition");
+            self.error("Expected 'then' after while condition".to_string());
             return None;
         }
 
@@ -177,19 +291,30 @@ impl Parser {
 
     fn parse_block(&mut self) -> Option<Node> {
         let mut statements = Vec::new();
+        let mut closed = false;
     
         while !self.is_at_end() {
+            self.take_comments(&mut statements);
             if let Some(Token::FrFr) = self.peek_token() {
                 self.advance(); // consume FrFr and end the block
+                closed = true;
                 break;
             }
+            let start = self.current;
             if let Some(stmt) = self.parse_statement() {
                 statements.push(stmt);
             } else {
+                if self.current == start {
+                    self.error(format!("Unexpected token {:?}", self.tokens[start]));
+                }
                 // In case of error, advance to avoid infinite loop
                 self.advance();
             }
         }
+
+        if !closed {
+            self.error("Expected 'fr fr' to close block".to_string());
+        }
     
         Some(Node::Block(statements))
     }
@@ -268,16 +393,72 @@ impl Parser {
             Token::Identifier(ref name) => {
                 let val = name.clone();
                 self.advance();
+                if let Some(Token::LeftParen) = self.peek_token() {
+                    return self.parse_call(val);
+                }
                 Some(Node::Identifier(val))
             }
+            Token::LeftParen => {
+                self.advance(); // consume '('
+                let expr = self.parse_expression()?;
+                if let Some(Token::RightParen) = self.peek_token() {
+                    self.advance();
+                } else {
+                    self.error("Expected ')' after expression".to_string());
+                    return None;
+                }
+                Some(expr)
+            }
             _ => {
-                eprintln!("Error: Unexpected token {:?}", self.peek_token());
+                let message = format!("Unexpected token {:?}", self.peek_token());
+                self.error(message);
                 self.advance();
                 None
             }
         }
     }
 
+    fn parse_call(&mut self, name: String) -> Option<Node> {
+        self.advance(); // consume '('
+
+        let mut args = Vec::new();
+        if let Some(Token::RightParen) = self.peek_token() {
+            self.advance();
+            return Some(Node::Call { name, args });
+        }
+
+        loop {
+            args.push(self.parse_expression()?);
+            match self.peek_token() {
+                Some(Token::Comma) => self.advance(),
+                Some(Token::RightParen) => {
+                    self.advance();
+                    break;
+                }
+                _ => {
+                    self.error(format!("Expected ',' or ')' in call to {}", name));
+                    return None;
+                }
+            }
+        }
+
+        Some(Node::Call { name, args })
+    }
+
+    fn error(&mut self, message: String) {
+        let line = self.current_line();
+        self.errors.push(ParseError { message, line });
+    }
+
+    /// Line of the current token, or of the last one once we've run off the end.
+    fn current_line(&self) -> usize {
+        self.lines
+            .get(self.current)
+            .or(self.lines.last())
+            .copied()
+            .unwrap_or(0)
+    }
+
     fn peek_token(&self) -> Option<&Token> {
         self.tokens.get(self.current)
     }
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use crate::ast::Node;
+use crate::formatter;
+use crate::interpreter::{Hook, Interpreter, RuntimeError};
+
+#[derive(Default)]
+struct LineStats {
+    code: String,
+    count: u64,
+    /// Time from the start of the statement to its end, including nested blocks.
+    total: Duration,
+    /// `total` minus the time spent in statements nested inside it.
+    own: Duration,
+}
+
+/// A statement that started but hasn't finished yet.
+struct Running {
+    line: usize,
+    start: Instant,
+    children: Duration,
+}
+
+/// Counts how often each source line runs and how long it takes, then
+/// reports the hottest lines when the program stops.
+pub struct Profiler {
+    lines: BTreeMap<usize, LineStats>,
+    running: Vec<Running>,
+    /// Own time per chain of nested statements, e.g. `main;line 2;line 3`.
+    stacks: HashMap<String, Duration>,
+    started: Instant,
+    report: Box<dyn Write>,
+    folded_path: Option<String>,
+}
+
+impl Profiler {
+    /// Writes the hot-spot report to `report` and, given a path, the
+    /// folded stacks that flamegraph tools take as input.
+    pub fn new(report: Box<dyn Write>, folded_path: Option<String>) -> Self {
+        Profiler {
+            lines: BTreeMap::new(),
+            running: Vec::new(),
+            stacks: HashMap::new(),
+            started: Instant::now(),
+            report,
+            folded_path,
+        }
+    }
+
+    fn stack_key(&self) -> String {
+        let mut key = "main".to_string();
+        for running in &self.running {
+            key.push_str(&format!(";line {}", running.line));
+        }
+        key
+    }
+
+    /// Closes the innermost running statement.
+    fn finish_statement(&mut self, now: Instant) {
+        let key = self.stack_key();
+        let running = match self.running.pop() {
+            Some(running) => running,
+            None => return,
+        };
+        let total = now - running.start;
+        let own = total.saturating_sub(running.children);
+        if let Some(parent) = self.running.last_mut() {
+            parent.children += total;
+        }
+        let stats = self.lines.entry(running.line).or_default();
+        stats.count += 1;
+        stats.total += total;
+        stats.own += own;
+        *self.stacks.entry(key).or_default() += own;
+    }
+
+    fn write_report(&mut self) {
+        let elapsed = self.started.elapsed();
+        let mut hot: Vec<(&usize, &LineStats)> = self.lines.iter().collect();
+        hot.sort_by(|a, b| b.1.own.cmp(&a.1.own).then(a.0.cmp(b.0)));
+
+        let mut out = String::new();
+        out.push_str(&format!("Profile: {} statement(s) ran in {}\n", self.lines.values().map(|s| s.count).sum::<u64>(), millis(elapsed)));
+        out.push_str(&format!("{:>6} {:>10} {:>12} {:>12} {:>6}  code\n", "line", "count", "total", "self", "self%"));
+        for (line, stats) in hot {
+            let percent = if elapsed.is_zero() { 0.0 } else { stats.own.as_secs_f64() * 100.0 / elapsed.as_secs_f64() };
+            out.push_str(&format!(
+                "{:>6} {:>10} {:>12} {:>12} {:>5.1}%  {}\n",
+                line,
+                stats.count,
+                millis(stats.total),
+                millis(stats.own),
+                percent,
+                stats.code
+            ));
+        }
+        let _ = self.report.write_all(out.as_bytes());
+    }
+
+    fn write_folded(&self, path: &str) -> std::io::Result<()> {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        stacks.sort();
+        let mut out = String::new();
+        for (stack, time) in stacks {
+            // Nanoseconds rather than samples, so quick statements still show up.
+            out.push_str(&format!("{} {}\n", stack, time.as_nanos()));
+        }
+        fs::write(path, out)
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+impl Hook for Profiler {
+    fn before_statement(&mut self, _interp: &mut Interpreter, line: usize, statement: &Node) -> Result<(), RuntimeError> {
+        let stats = self.lines.entry(line).or_default();
+        if stats.code.is_empty() {
+            stats.code = formatter::statement_header(statement);
+        }
+        self.running.push(Running { line, start: Instant::now(), children: Duration::ZERO });
+        Ok(())
+    }
+
+    fn after_statement(&mut self, _line: usize) {
+        self.finish_statement(Instant::now());
+    }
+
+    fn finish(&mut self) {
+        // After an error the failing statement and everything around it are
+        // still running; count them up to now.
+        let now = Instant::now();
+        while !self.running.is_empty() {
+            self.finish_statement(now);
+        }
+        self.write_report();
+        if let Some(path) = &self.folded_path {
+            if let Err(e) = self.write_folded(path) {
+                let _ = writeln!(self.report, "Could not write {}: {}", path, e);
+            }
+        }
+    }
+}
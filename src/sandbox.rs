@@ -0,0 +1,258 @@
+//! What a program may touch outside itself, and how much it may do. Scripts
+//! can be third-party code, so the filesystem builtins can't reach anything
+//! until a directory is granted, `Limits` stop programs that would run
+//! forever or eat all the memory, and an `Interrupt` stops one on request.
+
+use std::env;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::interpreter::{RuntimeError, Value};
+
+/// Directories the filesystem builtins may read from and write to. The
+/// default grants nothing. Writing (and deleting) doesn't imply reading.
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+}
+
+impl Permissions {
+    pub fn new() -> Self {
+        Permissions::default()
+    }
+
+    /// Lets programs read files and list directories anywhere under `dir`.
+    /// Fails if `dir` doesn't exist.
+    pub fn allow_read(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.read.push(dir.as_ref().canonicalize()?);
+        Ok(())
+    }
+
+    /// Lets programs create, change and delete files anywhere under `dir`.
+    /// Fails if `dir` doesn't exist.
+    pub fn allow_write(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.write.push(dir.as_ref().canonicalize()?);
+        Ok(())
+    }
+
+    /// Where `path` really is, if programs may read it.
+    pub fn check_read(&self, path: &str) -> Result<PathBuf, RuntimeError> {
+        check(&self.read, path, "read")
+    }
+
+    /// Where `path` really is, if programs may write it.
+    pub fn check_write(&self, path: &str) -> Result<PathBuf, RuntimeError> {
+        check(&self.write, path, "write")
+    }
+}
+
+fn check(granted: &[PathBuf], path: &str, access: &str) -> Result<PathBuf, RuntimeError> {
+    let denied = || RuntimeError::new(format!("Not allowed to {} \"{}\"", access, path));
+    let resolved = resolve(Path::new(path)).map_err(|_| denied())?;
+    if granted.iter().any(|dir| resolved.starts_with(dir)) {
+        Ok(resolved)
+    } else {
+        Err(denied())
+    }
+}
+
+/// Makes `path` absolute and follows symlinks as far as it exists, so
+/// neither `..` nor a link can lead out of a granted directory. The part
+/// that doesn't exist yet, e.g. a file about to be written, is kept as is.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in env::current_dir()?.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => {
+                resolved.push(other);
+                if let Ok(real) = resolved.canonicalize() {
+                    resolved = real;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Caps on how much work a program may do; each one that's hit stops the
+/// program with a runtime error of kind `ErrorKind::Limit`. `None` means no
+/// limit, the default. Every run (or standalone evaluation) starts with the
+/// full budget.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    /// Steps, one per syntax node run or evaluated by the interpreter, or
+    /// one per instruction on the VM.
+    pub max_steps: Option<u64>,
+    /// Wall-clock time from when the program starts.
+    pub max_time: Option<Duration>,
+    /// How deeply nodes may nest while running, e.g. blocks inside loops
+    /// inside branches. Bytecode is flat, so the VM doesn't check this.
+    pub max_depth: Option<usize>,
+    /// Total bytes of strings, lists and maps that expressions produce,
+    /// counting every copy.
+    pub max_memory: Option<usize>,
+}
+
+/// Which of the `Limits` a program ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    Steps,
+    Time,
+    Depth,
+    Memory,
+}
+
+/// Checks a running program against its `Limits`.
+#[derive(Debug, Default)]
+pub(crate) struct Meter {
+    limits: Limits,
+    running: bool,
+    steps: u64,
+    deadline: Option<Instant>,
+    depth: usize,
+    memory: usize,
+}
+
+/// The deadline is only checked this often, since reading the clock costs
+/// about as much as a step.
+const STEPS_PER_CLOCK_CHECK: u64 = 1024;
+
+impl Meter {
+    pub fn new(limits: Limits) -> Self {
+        Meter { limits, ..Meter::default() }
+    }
+
+    /// Starts a fresh budget, unless a run is already going (e.g. a debugger
+    /// evaluating an expression mid-run). Returns whether it did, in which
+    /// case the caller should `end` it.
+    pub fn begin(&mut self) -> bool {
+        if self.running {
+            return false;
+        }
+        *self = Meter::new(self.limits);
+        self.running = true;
+        self.deadline = self.limits.max_time.map(|time| Instant::now() + time);
+        true
+    }
+
+    pub fn end(&mut self) {
+        self.running = false;
+    }
+
+    /// Charges a step, e.g. for one VM instruction.
+    pub fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        if let Some(max) = self.limits.max_steps {
+            if self.steps > max {
+                return Err(RuntimeError::limit(
+                    LimitKind::Steps,
+                    format!("Step limit exceeded: ran more than {} steps", max),
+                ));
+            }
+        }
+        if let (Some(deadline), Some(max)) = (self.deadline, self.limits.max_time) {
+            if self.steps.is_multiple_of(STEPS_PER_CLOCK_CHECK) && Instant::now() > deadline {
+                return Err(RuntimeError::limit(
+                    LimitKind::Time,
+                    format!("Time limit exceeded: ran longer than {} ms", max.as_millis()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Charges a step for a node about to run, one level deeper than the
+    /// node running it. On error the node doesn't run, so there's no `leave`.
+    pub fn enter(&mut self) -> Result<(), RuntimeError> {
+        self.step()?;
+        if let Some(max) = self.limits.max_depth {
+            if self.depth >= max {
+                return Err(RuntimeError::limit(
+                    LimitKind::Depth,
+                    format!("Depth limit exceeded: nested more than {} levels deep", max),
+                ));
+            }
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// The node that `enter` charged for has finished.
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Charges for the memory an expression's value takes up.
+    pub fn allocate(&mut self, value: &Value) -> Result<(), RuntimeError> {
+        if let Some(max) = self.limits.max_memory {
+            self.memory += heap_size(value);
+            if self.memory > max {
+                return Err(RuntimeError::limit(
+                    LimitKind::Memory,
+                    format!("Memory limit exceeded: allocated more than {} bytes", max),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The bytes a value owns beyond itself.
+fn heap_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::List(items) => items.iter().map(|item| std::mem::size_of::<Value>() + heap_size(item)).sum(),
+        Value::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| key.len() + std::mem::size_of::<Value>() + heap_size(value))
+            .sum(),
+        Value::Number(_) | Value::Boolean(_) | Value::Null => 0,
+    }
+}
+
+/// Asks a running program to stop, from any thread. The program checks
+/// before every step and stops with a runtime error of kind
+/// `ErrorKind::Interrupted`, which clears the request; a builtin that's
+/// blocked, e.g. `read_line` waiting for input, finishes first. Clones share
+/// the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    requested: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    pub fn new() -> Self {
+        Interrupt::default()
+    }
+
+    /// Stops the program that's running, or the next one to start.
+    pub fn interrupt(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether an interrupt is waiting to stop a program.
+    pub fn is_interrupted(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Takes back an interrupt that hasn't stopped anything yet.
+    pub fn clear(&self) {
+        self.requested.store(false, Ordering::Relaxed);
+    }
+
+    /// Fails, once, if an interrupt was requested.
+    pub(crate) fn check(&self) -> Result<(), RuntimeError> {
+        if self.is_interrupted() {
+            self.clear();
+            return Err(RuntimeError::interrupted());
+        }
+        Ok(())
+    }
+}
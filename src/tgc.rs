@@ -0,0 +1,366 @@
+use std::fmt::Write as _;
+use crate::bytecode::{Chunk, Op};
+use crate::interpreter::Value;
+
+/// Compiled programs (`.tgc` files) start with these bytes.
+pub const MAGIC: &[u8; 4] = b"TGBC";
+/// Bumped whenever the layout below changes; older files are rejected.
+pub const FORMAT_VERSION: u16 = 1;
+
+// Layout, all integers little-endian:
+//   magic "TGBC" | version u16 | checksum u32 | body
+// The checksum is FNV-1a over the body, which is:
+//   source file name | constants | slot names | code | one line per instruction
+// Each list is a u32 count followed by its items.
+
+const HEADER_LEN: usize = 4 + 2 + 4;
+
+/// A compiled program along with the file it was compiled from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledFile {
+    pub source_name: String,
+    pub chunk: Chunk,
+}
+
+/// Writes a compiled program out as a `.tgc` file. Constants can only be
+/// null, numbers, strings or booleans, which is all the compiler makes.
+pub fn encode(file: &CompiledFile) -> Result<Vec<u8>, String> {
+    let chunk = &file.chunk;
+    let mut body = Vec::new();
+    put_str(&mut body, &file.source_name);
+    put_u32(&mut body, chunk.constants.len() as u32);
+    for constant in &chunk.constants {
+        put_value(&mut body, constant)?;
+    }
+    put_u32(&mut body, chunk.slots.len() as u32);
+    for slot in &chunk.slots {
+        put_str(&mut body, slot);
+    }
+    put_u32(&mut body, chunk.code.len() as u32);
+    for op in &chunk.code {
+        put_op(&mut body, op);
+    }
+    put_u32(&mut body, chunk.lines.len() as u32);
+    for line in &chunk.lines {
+        put_u32(&mut body, *line as u32);
+    }
+
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&checksum(&body).to_le_bytes());
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+/// Reads a `.tgc` file and checks it is safe to run: the header, checksum
+/// and every operand are validated, and the stack is proven to stay balanced.
+pub fn decode(bytes: &[u8]) -> Result<CompiledFile, String> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err("Not a compiled Touch Grass file".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != FORMAT_VERSION {
+        return Err(format!("Unsupported format version {} (expected {}); recompile the program", version, FORMAT_VERSION));
+    }
+    let expected = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+    let body = &bytes[HEADER_LEN..];
+    if checksum(body) != expected {
+        return Err("Checksum mismatch; the file is corrupted".to_string());
+    }
+
+    let mut reader = Reader { bytes: body, pos: 0 };
+    let source_name = reader.string()?;
+    let mut chunk = Chunk::default();
+    for _ in 0..reader.u32()? {
+        chunk.constants.push(reader.value()?);
+    }
+    for _ in 0..reader.u32()? {
+        chunk.slots.push(reader.string()?);
+    }
+    for _ in 0..reader.u32()? {
+        chunk.code.push(reader.op()?);
+    }
+    for _ in 0..reader.u32()? {
+        chunk.lines.push(reader.u32()? as usize);
+    }
+    if reader.pos != body.len() {
+        return Err("Unexpected data after the line table".to_string());
+    }
+    validate(&chunk)?;
+    Ok(CompiledFile { source_name, chunk })
+}
+
+/// FNV-1a, 32 bit.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, b| (hash ^ *b as u32).wrapping_mul(0x01000193))
+}
+
+fn validate(chunk: &Chunk) -> Result<(), String> {
+    if chunk.lines.len() != chunk.code.len() {
+        return Err(format!("Line table has {} entries for {} instructions", chunk.lines.len(), chunk.code.len()));
+    }
+    let constant = |i: u32| (i as usize) < chunk.constants.len();
+    let slot = |i: u32| (i as usize) < chunk.slots.len();
+    // Jumping to the very end is how a program finishes.
+    let target = |i: u32| (i as usize) <= chunk.code.len();
+    for (pc, op) in chunk.code.iter().enumerate() {
+        let ok = match *op {
+            Op::Constant(i) => constant(i),
+            Op::Load(i) | Op::Declare(i) | Op::Assign(i) => slot(i),
+            Op::Call { name, .. } => constant(name) && matches!(chunk.constants[name as usize], Value::String(_)),
+            Op::Jump(to) | Op::JumpIfFalse(to) => target(to),
+            _ => true,
+        };
+        if !ok {
+            return Err(format!("Invalid operand at instruction {}: {}", pc, describe(chunk, op)));
+        }
+    }
+
+    // Walk every path, recording the stack depth at each instruction. Paths
+    // that meet must agree, and no instruction may pop an empty stack.
+    let mut depths: Vec<Option<usize>> = vec![None; chunk.code.len() + 1];
+    let mut pending = vec![(0, 0)];
+    while let Some((pc, depth)) = pending.pop() {
+        match depths[pc] {
+            Some(seen) if seen == depth => continue,
+            Some(seen) => return Err(format!("Stack depth at instruction {} is both {} and {}", pc, seen, depth)),
+            None => depths[pc] = Some(depth),
+        }
+        let op = match chunk.code.get(pc) {
+            Some(op) => op,
+            None => continue,
+        };
+        let (pops, pushes) = stack_effect(op);
+        if depth < pops {
+            return Err(format!("Instruction {} pops an empty stack", pc));
+        }
+        let depth = depth - pops + pushes;
+        match *op {
+            Op::Jump(to) => pending.push((to as usize, depth)),
+            Op::JumpIfFalse(to) => {
+                pending.push((to as usize, depth));
+                pending.push((pc + 1, depth));
+            }
+            _ => pending.push((pc + 1, depth)),
+        }
+    }
+    Ok(())
+}
+
+/// How many values an instruction pops and pushes.
+fn stack_effect(op: &Op) -> (usize, usize) {
+    match *op {
+        Op::Constant(_) | Op::Load(_) => (0, 1),
+        Op::Declare(_) | Op::Assign(_) | Op::Print | Op::Pop | Op::JumpIfFalse(_) => (1, 0),
+        Op::Add | Op::Subtract | Op::Greater | Op::Less | Op::Equal => (2, 1),
+        Op::Call { argc, .. } => (argc as usize, 1),
+        Op::Jump(_) => (0, 0),
+    }
+}
+
+/// A readable listing of a compiled file. `source` is the program's text,
+/// when available, to show each line next to the code compiled from it.
+pub fn disassemble(file: &CompiledFile, source: Option<&str>) -> String {
+    let chunk = &file.chunk;
+    let source_lines: Vec<&str> = source.map(|s| s.lines().collect()).unwrap_or_default();
+    let mut out = String::new();
+    let _ = writeln!(out, "; compiled from {} (format version {})", file.source_name, FORMAT_VERSION);
+    let _ = writeln!(out, "; {} constants, {} slots, {} instructions", chunk.constants.len(), chunk.slots.len(), chunk.code.len());
+    out.push_str("\nconstants:\n");
+    for (i, constant) in chunk.constants.iter().enumerate() {
+        let _ = writeln!(out, "    {:>4}  {}", i, show(constant));
+    }
+    out.push_str("\nslots:\n");
+    for (i, name) in chunk.slots.iter().enumerate() {
+        let _ = writeln!(out, "    {:>4}  {}", i, name);
+    }
+    out.push_str("\ncode:\n");
+    let mut last_line = None;
+    for (pc, op) in chunk.code.iter().enumerate() {
+        let line = chunk.lines[pc];
+        if last_line != Some(line) {
+            match source_lines.get(line.wrapping_sub(1)) {
+                Some(text) => { let _ = writeln!(out, "; line {}: {}", line, text.trim()); }
+                None if line > 0 => { let _ = writeln!(out, "; line {}", line); }
+                None => {}
+            }
+            last_line = Some(line);
+        }
+        let _ = writeln!(out, "    {:04}  {}", pc, describe(chunk, op));
+    }
+    out
+}
+
+/// One instruction with its operands resolved, e.g. `LOAD 1 (i)`.
+fn describe(chunk: &Chunk, op: &Op) -> String {
+    let constant = |i: u32| chunk.constants.get(i as usize).map_or("?".to_string(), show);
+    let slot = |i: u32| chunk.slots.get(i as usize).map_or("?", |s| s.as_str()).to_string();
+    match *op {
+        Op::Constant(i) => format!("{:<14}{} ({})", "CONSTANT", i, constant(i)),
+        Op::Load(i) => format!("{:<14}{} ({})", "LOAD", i, slot(i)),
+        Op::Declare(i) => format!("{:<14}{} ({})", "DECLARE", i, slot(i)),
+        Op::Assign(i) => format!("{:<14}{} ({})", "ASSIGN", i, slot(i)),
+        Op::Add => "ADD".to_string(),
+        Op::Subtract => "SUBTRACT".to_string(),
+        Op::Greater => "GREATER".to_string(),
+        Op::Less => "LESS".to_string(),
+        Op::Equal => "EQUAL".to_string(),
+        Op::Print => "PRINT".to_string(),
+        Op::Call { name, argc } => format!("{:<14}{} ({}), {} arg(s)", "CALL", name, constant(name), argc),
+        Op::Pop => "POP".to_string(),
+        Op::Jump(to) => format!("{:<14}{:04}", "JUMP", to),
+        Op::JumpIfFalse(to) => format!("{:<14}{:04}", "JUMP_IF_FALSE", to),
+    }
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
+
+// Value tags.
+const NULL: u8 = 0;
+const NUMBER: u8 = 1;
+const STRING: u8 = 2;
+const BOOLEAN: u8 = 3;
+
+// Opcodes.
+const CONSTANT: u8 = 1;
+const LOAD: u8 = 2;
+const DECLARE: u8 = 3;
+const ASSIGN: u8 = 4;
+const ADD: u8 = 5;
+const SUBTRACT: u8 = 6;
+const GREATER: u8 = 7;
+const LESS: u8 = 8;
+const EQUAL: u8 = 9;
+const PRINT: u8 = 10;
+const CALL: u8 = 11;
+const POP: u8 = 12;
+const JUMP: u8 = 13;
+const JUMP_IF_FALSE: u8 = 14;
+
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_u32(out, s.len() as u32);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn put_value(out: &mut Vec<u8>, value: &Value) -> Result<(), String> {
+    match value {
+        Value::Null => out.push(NULL),
+        Value::Number(n) => {
+            out.push(NUMBER);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::String(s) => {
+            out.push(STRING);
+            put_str(out, s);
+        }
+        Value::Boolean(b) => {
+            out.push(BOOLEAN);
+            out.push(*b as u8);
+        }
+        // Lists and maps only come from native functions at runtime.
+        Value::List(_) | Value::Map(_) => return Err(format!("Can't store the constant {} in a .tgc file", value)),
+    }
+    Ok(())
+}
+
+fn put_op(out: &mut Vec<u8>, op: &Op) {
+    let (code, operands): (u8, &[u32]) = match op {
+        Op::Constant(i) => (CONSTANT, std::slice::from_ref(i)),
+        Op::Load(i) => (LOAD, std::slice::from_ref(i)),
+        Op::Declare(i) => (DECLARE, std::slice::from_ref(i)),
+        Op::Assign(i) => (ASSIGN, std::slice::from_ref(i)),
+        Op::Add => (ADD, &[]),
+        Op::Subtract => (SUBTRACT, &[]),
+        Op::Greater => (GREATER, &[]),
+        Op::Less => (LESS, &[]),
+        Op::Equal => (EQUAL, &[]),
+        Op::Print => (PRINT, &[]),
+        Op::Call { name, argc } => {
+            out.push(CALL);
+            put_u32(out, *name);
+            put_u32(out, *argc);
+            return;
+        }
+        Op::Pop => (POP, &[]),
+        Op::Jump(to) => (JUMP, std::slice::from_ref(to)),
+        Op::JumpIfFalse(to) => (JUMP_IF_FALSE, std::slice::from_ref(to)),
+    };
+    out.push(code);
+    for operand in operands {
+        put_u32(out, *operand);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len()).ok_or("Unexpected end of file")?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "Invalid UTF-8 in string".to_string())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.u8()? {
+            NULL => Ok(Value::Null),
+            NUMBER => {
+                let bytes = self.take(8)?;
+                let mut n = [0; 8];
+                n.copy_from_slice(bytes);
+                Ok(Value::Number(i64::from_le_bytes(n)))
+            }
+            STRING => self.string().map(Value::String),
+            BOOLEAN => Ok(Value::Boolean(self.u8()? != 0)),
+            tag => Err(format!("Unknown constant tag {}", tag)),
+        }
+    }
+
+    fn op(&mut self) -> Result<Op, String> {
+        let op = match self.u8()? {
+            CONSTANT => Op::Constant(self.u32()?),
+            LOAD => Op::Load(self.u32()?),
+            DECLARE => Op::Declare(self.u32()?),
+            ASSIGN => Op::Assign(self.u32()?),
+            ADD => Op::Add,
+            SUBTRACT => Op::Subtract,
+            GREATER => Op::Greater,
+            LESS => Op::Less,
+            EQUAL => Op::Equal,
+            PRINT => Op::Print,
+            CALL => Op::Call { name: self.u32()?, argc: self.u32()? },
+            POP => Op::Pop,
+            JUMP => Op::Jump(self.u32()?),
+            JUMP_IF_FALSE => Op::JumpIfFalse(self.u32()?),
+            code => return Err(format!("Unknown opcode {}", code)),
+        };
+        Ok(op)
+    }
+}
@@ -1,4 +1,5 @@
 #[derive(Debug, PartialEq, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     // Keywords for the lang
     Print,
@@ -33,9 +34,14 @@ pub enum Token {
     Plus,
     Minus,
 
+    // Function calls and grouping
+    LeftParen,
+    RightParen,
+    Comma,
+
     // Identifiers and others
     Identifier(String),
-    Comment,
+    Comment(String), // `//` until the end of the line, or a `#!` first line
     Illegal(char),
     EOF,
 }
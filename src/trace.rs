@@ -0,0 +1,99 @@
+use std::io::Write;
+use crate::ast::Node;
+use crate::formatter;
+use crate::interpreter::{Event, Hook, Interpreter, RuntimeError, Value};
+use crate::json::Json;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    /// Indented, human readable lines.
+    Text,
+    /// One JSON object per line, stable enough to diff between versions.
+    JsonLines,
+}
+
+/// Logs everything the interpreter does: statements, operator results,
+/// variable writes and loop iterations, indented by block nesting.
+pub struct Tracer {
+    format: TraceFormat,
+    out: Box<dyn Write>,
+}
+
+impl Tracer {
+    pub fn new(format: TraceFormat, out: Box<dyn Write>) -> Self {
+        Tracer { format, out }
+    }
+
+    fn text(&mut self, depth: usize, text: String) {
+        let indent = "  ".repeat(depth.saturating_sub(1));
+        let _ = writeln!(self.out, "[trace] {}{}", indent, text);
+    }
+
+    fn json(&mut self, depth: usize, event: &str, mut fields: Vec<(&str, Json)>) {
+        fields.insert(0, ("event", event.into()));
+        fields.insert(1, ("depth", depth.saturating_sub(1).into()));
+        let _ = writeln!(self.out, "{}", Json::object(fields));
+    }
+}
+
+pub fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Number(n) => Json::from(*n),
+        Value::String(s) => Json::from(s.as_str()),
+        Value::Boolean(b) => Json::from(*b),
+        Value::List(items) => Json::Array(items.iter().map(value_to_json).collect()),
+        Value::Map(entries) => Json::object(entries.iter().map(|(key, value)| (key.as_str(), value_to_json(value))).collect()),
+        Value::Null => Json::Null,
+    }
+}
+
+impl Hook for Tracer {
+    fn before_statement(&mut self, interp: &mut Interpreter, line: usize, statement: &Node) -> Result<(), RuntimeError> {
+        let depth = interp.stack.len();
+        let code = formatter::statement_header(statement);
+        match self.format {
+            TraceFormat::Text => self.text(depth, format!("line {}: {}", line, code)),
+            TraceFormat::JsonLines => self.json(depth, "statement", vec![
+                ("line", line.into()),
+                ("code", code.into()),
+            ]),
+        }
+        Ok(())
+    }
+
+    fn event(&mut self, depth: usize, event: Event) {
+        // Values belong under the statement that produced them; loop events
+        // line up with the loop body.
+        let depth = match event {
+            Event::BinaryOp { .. } | Event::VariableWrite { .. } => depth + 1,
+            Event::LoopIteration(_) | Event::LoopEnd(_) => depth,
+        };
+        match (self.format, event) {
+            (TraceFormat::Text, Event::BinaryOp { left, operator, right, result }) => {
+                self.text(depth, format!("{} {} {} => {}", left, operator.symbol(), right, result));
+            }
+            (TraceFormat::Text, Event::VariableWrite { name, value }) => {
+                self.text(depth, format!("{} := {}", name, value));
+            }
+            (TraceFormat::Text, Event::LoopIteration(n)) => self.text(depth, format!("iteration {}", n)),
+            (TraceFormat::Text, Event::LoopEnd(n)) => self.text(depth, format!("loop done after {} iteration(s)", n)),
+            (TraceFormat::JsonLines, Event::BinaryOp { left, operator, right, result }) => {
+                self.json(depth, "binary_op", vec![
+                    ("left", value_to_json(left)),
+                    ("operator", operator.symbol().into()),
+                    ("right", value_to_json(right)),
+                    ("result", value_to_json(result)),
+                ]);
+            }
+            (TraceFormat::JsonLines, Event::VariableWrite { name, value }) => {
+                self.json(depth, "write", vec![("name", name.into()), ("value", value_to_json(value))]);
+            }
+            (TraceFormat::JsonLines, Event::LoopIteration(n)) => {
+                self.json(depth, "iteration", vec![("n", n.into())]);
+            }
+            (TraceFormat::JsonLines, Event::LoopEnd(n)) => {
+                self.json(depth, "loop_end", vec![("iterations", n.into())]);
+            }
+        }
+    }
+}
@@ -0,0 +1,588 @@
+use crate::ast::{Node, Operator};
+use crate::checker::TypeChecker;
+use crate::formatter::format_program;
+use crate::lexer;
+use crate::parser::{ParseError, Parser};
+
+/// Rewrites the "plain" syntax into Touch Grass. Plain syntax is a small
+/// Python-like language with the same meaning as Touch Grass:
+///
+/// ```text
+/// # comments start with #
+/// let count = 0             touch grass number count as 0
+/// count = count + 1         set count to count + 1
+/// count += 1                set count to count + 1
+/// print "hi"                print "hi"
+/// exit_code(1)              exit_code(1)
+/// if count > 1:             go outside if count > 1 then
+///     print "big"               print "big"
+/// elif count == 1:          fr fr instead
+///     print "one"               go outside if count = 1 then ...
+/// else:
+///     print "small"
+/// while count < 10:         while count < 10 then
+///     count += 1                set count to count + 1
+///                           fr fr
+/// ```
+///
+/// `let` always declares a `number`, the one type Touch Grass has a keyword
+/// for, so `let name = "grass"` is an error. Each statement is type checked
+/// as it's read, so a translation always passes `touch-grass check`. Blocks
+/// are indented under a line ending in `:`. Expressions have numbers,
+/// strings in single or double quotes (no escapes), `true`/`false`, names,
+/// calls, parentheses, `+`, `-` (also as a prefix), `==`, `<` and `>`.
+pub fn translate(source: &str) -> Result<String, Vec<ParseError>> {
+    let lines = split_lines(source)?;
+    let mut translator = Translator {
+        lines,
+        pos: 0,
+        errors: Vec::new(),
+        trailing: None,
+        checker: TypeChecker::new(),
+        type_errors: 0,
+    };
+    let statements = translator.block("");
+    if !translator.errors.is_empty() {
+        return Err(translator.errors);
+    }
+
+    let program = Node::Program(statements);
+    let output = format_program(&program);
+    // Names and strings are checked as they're read, so this should never
+    // fail; but don't hand back something that means a different program.
+    let mut parser = Parser::new(lexer::tokenize(&output));
+    if parser.parse() != without_comments(&program) || !parser.errors().is_empty() {
+        let message = "The translation doesn't parse back to the same program".to_string();
+        return Err(vec![ParseError { message, line: 0 }]);
+    }
+    Ok(output)
+}
+
+/// Words that mean something in Touch Grass, so can't be names.
+const TOUCH_GRASS_KEYWORDS: &[&str] = &[
+    "touch", "grass", "go", "outside", "if", "then", "instead", "fr", "print", "as", "number", "cap", "no_cap",
+    "bugatti", "set", "to", "while",
+];
+
+/// Python words a newcomer might reach for that have no translation.
+const UNSUPPORTED: &[&str] = &[
+    "and", "or", "not", "for", "in", "def", "return", "break", "continue", "None", "is", "import", "class",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(i64),
+    Str(String),
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "`{}`", name),
+            Token::Number(n) => write!(f, "`{}`", n),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Symbol(symbol) => write!(f, "`{}`", symbol),
+        }
+    }
+}
+
+struct Line {
+    number: usize,
+    indent: String,
+    tokens: Vec<Token>,
+    comment: Option<String>,
+}
+
+/// The symbols plain syntax has, longest first so `==` wins over `=`.
+const SYMBOLS: &[&str] = &["==", "+=", "-=", "!=", "<=", ">=", "=", "<", ">", "+", "-", "(", ")", ",", ":"];
+
+fn split_lines(source: &str) -> Result<Vec<Line>, Vec<ParseError>> {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        let number = i + 1;
+        let code = text.trim_start();
+        let indent = text[..text.len() - code.len()].to_string();
+        match tokenize_line(code) {
+            Ok((tokens, comment)) => {
+                if !tokens.is_empty() || comment.is_some() {
+                    lines.push(Line { number, indent, tokens, comment });
+                }
+            }
+            Err(message) => errors.push(ParseError { message, line: number }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(lines)
+    } else {
+        Err(errors)
+    }
+}
+
+fn tokenize_line(code: &str) -> Result<(Vec<Token>, Option<String>), String> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    'chars: while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            let text: String = chars[i + 1..].iter().collect();
+            return Ok((tokens, Some(format!("//{}", text.trim_end()))));
+        } else if c == '"' || c == '\'' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err("Unterminated string".to_string());
+            }
+            let text: String = chars[start..i].iter().collect();
+            if text.contains('"') {
+                return Err("Touch Grass strings can't contain `\"`".to_string());
+            }
+            tokens.push(Token::Str(text));
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            let n = digits.parse().map_err(|_| format!("{} is too big for a Touch Grass number", digits))?;
+            tokens.push(Token::Number(n));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else {
+            for symbol in SYMBOLS {
+                if chars[i..].starts_with(&symbol.chars().collect::<Vec<_>>()) {
+                    tokens.push(Token::Symbol(symbol));
+                    i += symbol.len();
+                    continue 'chars;
+                }
+            }
+            return Err(format!("Unexpected character `{}`", c));
+        }
+    }
+    Ok((tokens, None))
+}
+
+fn without_comments(node: &Node) -> Node {
+    let strip = |statements: &[Node]| -> Vec<Node> {
+        statements.iter().filter(|s| !matches!(s, Node::Comment { .. })).map(without_comments).collect()
+    };
+    match node {
+        Node::Program(statements) => Node::Program(strip(statements)),
+        Node::Block(statements) => Node::Block(strip(statements)),
+        Node::GoOutside { condition, then_branch, else_branch } => Node::GoOutside {
+            condition: condition.clone(),
+            then_branch: Box::new(without_comments(then_branch)),
+            else_branch: else_branch.as_ref().map(|branch| Box::new(without_comments(branch))),
+        },
+        Node::WhileLoop { condition, body } => {
+            Node::WhileLoop { condition: condition.clone(), body: Box::new(without_comments(body)) }
+        }
+        other => other.clone(),
+    }
+}
+
+struct Translator {
+    lines: Vec<Line>,
+    pos: usize,
+    errors: Vec<ParseError>,
+    /// A comment after a simple statement, to go right after it.
+    trailing: Option<String>,
+    /// Sees the statements in source order, like `check` sees the translation.
+    checker: TypeChecker,
+    /// How many of the checker's errors have been reported.
+    type_errors: usize,
+}
+
+impl Translator {
+    fn error(&mut self, line: usize, message: String) {
+        self.errors.push(ParseError { message, line });
+    }
+
+    /// Reports any type errors in a statement or condition read from `line`.
+    fn type_check(&mut self, line: usize, node: &Node) {
+        let new: Vec<String> = self.checker.check(node)[self.type_errors..]
+            .iter()
+            .map(|error| error.message.clone())
+            .collect();
+        self.type_errors += new.len();
+        for message in new {
+            self.error(line, message);
+        }
+    }
+
+    /// Reads statements indented exactly by `indent`, stopping at the first
+    /// line indented less. Comment-only lines go wherever they fall.
+    fn block(&mut self, indent: &str) -> Vec<Node> {
+        let mut statements = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.tokens.is_empty() {
+                let text = line.comment.clone().unwrap_or_default();
+                statements.push(Node::Comment { text, trailing: false });
+                self.pos += 1;
+                continue;
+            }
+            if line.indent != indent {
+                if line.indent.len() < indent.len() && indent.starts_with(line.indent.as_str()) {
+                    break;
+                }
+                let number = line.number;
+                self.error(number, "Unexpected indentation".to_string());
+                self.pos += 1;
+                continue;
+            }
+            let keyword = match line.tokens.first() {
+                Some(Token::Name(name)) => name.clone(),
+                _ => String::new(),
+            };
+            if keyword == "elif" || keyword == "else" {
+                let number = line.number;
+                self.error(number, format!("`{}` without an `if` before it", keyword));
+                self.pos += 1;
+                continue;
+            }
+            if let Some(statement) = self.statement(indent) {
+                statements.push(statement);
+            }
+            if let Some(text) = self.trailing.take() {
+                statements.push(Node::Comment { text, trailing: true });
+            }
+        }
+        statements
+    }
+
+    fn statement(&mut self, indent: &str) -> Option<Node> {
+        let line = &self.lines[self.pos];
+        let number = line.number;
+        let mut parser = LineParser { tokens: line.tokens.clone(), pos: 0, line: number };
+        let comment = line.comment.clone();
+        self.pos += 1;
+
+        let result = match parser.peek_name() {
+            Some("if") => {
+                parser.pos += 1;
+                return self.if_statement(parser, comment, indent);
+            }
+            Some("while") => {
+                parser.pos += 1;
+                let parsed = parser.expression().and_then(|condition| parser.header_end().map(|_| condition));
+                let condition = match parsed {
+                    Ok(condition) => condition,
+                    Err(message) => {
+                        self.error(number, message);
+                        self.skip_block(indent);
+                        return None;
+                    }
+                };
+                self.type_check(number, &condition);
+                let body = self.body(indent, number, "while", comment);
+                return Some(Node::WhileLoop { condition: Box::new(condition), body: Box::new(body) });
+            }
+            _ => parser.simple_statement(),
+        };
+        match result {
+            Ok(statement) => {
+                self.type_check(number, &statement);
+                self.trailing = comment;
+                Some(statement)
+            }
+            Err(message) => {
+                self.error(number, message);
+                None
+            }
+        }
+    }
+
+    /// `if cond:` with its block and any `elif` / `else` after it.
+    fn if_statement(&mut self, mut parser: LineParser, comment: Option<String>, indent: &str) -> Option<Node> {
+        let number = parser.line;
+        let condition = match parser.expression().and_then(|condition| parser.header_end().map(|_| condition)) {
+            Ok(condition) => condition,
+            Err(message) => {
+                self.error(number, message);
+                self.skip_block(indent);
+                return None;
+            }
+        };
+        self.type_check(number, &condition);
+        let then_branch = self.body(indent, number, "if", comment);
+
+        let else_branch = match self.lines.get(self.pos) {
+            Some(line) if line.indent == indent => match line.tokens.first() {
+                Some(Token::Name(name)) if name == "elif" => {
+                    let parser = LineParser { tokens: line.tokens.clone(), pos: 1, line: line.number };
+                    let comment = line.comment.clone();
+                    self.pos += 1;
+                    self.if_statement(parser, comment, indent).map(|node| Node::Block(vec![node]))
+                }
+                Some(Token::Name(name)) if name == "else" => {
+                    let mut parser = LineParser { tokens: line.tokens.clone(), pos: 1, line: line.number };
+                    let (number, comment) = (line.number, line.comment.clone());
+                    self.pos += 1;
+                    if let Err(message) = parser.header_end() {
+                        self.error(number, message);
+                    }
+                    Some(self.body(indent, number, "else", comment))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        Some(Node::GoOutside {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+        })
+    }
+
+    /// The indented block under a header line.
+    fn body(&mut self, indent: &str, header: usize, keyword: &str, comment: Option<String>) -> Node {
+        let mut statements = Vec::new();
+        if let Some(text) = comment {
+            statements.push(Node::Comment { text, trailing: true });
+        }
+        let inner = self.lines[self.pos..]
+            .iter()
+            .find(|line| !line.tokens.is_empty())
+            .map(|line| line.indent.clone())
+            .filter(|inner| inner.len() > indent.len() && inner.starts_with(indent));
+        match inner {
+            Some(inner) => statements.extend(self.block(&inner)),
+            None => self.error(header, format!("Expected an indented block after `{}`", keyword)),
+        }
+        Node::Block(statements)
+    }
+
+    /// Skips the block under a header that failed to parse.
+    fn skip_block(&mut self, indent: &str) {
+        while let Some(line) = self.lines.get(self.pos) {
+            if !line.tokens.is_empty() && line.indent.len() <= indent.len() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+/// Parses the tokens of one line.
+struct LineParser {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+}
+
+impl LineParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_name(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Name(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn end(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(Token::Name(name)) if UNSUPPORTED.contains(&name.as_str()) => {
+                Err(format!("`{}` has no Touch Grass equivalent", name))
+            }
+            Some(token) => Err(format!("Unexpected {}", token)),
+        }
+    }
+
+    /// The `:` that ends an `if`, `elif`, `else` or `while` line.
+    fn header_end(&mut self) -> Result<(), String> {
+        if !self.eat(":") {
+            return Err(match self.peek() {
+                Some(token) => format!("Expected `:` but found {}", token),
+                None => "Expected `:` at the end of the line".to_string(),
+            });
+        }
+        self.end()
+    }
+
+    fn simple_statement(&mut self) -> Result<Node, String> {
+        let statement = match self.peek_name() {
+            Some("let") => {
+                self.pos += 1;
+                let name = self.name()?;
+                if !self.eat("=") {
+                    return Err(format!("Expected `=` after `let {}`", name));
+                }
+                let value = self.expression()?;
+                // Touch Grass only has the one type keyword.
+                Node::VarDeclaration { name, var_type: "number".to_string(), value: Box::new(value) }
+            }
+            Some("print") => {
+                self.pos += 1;
+                Node::Print(Box::new(self.expression()?))
+            }
+            _ => match self.tokens.get(self.pos + 1) {
+                Some(Token::Symbol(op @ ("=" | "+=" | "-="))) => {
+                    let op = *op;
+                    let name = self.name()?;
+                    self.pos += 1;
+                    let value = self.expression()?;
+                    let value = match op {
+                        "+=" => binary(Node::Identifier(name.clone()), Operator::Plus, value),
+                        "-=" => binary(Node::Identifier(name.clone()), Operator::Minus, value),
+                        _ => value,
+                    };
+                    Node::Assignment { name, value: Box::new(value) }
+                }
+                _ => {
+                    let expr = self.expression()?;
+                    self.end()?;
+                    if !matches!(expr, Node::Call { .. }) {
+                        return Err("Only a function call can be a statement on its own".to_string());
+                    }
+                    expr
+                }
+            },
+        };
+        self.end()?;
+        Ok(statement)
+    }
+
+    /// A variable or function name that's also valid in Touch Grass.
+    fn name(&mut self) -> Result<String, String> {
+        let name = match self.peek() {
+            Some(Token::Name(name)) => name.clone(),
+            Some(token) => return Err(format!("Expected a name but found {}", token)),
+            None => return Err("Expected a name".to_string()),
+        };
+        check_name(&name)?;
+        self.pos += 1;
+        Ok(name)
+    }
+
+    /// Comparisons bind looser than `+` and `-`, as in Touch Grass.
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut left = self.additive()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol("==")) => Operator::Equals,
+                Some(Token::Symbol("<")) => Operator::LessThan,
+                Some(Token::Symbol(">")) => Operator::GreaterThan,
+                Some(Token::Symbol(op @ ("!=" | "<=" | ">="))) => {
+                    return Err(format!("`{}` has no Touch Grass equivalent", op));
+                }
+                Some(Token::Symbol("=")) => return Err("Use `==` to compare".to_string()),
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.additive()?;
+            left = binary(left, operator, right);
+        }
+    }
+
+    fn additive(&mut self) -> Result<Node, String> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = if self.eat("+") {
+                Operator::Plus
+            } else if self.eat("-") {
+                Operator::Minus
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            left = binary(left, operator, right);
+        }
+    }
+
+    /// Touch Grass has no negative literals, so `-x` becomes `0 - x`.
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat("-") {
+            let operand = self.unary()?;
+            return Ok(binary(Node::NumberLiteral(0), Operator::Minus, operand));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        let token = self.peek().cloned().ok_or("Expected an expression")?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Node::NumberLiteral(n)),
+            Token::Str(s) => Ok(Node::StringLiteral(s)),
+            Token::Name(name) => match name.as_str() {
+                "true" | "True" => Ok(Node::Boolean(true)),
+                "false" | "False" => Ok(Node::Boolean(false)),
+                _ => {
+                    check_name(&name)?;
+                    if !self.eat("(") {
+                        return Ok(Node::Identifier(name));
+                    }
+                    let mut args = Vec::new();
+                    if !self.eat(")") {
+                        loop {
+                            args.push(self.expression()?);
+                            if self.eat(")") {
+                                break;
+                            }
+                            if !self.eat(",") {
+                                return Err(format!("Expected `,` or `)` in the call to {}", name));
+                            }
+                        }
+                    }
+                    Ok(Node::Call { name, args })
+                }
+            },
+            Token::Symbol("(") => {
+                let expr = self.expression()?;
+                if !self.eat(")") {
+                    return Err("Expected `)`".to_string());
+                }
+                Ok(expr)
+            }
+            token => Err(format!("Unexpected {}", token)),
+        }
+    }
+}
+
+fn binary(left: Node, operator: Operator, right: Node) -> Node {
+    Node::BinaryOp { left: Box::new(left), operator, right: Box::new(right) }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if UNSUPPORTED.contains(&name) {
+        return Err(format!("`{}` has no Touch Grass equivalent", name));
+    }
+    if ["let", "if", "elif", "else", "while", "print", "true", "false", "True", "False"].contains(&name) {
+        return Err(format!("Unexpected `{}`", name));
+    }
+    if TOUCH_GRASS_KEYWORDS.contains(&name) {
+        return Err(format!("`{}` is a keyword in Touch Grass; pick another name", name));
+    }
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(char::is_alphabetic) && chars.all(|c| c.is_alphabetic() || c == '_');
+    if !valid {
+        return Err(format!("`{}` can't be a Touch Grass name: use letters and underscores, starting with a letter", name));
+    }
+    Ok(())
+}
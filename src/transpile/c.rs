@@ -0,0 +1,506 @@
+use crate::ast::{Node, Operator};
+use crate::builtins::BUILTINS;
+use super::{variables, Declared};
+
+/// Runtime support for generated programs. Values are tagged unions; strings
+/// only ever come from literals, `args`, the environment or input, and live
+/// until the program exits, so nothing is freed.
+const PRELUDE: &str = r#"#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* Not every program uses every part of the runtime. */
+#if defined(__GNUC__)
+#define TG_RUNTIME static __attribute__((unused))
+#else
+#define TG_RUNTIME static
+#endif
+
+typedef enum { TG_UNDECLARED, TG_NULL, TG_NUMBER, TG_STRING, TG_BOOLEAN, TG_LIST } tg_tag;
+
+typedef struct tg_value {
+    tg_tag tag;
+    union {
+        int64_t number;
+        const char *string;
+        int boolean;
+        struct tg_list *list;
+    } as;
+} tg_value;
+
+typedef struct tg_list {
+    size_t len;
+    tg_value *items;
+} tg_list;
+
+static int tg_exit_code = 0;
+
+TG_RUNTIME tg_value tg_null(void) { tg_value v; v.tag = TG_NULL; v.as.number = 0; return v; }
+TG_RUNTIME tg_value tg_number(int64_t n) { tg_value v; v.tag = TG_NUMBER; v.as.number = n; return v; }
+TG_RUNTIME tg_value tg_string(const char *s) { tg_value v; v.tag = TG_STRING; v.as.string = s; return v; }
+TG_RUNTIME tg_value tg_boolean(int b) { tg_value v; v.tag = TG_BOOLEAN; v.as.boolean = b; return v; }
+
+TG_RUNTIME void tg_error(const char *format, ...) {
+    va_list args;
+    fflush(stdout);
+    fputs("Runtime error: ", stderr);
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fputc('\n', stderr);
+    exit(3);
+}
+
+TG_RUNTIME const char *tg_type_name(tg_value v) {
+    switch (v.tag) {
+    case TG_NUMBER: return "number";
+    case TG_STRING: return "string";
+    case TG_BOOLEAN: return "boolean";
+    case TG_LIST: return "list";
+    default: return "null";
+    }
+}
+
+/* Same text as Value's Display: strings inside lists are quoted. */
+TG_RUNTIME void tg_display(tg_value v, int quote_strings) {
+    size_t i;
+    switch (v.tag) {
+    case TG_NUMBER: printf("%lld", (long long)v.as.number); break;
+    case TG_STRING: printf(quote_strings ? "\"%s\"" : "%s", v.as.string); break;
+    case TG_BOOLEAN: fputs(v.as.boolean ? "true" : "false", stdout); break;
+    case TG_LIST:
+        putchar('[');
+        for (i = 0; i < v.as.list->len; i++) {
+            if (i > 0) fputs(", ", stdout);
+            tg_display(v.as.list->items[i], 1);
+        }
+        putchar(']');
+        break;
+    default: fputs("null", stdout); break;
+    }
+}
+
+TG_RUNTIME void tg_print(tg_value v) {
+    tg_display(v, 0);
+    putchar('\n');
+}
+
+TG_RUNTIME int tg_truthy(tg_value v) {
+    switch (v.tag) {
+    case TG_BOOLEAN: return v.as.boolean;
+    case TG_NUMBER: return v.as.number != 0;
+    case TG_STRING: return v.as.string[0] != '\0';
+    case TG_LIST: return v.as.list->len > 0;
+    default: return 0;
+    }
+}
+
+/* Reading a variable that was never declared gives null. */
+TG_RUNTIME tg_value tg_read(tg_value v) {
+    return v.tag == TG_UNDECLARED ? tg_null() : v;
+}
+
+/* `set` on a variable that was never declared is an error. */
+TG_RUNTIME tg_value tg_assign(tg_value current, const char *name, tg_value value) {
+    if (current.tag == TG_UNDECLARED) tg_error("Undefined variable: %s", name);
+    return value;
+}
+
+/* Operators only work on numbers; anything else gives null. Arithmetic wraps. */
+TG_RUNTIME tg_value tg_add(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_number((int64_t)((uint64_t)a.as.number + (uint64_t)b.as.number));
+}
+TG_RUNTIME tg_value tg_sub(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_number((int64_t)((uint64_t)a.as.number - (uint64_t)b.as.number));
+}
+TG_RUNTIME tg_value tg_gt(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_boolean(a.as.number > b.as.number);
+}
+TG_RUNTIME tg_value tg_lt(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_boolean(a.as.number < b.as.number);
+}
+TG_RUNTIME tg_value tg_eq(tg_value a, tg_value b) {
+    if (a.tag != TG_NUMBER || b.tag != TG_NUMBER) return tg_null();
+    return tg_boolean(a.as.number == b.as.number);
+}
+
+typedef struct {
+    const char *name;
+    size_t min_args, max_args;
+} tg_builtin;
+"#;
+
+/// The rest of the runtime, after the table of builtins.
+const PRELUDE_END: &str = r#"
+TG_RUNTIME size_t tg_utf8_length(const char *s) {
+    size_t n = 0;
+    for (; *s; s++) {
+        if (((unsigned char)*s & 0xC0) != 0x80) n++;
+    }
+    return n;
+}
+
+/* The next line without its line ending, or null at the end of input. */
+TG_RUNTIME tg_value tg_read_line(void) {
+    size_t len = 0, capacity = 64;
+    char *line = malloc(capacity);
+    int c;
+    while ((c = getchar()) != EOF && c != '\n') {
+        if (len + 1 == capacity) line = realloc(line, capacity *= 2);
+        line[len++] = (char)c;
+    }
+    if (c == EOF && len == 0) {
+        free(line);
+        return tg_null();
+    }
+    if (c == '\n' && len > 0 && line[len - 1] == '\r') len--;
+    line[len] = '\0';
+    return tg_string(line);
+}
+
+TG_RUNTIME tg_value tg_read_all(void) {
+    size_t len = 0, capacity = 4096, n;
+    char *text = malloc(capacity);
+    while ((n = fread(text + len, 1, capacity - len - 1, stdin)) > 0) {
+        len += n;
+        if (len + 1 == capacity) text = realloc(text, capacity *= 2);
+    }
+    text[len] = '\0';
+    return tg_string(text);
+}
+
+/* An optional minus sign and then digits, nothing else. */
+TG_RUNTIME tg_value tg_parse_number(const char *text) {
+    const char *p = text + (text[0] == '-');
+    uint64_t n = 0, limit = text[0] == '-' ? (uint64_t)INT64_MAX + 1 : (uint64_t)INT64_MAX;
+    if (*p == '\0') tg_error("Cannot parse \"%s\" as a number", text);
+    for (; *p; p++) {
+        if (*p < '0' || *p > '9' || n > (limit - (uint64_t)(*p - '0')) / 10) {
+            tg_error("Cannot parse \"%s\" as a number", text);
+        }
+        n = n * 10 + (uint64_t)(*p - '0');
+    }
+    return tg_number(text[0] == '-' ? (int64_t)(0 - n) : (int64_t)n);
+}
+
+TG_RUNTIME tg_value tg_call(const char *name, size_t argc, const tg_value *args) {
+    const tg_builtin *builtin = NULL;
+    size_t i;
+    for (i = 0; i < sizeof tg_builtins / sizeof tg_builtins[0]; i++) {
+        if (strcmp(tg_builtins[i].name, name) == 0) builtin = &tg_builtins[i];
+    }
+    if (builtin == NULL) tg_error("Unknown function: %s", name);
+    if (argc < builtin->min_args || argc > builtin->max_args) {
+        if (builtin->min_args == builtin->max_args) {
+            tg_error("%s expects %zu argument(s) but got %zu", name, builtin->min_args, argc);
+        }
+        tg_error("%s expects %zu to %zu argument(s) but got %zu", name, builtin->min_args, builtin->max_args, argc);
+    }
+
+    if (strcmp(name, "env") == 0 && args[0].tag == TG_STRING) {
+        const char *value = getenv(args[0].as.string);
+        return value ? tg_string(value) : tg_null();
+    }
+    if (strcmp(name, "exit_code") == 0 && args[0].tag == TG_NUMBER) {
+        int64_t code = args[0].as.number;
+        if (code < INT32_MIN || code > INT32_MAX) tg_error("Exit code %lld is out of range", (long long)code);
+        tg_exit_code = (int32_t)code;
+        return tg_null();
+    }
+    if (strcmp(name, "len") == 0 && args[0].tag == TG_LIST) return tg_number((int64_t)args[0].as.list->len);
+    if (strcmp(name, "len") == 0 && args[0].tag == TG_STRING) return tg_number((int64_t)tg_utf8_length(args[0].as.string));
+    if (strcmp(name, "at") == 0 && args[0].tag == TG_LIST && args[1].tag == TG_NUMBER) {
+        int64_t index = args[1].as.number;
+        if (index < 0 || (uint64_t)index >= args[0].as.list->len) {
+            tg_error("Index %lld is out of range for a list of %zu items", (long long)index, args[0].as.list->len);
+        }
+        return args[0].as.list->items[index];
+    }
+    if (strcmp(name, "read_line") == 0 && (argc == 0 || args[0].tag == TG_STRING)) {
+        if (argc == 1) {
+            fputs(args[0].as.string, stdout);
+            fflush(stdout);
+        }
+        return tg_read_line();
+    }
+    if (strcmp(name, "read_all") == 0) return tg_read_all();
+    if (strcmp(name, "parse_number") == 0 && args[0].tag == TG_STRING) return tg_parse_number(args[0].as.string);
+    /* Generated programs get no filesystem access, like `run` without --allow-read or --allow-write. */
+    if ((strcmp(name, "read_file") == 0 || strcmp(name, "list_dir") == 0 || strcmp(name, "file_exists") == 0)
+        && args[0].tag == TG_STRING) {
+        tg_error("Not allowed to read \"%s\"", args[0].as.string);
+    }
+    if (((strcmp(name, "write_file") == 0 && args[1].tag == TG_STRING) || strcmp(name, "delete_file") == 0)
+        && args[0].tag == TG_STRING) {
+        tg_error("Not allowed to write \"%s\"", args[0].as.string);
+    }
+
+    fflush(stdout);
+    fprintf(stderr, "Runtime error: Invalid arguments to %s: ", name);
+    for (i = 0; i < argc; i++) {
+        fprintf(stderr, i > 0 ? ", %s" : "%s", tg_type_name(args[i]));
+    }
+    fputc('\n', stderr);
+    exit(3);
+}
+
+TG_RUNTIME tg_value tg_args(int argc, char **argv) {
+    static tg_list list;
+    tg_value v;
+    int i;
+    list.len = argc > 1 ? (size_t)(argc - 1) : 0;
+    list.items = malloc((list.len ? list.len : 1) * sizeof(tg_value));
+    for (i = 1; i < argc; i++) list.items[i - 1] = tg_string(argv[i]);
+    v.tag = TG_LIST;
+    v.as.list = &list;
+    return v;
+}
+"#;
+
+const INDENT: &str = "    ";
+
+pub fn generate(program: &Node, source_name: &str) -> String {
+    let mut out = format!("/* Generated by touch-grass transpile from {} */\n", comment_safe(source_name));
+    out.push_str("/* The touch-grass runtime, the same in every program. */\n");
+    out.push_str(PRELUDE);
+    out.push_str("\nstatic const tg_builtin tg_builtins[] = {\n");
+    for builtin in BUILTINS {
+        out.push_str(&format!("    {{ \"{}\", {}, {} }},\n", builtin.name, builtin.min_args, builtin.max_args));
+    }
+    out.push_str("};\n");
+    out.push_str(PRELUDE_END);
+    out.push_str("\n/* End of the runtime. */\n\n");
+
+    // Zero-initialized globals have the TG_UNDECLARED tag.
+    out.push_str(&format!("static tg_value {};\n", name("args")));
+    for var in variables(program) {
+        if var != "args" {
+            out.push_str(&format!("static tg_value {};\n", name(&var)));
+        }
+    }
+
+    let mut gen = Generator { out: String::new(), depth: 1, declared: Declared::new(), pending: Vec::new(), temps: 0 };
+    gen.statement(program);
+    out.push_str("\nint main(int argc, char **argv) {\n");
+    out.push_str(&format!("{}{} = tg_args(argc, argv);\n", INDENT, name("args")));
+    out.push_str(&gen.out);
+    out.push_str(&format!("{}return tg_exit_code;\n}}\n", INDENT));
+    out
+}
+
+/// C names for variables. Plain ASCII names get a `v_` prefix, which keeps
+/// them clear of keywords and the runtime; others are hex-encoded under `u_`.
+fn name(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        format!("v_{}", name)
+    } else {
+        let mut out = String::from("u");
+        for c in name.chars() {
+            out.push_str(&format!("_{:x}", c as u32));
+        }
+        out
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            // `??` could start a trigraph.
+            b'?' => out.push_str("\\?"),
+            b' '..=b'~' => out.push(b as char),
+            // Octal escapes stop after three digits, unlike hex ones.
+            b => out.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn comment_safe(text: &str) -> String {
+    text.replace("*/", "* /")
+}
+
+/// Whether evaluating an expression can have an effect, i.e. calls a builtin.
+/// C leaves the order of function arguments unspecified, so those get
+/// evaluated into temporaries first to keep Touch Grass's left-to-right order.
+fn has_call(node: &Node) -> bool {
+    match node {
+        Node::Call { .. } => true,
+        Node::BinaryOp { left, right, .. } => has_call(left) || has_call(right),
+        _ => false,
+    }
+}
+
+struct Generator {
+    out: String,
+    depth: usize,
+    declared: Declared,
+    /// Temporaries the current statement needs, computed before it.
+    pending: Vec<String>,
+    temps: usize,
+}
+
+impl Generator {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn flush_pending(&mut self) {
+        for temp in std::mem::take(&mut self.pending) {
+            self.line(&temp);
+        }
+    }
+
+    fn block(&mut self, block: &Node) {
+        self.depth += 1;
+        self.declared.enter_block();
+        self.statement(block);
+        self.declared.leave_block();
+        self.depth -= 1;
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) | Node::Block(statements) => {
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+            }
+            Node::Statement { statement, .. } => self.statement(statement),
+            Node::VarDeclaration { name: var, value, .. } => {
+                let value = self.expression(value);
+                self.flush_pending();
+                self.line(&format!("{} = {};", name(var), value));
+                self.declared.insert(var);
+            }
+            Node::Assignment { name: var, value } => {
+                let value = self.expression(value);
+                self.flush_pending();
+                let var_name = name(var);
+                if self.declared.contains(var) {
+                    self.line(&format!("{} = {};", var_name, value));
+                } else {
+                    self.line(&format!("{} = tg_assign({}, {}, {});", var_name, var_name, string(var), value));
+                }
+            }
+            Node::Print(expr) => {
+                let value = self.expression(expr);
+                self.flush_pending();
+                self.line(&format!("tg_print({});", value));
+            }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                let condition = self.expression(condition);
+                self.flush_pending();
+                self.line(&format!("if (tg_truthy({})) {{", condition));
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.line("} else {");
+                    self.block(else_branch);
+                }
+                self.line("}");
+            }
+            Node::WhileLoop { condition, body } => {
+                let value = self.expression(condition);
+                if self.pending.is_empty() {
+                    self.line(&format!("while (tg_truthy({})) {{", value));
+                    self.block(body);
+                } else {
+                    // The temporaries have to be recomputed on every pass.
+                    self.line("for (;;) {");
+                    self.depth += 1;
+                    self.flush_pending();
+                    self.line(&format!("if (!tg_truthy({})) break;", value));
+                    self.depth -= 1;
+                    self.block(body);
+                }
+                self.line("}");
+            }
+            Node::Comment { text, trailing } => {
+                if !text.starts_with("//") {
+                    return;
+                }
+                if *trailing && self.out.ends_with('\n') {
+                    self.out.pop();
+                    self.out.push(' ');
+                    self.out.push_str(text);
+                    self.out.push('\n');
+                } else {
+                    self.line(text);
+                }
+            }
+            expr => {
+                let value = self.expression(expr);
+                self.flush_pending();
+                self.line(&format!("{};", value));
+            }
+        }
+    }
+
+    /// Stores an already generated expression in a new temporary.
+    fn temp(&mut self, value: String) -> String {
+        self.temps += 1;
+        let temp = format!("t{}", self.temps);
+        self.pending.push(format!("tg_value {} = {};", temp, value));
+        temp
+    }
+
+    /// Generates each operand, moving all but the last into temporaries when
+    /// more than one of them could have an effect.
+    fn operands(&mut self, nodes: &[&Node]) -> Vec<String> {
+        let ordered = nodes.iter().filter(|node| has_call(node)).count() > 1;
+        let last = nodes.len().saturating_sub(1);
+        let mut values = Vec::with_capacity(nodes.len());
+        for (i, node) in nodes.iter().enumerate() {
+            let value = self.expression(node);
+            values.push(if ordered && i < last { self.temp(value) } else { value });
+        }
+        values
+    }
+
+    fn expression(&mut self, node: &Node) -> String {
+        match node {
+            Node::Identifier(var) if self.declared.contains(var) => name(var),
+            Node::Identifier(var) => format!("tg_read({})", name(var)),
+            Node::NumberLiteral(n) => format!("tg_number({})", n),
+            Node::StringLiteral(s) => format!("tg_string({})", string(s)),
+            Node::Boolean(b) => format!("tg_boolean({})", *b as u8),
+            Node::Bugatti => format!("tg_string({})", string("Bugatti!")),
+            Node::BinaryOp { left, operator, right } => {
+                let function = match operator {
+                    Operator::Plus => "tg_add",
+                    Operator::Minus => "tg_sub",
+                    Operator::GreaterThan => "tg_gt",
+                    Operator::LessThan => "tg_lt",
+                    Operator::Equals => "tg_eq",
+                };
+                let values = self.operands(&[left, right]);
+                format!("{}({}, {})", function, values[0], values[1])
+            }
+            Node::Call { name: function, args } => {
+                if args.is_empty() {
+                    return format!("tg_call({}, 0, NULL)", string(function));
+                }
+                let nodes: Vec<&Node> = args.iter().collect();
+                let values = self.operands(&nodes);
+                format!("tg_call({}, {}, (tg_value[]){{ {} }})", string(function), args.len(), values.join(", "))
+            }
+            _ => "tg_null()".to_string(),
+        }
+    }
+}
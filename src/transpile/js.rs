@@ -0,0 +1,335 @@
+use crate::ast::{Node, Operator};
+use crate::builtins::BUILTINS;
+use super::{variables, Declared};
+
+/// Runtime support for generated programs. Numbers are BigInts wrapped to
+/// 64 bits, `null` is null, lists are arrays; everything else maps directly.
+/// It goes inside `const $tg = (() => {`, after the table of builtin arities.
+const PRELUDE: &str = r#"  class RuntimeError extends Error {}
+  const node = typeof process !== "undefined" && process.versions != null && process.versions.node != null;
+
+  const typeName = (value) => {
+    switch (typeof value) {
+      case "bigint": return "number";
+      case "string": return "string";
+      case "boolean": return "boolean";
+      default: return Array.isArray(value) ? "list" : "null";
+    }
+  };
+
+  // Same text as Value's Display: strings inside lists are quoted.
+  const display = (value) => {
+    if (value === null || value === undefined) return "null";
+    if (Array.isArray(value)) {
+      return "[" + value.map((item) => typeof item === "string" ? `"${item}"` : display(item)).join(", ") + "]";
+    }
+    return String(value);
+  };
+
+  // Stdin is read a byte at a time, so a prompt shows before each line.
+  let inputEnded = false;
+  const readBytes = (stopAtNewline) => {
+    const bytes = [];
+    const buffer = new Uint8Array(1);
+    while (node && !inputEnded) {
+      let n = 0;
+      try {
+        n = require("fs").readSync(0, buffer, 0, 1, null);
+      } catch (error) {
+        if (error.code === "EAGAIN") continue;
+        if (error.code !== "EOF") throw error;
+      }
+      if (n === 0) {
+        inputEnded = true;
+      } else if (stopAtNewline && buffer[0] === 10) {
+        return { bytes, newline: true };
+      } else {
+        bytes.push(buffer[0]);
+      }
+    }
+    return { bytes, newline: false };
+  };
+  const decode = (bytes) => new TextDecoder().decode(Uint8Array.from(bytes));
+
+  // Generated programs get no filesystem access, like `run` without --allow-read or --allow-write.
+  const denied = (access, path) => {
+    if (typeof path !== "string") return undefined;
+    throw new RuntimeError(`Not allowed to ${access} "${path}"`);
+  };
+
+  const numbers = (op) => (a, b) => typeof a === "bigint" && typeof b === "bigint" ? op(a, b) : null;
+
+  const builtins = {
+    env: (key) => typeof key === "string" ? (node ? process.env[key] ?? null : null) : undefined,
+    exit_code: (code) => {
+      if (typeof code !== "bigint") return undefined;
+      if (BigInt.asIntN(32, code) !== code) throw new RuntimeError(`Exit code ${code} is out of range`);
+      $tg.exitCode = Number(code);
+      return null;
+    },
+    len: (value) => {
+      if (Array.isArray(value)) return BigInt(value.length);
+      if (typeof value === "string") return BigInt([...value].length);
+      return undefined;
+    },
+    at: (list, index) => {
+      if (!Array.isArray(list) || typeof index !== "bigint") return undefined;
+      if (index < 0n || index >= BigInt(list.length)) {
+        throw new RuntimeError(`Index ${index} is out of range for a list of ${list.length} items`);
+      }
+      return list[Number(index)];
+    },
+    read_line: (prompt) => {
+      if (prompt !== undefined && typeof prompt !== "string") return undefined;
+      if (prompt !== undefined) $tg.prompt(prompt);
+      const { bytes, newline } = readBytes(true);
+      if (!newline && bytes.length === 0) return null;
+      if (newline && bytes[bytes.length - 1] === 13) bytes.pop();
+      return decode(bytes);
+    },
+    read_all: () => decode(readBytes(false).bytes),
+    parse_number: (text) => {
+      if (typeof text !== "string") return undefined;
+      const n = /^-?[0-9]+$/.test(text) ? BigInt(text) : null;
+      if (n === null || n !== BigInt.asIntN(64, n)) throw new RuntimeError(`Cannot parse "${text}" as a number`);
+      return n;
+    },
+    read_file: (path) => denied("read", path),
+    write_file: (path, text) => typeof text === "string" ? denied("write", path) : undefined,
+    list_dir: (path) => denied("read", path),
+    file_exists: (path) => denied("read", path),
+    delete_file: (path) => denied("write", path),
+  };
+
+  return {
+    RuntimeError,
+    args: node ? process.argv.slice(2) : [],
+    exitCode: null,
+    // Replace to send output somewhere other than the console.
+    write: (text) => console.log(text),
+    // Shows read_line's prompt, without a newline; replace along with write.
+    prompt: (text) => node ? process.stdout.write(text) : console.log(text),
+    print(value) {
+      this.write(display(value));
+    },
+    truthy(value) {
+      if (typeof value === "bigint") return value !== 0n;
+      if (typeof value === "string" || Array.isArray(value)) return value.length > 0;
+      return value === true;
+    },
+    add: numbers((a, b) => BigInt.asIntN(64, a + b)),
+    sub: numbers((a, b) => BigInt.asIntN(64, a - b)),
+    gt: numbers((a, b) => a > b),
+    lt: numbers((a, b) => a < b),
+    eq: numbers((a, b) => a === b),
+    // `set` on a variable that was never declared is an error.
+    assign(current, name, value) {
+      if (current === undefined) throw new RuntimeError(`Undefined variable: ${name}`);
+      return value;
+    },
+    call(name, ...args) {
+      const arity = ARITY[name];
+      if (arity === undefined) throw new RuntimeError(`Unknown function: ${name}`);
+      const [min, max] = arity;
+      if (args.length < min || args.length > max) {
+        const expected = min === max ? `${min}` : `${min} to ${max}`;
+        throw new RuntimeError(`${name} expects ${expected} argument(s) but got ${args.length}`);
+      }
+      const result = builtins[name](...args);
+      if (result === undefined) {
+        throw new RuntimeError(`Invalid arguments to ${name}: ${args.map(typeName).join(", ")}`);
+      }
+      return result;
+    },
+    run(program) {
+      try {
+        program();
+      } catch (error) {
+        if (!(error instanceof RuntimeError)) throw error;
+        console.error(`Runtime error: ${error.message}`);
+        if (node) process.exitCode = 3;
+        return;
+      }
+      if (node && this.exitCode !== null) process.exitCode = this.exitCode;
+    },
+  };
+})();
+"#;
+
+const INDENT: &str = "  ";
+
+pub fn generate(program: &Node, source_name: &str) -> String {
+    let mut gen = Generator { out: String::new(), depth: 1, declared: Declared::new() };
+    let mut out = format!("// Generated by touch-grass transpile from {}\n\n", source_name);
+
+    out.push_str("// The touch-grass runtime, the same in every program.\n");
+    out.push_str("const $tg = (() => {\n  const ARITY = {");
+    for (i, builtin) in BUILTINS.iter().enumerate() {
+        let separator = if i == 0 { " " } else { ", " };
+        out.push_str(&format!("{}{}: [{}, {}]", separator, builtin.name, builtin.min_args, builtin.max_args));
+    }
+    out.push_str(" };\n\n");
+    out.push_str(PRELUDE);
+    out.push_str("// End of the runtime.\n\n");
+
+    out.push_str(&format!("let {} = $tg.args;\n", name("args")));
+    let names: Vec<String> = variables(program).iter().filter(|n| *n != "args").map(|n| name(n)).collect();
+    if !names.is_empty() {
+        // Undefined until declared; reading an undeclared variable gives null.
+        out.push_str(&format!("let {};\n", names.join(", ")));
+    }
+    out.push_str("\n$tg.run(() => {\n");
+    gen.statement(program);
+    out.push_str(&gen.out);
+    out.push_str("});\n");
+    out
+}
+
+/// JavaScript names for variables, as in the C backend: plain ASCII names
+/// get a `v_` prefix, which keeps them clear of keywords, globals like
+/// `console` and the runtime; others are hex-encoded under `u_`.
+fn name(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        format!("v_{}", name)
+    } else {
+        let mut out = String::from("u");
+        for c in name.chars() {
+            out.push_str(&format!("_{:x}", c as u32));
+        }
+        out
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Generator {
+    out: String,
+    depth: usize,
+    declared: Declared,
+}
+
+impl Generator {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn block(&mut self, block: &Node) {
+        self.depth += 1;
+        self.declared.enter_block();
+        self.statement(block);
+        self.declared.leave_block();
+        self.depth -= 1;
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) | Node::Block(statements) => {
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+            }
+            Node::Statement { statement, .. } => self.statement(statement),
+            Node::VarDeclaration { name: var, value, .. } => {
+                let value = self.expression(value);
+                self.line(&format!("{} = {};", name(var), value));
+                self.declared.insert(var);
+            }
+            Node::Assignment { name: var, value } => {
+                let value = self.expression(value);
+                let var_name = name(var);
+                if self.declared.contains(var) {
+                    self.line(&format!("{} = {};", var_name, value));
+                } else {
+                    self.line(&format!("{} = $tg.assign({}, {}, {});", var_name, var_name, string(var), value));
+                }
+            }
+            Node::Print(expr) => {
+                let value = self.expression(expr);
+                self.line(&format!("$tg.print({});", value));
+            }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                let condition = self.expression(condition);
+                self.line(&format!("if ($tg.truthy({})) {{", condition));
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.line("} else {");
+                    self.block(else_branch);
+                }
+                self.line("}");
+            }
+            Node::WhileLoop { condition, body } => {
+                let condition = self.expression(condition);
+                self.line(&format!("while ($tg.truthy({})) {{", condition));
+                self.block(body);
+                self.line("}");
+            }
+            Node::Comment { text, trailing } => {
+                // A `#!` line only means something at the top of a .tg file.
+                if !text.starts_with("//") {
+                    return;
+                }
+                if *trailing && self.out.ends_with('\n') {
+                    self.out.pop();
+                    self.out.push(' ');
+                    self.out.push_str(text);
+                    self.out.push('\n');
+                } else {
+                    self.line(text);
+                }
+            }
+            expr => {
+                let value = self.expression(expr);
+                self.line(&format!("{};", value));
+            }
+        }
+    }
+
+    fn expression(&mut self, node: &Node) -> String {
+        match node {
+            Node::Identifier(var) if self.declared.contains(var) => name(var),
+            Node::Identifier(var) => format!("({} ?? null)", name(var)),
+            Node::NumberLiteral(n) => format!("{}n", n),
+            Node::StringLiteral(s) => string(s),
+            Node::Boolean(b) => b.to_string(),
+            Node::Bugatti => string("Bugatti!"),
+            Node::BinaryOp { left, operator, right } => {
+                let function = match operator {
+                    Operator::Plus => "add",
+                    Operator::Minus => "sub",
+                    Operator::GreaterThan => "gt",
+                    Operator::LessThan => "lt",
+                    Operator::Equals => "eq",
+                };
+                format!("$tg.{}({}, {})", function, self.expression(left), self.expression(right))
+            }
+            Node::Call { name: function, args } => {
+                let mut call = format!("$tg.call({}", string(function));
+                for arg in args {
+                    call.push_str(", ");
+                    call.push_str(&self.expression(arg));
+                }
+                call.push(')');
+                call
+            }
+            _ => "null".to_string(),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeSet;
+use crate::ast::Node;
+
+mod c;
+mod js;
+mod wat;
+mod wat_validate;
+
+pub use wat_validate::validate as validate_wat;
+
+/// A language `touch-grass transpile` can write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    JavaScript,
+    C,
+    /// WebAssembly text format, for a host that provides the imports.
+    Wat,
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Target> {
+        match name {
+            "js" | "javascript" => Some(Target::JavaScript),
+            "c" => Some(Target::C),
+            "wat" | "wasm" => Some(Target::Wat),
+            _ => None,
+        }
+    }
+}
+
+/// Turns a parsed program into a standalone program in another language
+/// that prints the same output and fails with the same runtime errors.
+pub fn transpile(program: &Node, target: Target, source_name: &str) -> String {
+    match target {
+        Target::JavaScript => js::generate(program, source_name),
+        Target::C => c::generate(program, source_name),
+        Target::Wat => wat::generate(program, source_name),
+    }
+}
+
+/// Every variable a program mentions, in order of first appearance. Touch
+/// Grass has a single flat scope, so backends declare them all up front.
+fn variables(node: &Node) -> Vec<String> {
+    fn add(name: &str, seen: &mut BTreeSet<String>, out: &mut Vec<String>) {
+        if seen.insert(name.to_string()) {
+            out.push(name.to_string());
+        }
+    }
+    fn visit(node: &Node, seen: &mut BTreeSet<String>, out: &mut Vec<String>) {
+        match node {
+            Node::Program(nodes) | Node::Block(nodes) => {
+                for node in nodes {
+                    visit(node, seen, out);
+                }
+            }
+            Node::Statement { statement, .. } => visit(statement, seen, out),
+            Node::VarDeclaration { name, value, .. } | Node::Assignment { name, value } => {
+                visit(value, seen, out);
+                add(name, seen, out);
+            }
+            Node::Identifier(name) => add(name, seen, out),
+            Node::Print(expr) => visit(expr, seen, out),
+            Node::Call { args, .. } => {
+                for arg in args {
+                    visit(arg, seen, out);
+                }
+            }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                visit(condition, seen, out);
+                visit(then_branch, seen, out);
+                if let Some(else_branch) = else_branch {
+                    visit(else_branch, seen, out);
+                }
+            }
+            Node::WhileLoop { condition, body } => {
+                visit(condition, seen, out);
+                visit(body, seen, out);
+            }
+            Node::BinaryOp { left, right, .. } => {
+                visit(left, seen, out);
+                visit(right, seen, out);
+            }
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    visit(node, &mut BTreeSet::new(), &mut out);
+    out
+}
+
+/// Tracks which variables are certainly declared at the current point, so
+/// generated code only checks for undeclared variables where it has to.
+/// A declaration counts for the rest of the block it's in; once the block
+/// ends it may not have run.
+struct Declared {
+    names: BTreeSet<String>,
+    saved: Vec<BTreeSet<String>>,
+}
+
+impl Declared {
+    fn new() -> Self {
+        let mut names = BTreeSet::new();
+        names.insert("args".to_string());
+        Declared { names, saved: Vec::new() }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    fn insert(&mut self, name: &str) {
+        self.names.insert(name.to_string());
+    }
+
+    fn enter_block(&mut self) {
+        self.saved.push(self.names.clone());
+    }
+
+    fn leave_block(&mut self) {
+        if let Some(names) = self.saved.pop() {
+            self.names = names;
+        }
+    }
+}
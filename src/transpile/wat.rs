@@ -0,0 +1,1314 @@
+use std::collections::HashMap;
+use crate::ast::{Node, Operator};
+use crate::builtins;
+use super::{variables, Declared};
+
+/// Runtime support for generated modules. A value is a pair on the operand
+/// stack: an i32 tag and an i64 payload. Strings are `offset << 32 | length`
+/// in memory, lists point at a length followed by 16-byte (tag, payload)
+/// items. Nothing is freed; `$alloc` bumps `$heap` and grows memory.
+///
+/// `@<text>` stands for the offset and length of `text` in the data segment.
+const RUNTIME: &str = r#"  ;; Tags: 0 undeclared, 1 null, 2 number, 3 string, 4 boolean, 5 list.
+
+  (func $fail (param $ptr i32) (param $len i32)
+    local.get $ptr
+    local.get $len
+    call $error
+    unreachable)
+
+  (func $alloc (param $size i32) (result i32)
+    (local $ptr i32)
+    global.get $heap
+    local.set $ptr
+    local.get $ptr
+    local.get $size
+    i32.add
+    i32.const 7
+    i32.add
+    i32.const -8
+    i32.and
+    global.set $heap
+    block $done
+      loop $grow
+        global.get $heap
+        memory.size
+        i32.const 16
+        i32.shl
+        i32.le_u
+        br_if $done
+        i32.const 1
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          @<Out of memory>
+          call $fail
+        end
+        br $grow
+      end
+    end
+    local.get $ptr)
+
+  ;; Writes the digits of n so they end at offset 32; returns where they start.
+  (func $format_number (param $n i64) (result i32)
+    (local $pos i32) (local $u i64) (local $negative i32)
+    i32.const 32
+    local.set $pos
+    local.get $n
+    i64.const 0
+    i64.lt_s
+    local.set $negative
+    local.get $n
+    local.set $u
+    local.get $negative
+    if
+      i64.const 0
+      local.get $n
+      i64.sub
+      local.set $u
+    end
+    loop $digits
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.set $pos
+      local.get $pos
+      local.get $u
+      i64.const 10
+      i64.rem_u
+      i32.wrap_i64
+      i32.const 48
+      i32.add
+      i32.store8
+      local.get $u
+      i64.const 10
+      i64.div_u
+      local.tee $u
+      i64.const 0
+      i64.ne
+      br_if $digits
+    end
+    local.get $negative
+    if
+      local.get $pos
+      i32.const 1
+      i32.sub
+      local.set $pos
+      local.get $pos
+      i32.const 45
+      i32.store8
+    end
+    local.get $pos)
+
+  ;; Runtime error messages are built at offset 32, after the digit scratch.
+  (func $message_append (param $ptr i32) (param $len i32)
+    (local $i i32)
+    block $done
+      loop $copy
+        local.get $i
+        local.get $len
+        i32.ge_u
+        br_if $done
+        global.get $message_len
+        i32.const 992
+        i32.ge_u
+        br_if $done
+        global.get $message_len
+        i32.const 32
+        i32.add
+        local.get $ptr
+        local.get $i
+        i32.add
+        i32.load8_u
+        i32.store8
+        global.get $message_len
+        i32.const 1
+        i32.add
+        global.set $message_len
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $copy
+      end
+    end)
+
+  (func $message_number (param $n i64)
+    (local $ptr i32)
+    local.get $n
+    call $format_number
+    local.set $ptr
+    local.get $ptr
+    i32.const 32
+    local.get $ptr
+    i32.sub
+    call $message_append)
+
+  (func $message_type (param $tag i32)
+    local.get $tag
+    i32.const 2
+    i32.eq
+    if
+      @<number>
+      call $message_append
+      return
+    end
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      @<string>
+      call $message_append
+      return
+    end
+    local.get $tag
+    i32.const 4
+    i32.eq
+    if
+      @<boolean>
+      call $message_append
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      @<list>
+      call $message_append
+      return
+    end
+    @<null>
+    call $message_append)
+
+  (func $message_fail
+    i32.const 32
+    global.get $message_len
+    call $fail)
+
+  (func $write_number (param $n i64)
+    (local $ptr i32)
+    local.get $n
+    call $format_number
+    local.set $ptr
+    local.get $ptr
+    i32.const 32
+    local.get $ptr
+    i32.sub
+    call $write)
+
+  (func $write_string (param $v i64)
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.get $v
+    i32.wrap_i64
+    call $write)
+
+  ;; Same text as Value's Display: strings inside lists are quoted.
+  (func $display (param $tag i32) (param $v i64) (param $quote i32)
+    (local $list i32) (local $i i32) (local $item i32)
+    local.get $tag
+    i32.const 2
+    i32.eq
+    if
+      local.get $v
+      call $write_number
+      return
+    end
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      local.get $quote
+      if
+        @<">
+        call $write
+      end
+      local.get $v
+      call $write_string
+      local.get $quote
+      if
+        @<">
+        call $write
+      end
+      return
+    end
+    local.get $tag
+    i32.const 4
+    i32.eq
+    if
+      local.get $v
+      i64.eqz
+      if
+        @<false>
+        call $write
+      else
+        @<true>
+        call $write
+      end
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      local.get $v
+      i32.wrap_i64
+      local.set $list
+      @<[>
+      call $write
+      block $done
+        loop $items
+          local.get $i
+          local.get $list
+          i32.load
+          i32.ge_u
+          br_if $done
+          local.get $i
+          if
+            @<, >
+            call $write
+          end
+          local.get $list
+          i32.const 8
+          i32.add
+          local.get $i
+          i32.const 16
+          i32.mul
+          i32.add
+          local.set $item
+          local.get $item
+          i32.load
+          local.get $item
+          i64.load offset=8
+          i32.const 1
+          call $display
+          local.get $i
+          i32.const 1
+          i32.add
+          local.set $i
+          br $items
+        end
+      end
+      @<]>
+      call $write
+      return
+    end
+    @<null>
+    call $write)
+
+  (func $print (param $tag i32) (param $v i64)
+    local.get $tag
+    local.get $v
+    i32.const 0
+    call $display
+    @<
+>
+    call $write)
+
+  (func $truthy (param $tag i32) (param $v i64) (result i32)
+    local.get $tag
+    i32.const 2
+    i32.eq
+    local.get $tag
+    i32.const 4
+    i32.eq
+    i32.or
+    if
+      local.get $v
+      i64.const 0
+      i64.ne
+      return
+    end
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      local.get $v
+      i32.wrap_i64
+      i32.const 0
+      i32.ne
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      local.get $v
+      i32.wrap_i64
+      i32.load
+      i32.const 0
+      i32.ne
+      return
+    end
+    i32.const 0)
+
+  ;; Reading a variable that was never declared gives null.
+  (func $read (param $tag i32) (param $v i64) (result i32 i64)
+    local.get $tag
+    i32.eqz
+    if
+      i32.const 1
+      i64.const 0
+      return
+    end
+    local.get $tag
+    local.get $v)
+
+  ;; `set` on a variable that was never declared is an error.
+  (func $check_declared (param $tag i32) (param $ptr i32) (param $len i32)
+    local.get $tag
+    i32.eqz
+    if
+      local.get $ptr
+      local.get $len
+      call $fail
+    end)
+
+  ;; Operators only work on numbers; anything else gives null. Arithmetic wraps.
+  (func $numbers (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    i32.const 2
+    i32.eq
+    local.get $b
+    i32.const 2
+    i32.eq
+    i32.and)
+
+  (func $add (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 2
+      local.get $a
+      local.get $b
+      i64.add
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $sub (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 2
+      local.get $a
+      local.get $b
+      i64.sub
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $gt (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 4
+      local.get $a
+      local.get $b
+      i64.gt_s
+      i64.extend_i32_u
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $lt (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 4
+      local.get $a
+      local.get $b
+      i64.lt_s
+      i64.extend_i32_u
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $eq (param $at i32) (param $a i64) (param $bt i32) (param $b i64) (result i32 i64)
+    local.get $at
+    local.get $bt
+    call $numbers
+    if
+      i32.const 4
+      local.get $a
+      local.get $b
+      i64.eq
+      i64.extend_i32_u
+      return
+    end
+    i32.const 1
+    i64.const 0)
+
+  (func $args (result i32 i64)
+    (local $count i32) (local $list i32) (local $i i32) (local $len i32) (local $ptr i32) (local $item i32)
+    call $arg_count
+    local.set $count
+    local.get $count
+    i32.const 16
+    i32.mul
+    i32.const 8
+    i32.add
+    call $alloc
+    local.set $list
+    local.get $list
+    local.get $count
+    i32.store
+    block $done
+      loop $each
+        local.get $i
+        local.get $count
+        i32.ge_u
+        br_if $done
+        local.get $i
+        call $arg_length
+        local.set $len
+        local.get $len
+        call $alloc
+        local.set $ptr
+        local.get $i
+        local.get $ptr
+        call $arg_read
+        local.get $list
+        i32.const 8
+        i32.add
+        local.get $i
+        i32.const 16
+        i32.mul
+        i32.add
+        local.set $item
+        local.get $item
+        i32.const 3
+        i32.store
+        local.get $item
+        local.get $ptr
+        i64.extend_i32_u
+        i64.const 32
+        i64.shl
+        local.get $len
+        i64.extend_i32_u
+        i64.or
+        i64.store offset=8
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $each
+      end
+    end
+    i32.const 5
+    local.get $list
+    i64.extend_i32_u)
+
+  (func $utf8_length (param $v i64) (result i64)
+    (local $ptr i32) (local $end i32) (local $count i64)
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.set $ptr
+    local.get $ptr
+    local.get $v
+    i32.wrap_i64
+    i32.add
+    local.set $end
+    block $done
+      loop $bytes
+        local.get $ptr
+        local.get $end
+        i32.ge_u
+        br_if $done
+        local.get $ptr
+        i32.load8_u
+        i32.const 192
+        i32.and
+        i32.const 128
+        i32.ne
+        if
+          local.get $count
+          i64.const 1
+          i64.add
+          local.set $count
+        end
+        local.get $ptr
+        i32.const 1
+        i32.add
+        local.set $ptr
+        br $bytes
+      end
+    end
+    local.get $count)
+
+  (func $invalid_arguments (param $ptr i32) (param $len i32) (param $tag i32)
+    i32.const 0
+    global.set $message_len
+    @<Invalid arguments to >
+    call $message_append
+    local.get $ptr
+    local.get $len
+    call $message_append
+    @<: >
+    call $message_append
+    local.get $tag
+    call $message_type)
+
+  (func $builtin_env (param $tag i32) (param $v i64) (result i32 i64)
+    (local $key i32) (local $key_len i32) (local $len i32) (local $ptr i32)
+    local.get $tag
+    i32.const 3
+    i32.ne
+    if
+      @<env>
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.set $key
+    local.get $v
+    i32.wrap_i64
+    local.set $key_len
+    local.get $key
+    local.get $key_len
+    call $env_length
+    local.set $len
+    local.get $len
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      i64.const 0
+      return
+    end
+    local.get $len
+    call $alloc
+    local.set $ptr
+    local.get $key
+    local.get $key_len
+    local.get $ptr
+    call $env_read
+    i32.const 3
+    local.get $ptr
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $len
+    i64.extend_i32_u
+    i64.or)
+
+  (func $builtin_exit_code (param $tag i32) (param $v i64) (result i32 i64)
+    local.get $tag
+    i32.const 2
+    i32.ne
+    if
+      @<exit_code>
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    ;; Codes that don't survive the round trip through i32 are out of range.
+    local.get $v
+    local.get $v
+    i32.wrap_i64
+    i64.extend_i32_s
+    i64.ne
+    if
+      i32.const 0
+      global.set $message_len
+      @<Exit code >
+      call $message_append
+      local.get $v
+      call $message_number
+      @< is out of range>
+      call $message_append
+      call $message_fail
+    end
+    local.get $v
+    i32.wrap_i64
+    global.set $exit_code
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_len (param $tag i32) (param $v i64) (result i32 i64)
+    local.get $tag
+    i32.const 3
+    i32.eq
+    if
+      i32.const 2
+      local.get $v
+      call $utf8_length
+      return
+    end
+    local.get $tag
+    i32.const 5
+    i32.eq
+    if
+      i32.const 2
+      local.get $v
+      i32.wrap_i64
+      i64.load32_u
+      return
+    end
+    @<len>
+    local.get $tag
+    call $invalid_arguments
+    call $message_fail
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_at (param $list_tag i32) (param $list i64) (param $index_tag i32) (param $index i64) (result i32 i64)
+    (local $len i64) (local $item i32)
+    local.get $list_tag
+    i32.const 5
+    i32.ne
+    local.get $index_tag
+    i32.const 2
+    i32.ne
+    i32.or
+    if
+      @<at>
+      local.get $list_tag
+      call $invalid_arguments
+      @<, >
+      call $message_append
+      local.get $index_tag
+      call $message_type
+      call $message_fail
+    end
+    local.get $list
+    i32.wrap_i64
+    i64.load32_u
+    local.set $len
+    ;; Unsigned, so negative indexes are out of range too.
+    local.get $index
+    local.get $len
+    i64.ge_u
+    if
+      i32.const 0
+      global.set $message_len
+      @<Index >
+      call $message_append
+      local.get $index
+      call $message_number
+      @< is out of range for a list of >
+      call $message_append
+      local.get $len
+      call $message_number
+      @< items>
+      call $message_append
+      call $message_fail
+    end
+    local.get $list
+    i32.wrap_i64
+    i32.const 8
+    i32.add
+    local.get $index
+    i32.wrap_i64
+    i32.const 16
+    i32.mul
+    i32.add
+    local.set $item
+    local.get $item
+    i32.load
+    local.get $item
+    i64.load offset=8)
+
+  ;; Copies the text the host just read into a new string.
+  (func $input_string (param $len i32) (result i32 i64)
+    (local $ptr i32)
+    local.get $len
+    call $alloc
+    local.set $ptr
+    local.get $ptr
+    call $input_read
+    i32.const 3
+    local.get $ptr
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $len
+    i64.extend_i32_u
+    i64.or)
+
+  ;; The undeclared tag means no prompt was passed.
+  (func $builtin_read_line (param $tag i32) (param $v i64) (result i32 i64)
+    (local $len i32)
+    local.get $tag
+    if
+      local.get $tag
+      i32.const 3
+      i32.ne
+      if
+        @<read_line>
+        local.get $tag
+        call $invalid_arguments
+        call $message_fail
+      end
+      local.get $v
+      i64.const 32
+      i64.shr_u
+      i32.wrap_i64
+      local.get $v
+      i32.wrap_i64
+      call $write
+    end
+    call $read_line
+    local.set $len
+    local.get $len
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      i64.const 0
+      return
+    end
+    local.get $len
+    call $input_string)
+
+  (func $builtin_read_all (result i32 i64)
+    call $read_all
+    call $input_string)
+
+  ;; Accumulates the digits as a negative number, which has room for i64 min.
+  (func $builtin_parse_number (param $tag i32) (param $v i64) (result i32 i64)
+    (local $ptr i32) (local $end i32) (local $negative i32) (local $byte i32) (local $digit i64) (local $n i64)
+    local.get $tag
+    i32.const 3
+    i32.ne
+    if
+      @<parse_number>
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.tee $ptr
+    local.get $v
+    i32.wrap_i64
+    i32.add
+    local.set $end
+    block $invalid
+      local.get $ptr
+      local.get $end
+      i32.lt_u
+      if
+        local.get $ptr
+        i32.load8_u
+        i32.const 45
+        i32.eq
+        local.set $negative
+      end
+      local.get $ptr
+      local.get $negative
+      i32.add
+      local.tee $ptr
+      local.get $end
+      i32.eq
+      br_if $invalid
+      loop $digits
+        local.get $ptr
+        i32.load8_u
+        i32.const 48
+        i32.sub
+        local.tee $byte
+        i32.const 9
+        i32.gt_u
+        br_if $invalid
+        local.get $byte
+        i64.extend_i32_u
+        local.set $digit
+        ;; n * 10 - digit would go below i64 min.
+        local.get $n
+        i64.const -9223372036854775808
+        local.get $digit
+        i64.add
+        i64.const 10
+        i64.div_s
+        i64.lt_s
+        br_if $invalid
+        local.get $n
+        i64.const 10
+        i64.mul
+        local.get $digit
+        i64.sub
+        local.set $n
+        local.get $ptr
+        i32.const 1
+        i32.add
+        local.tee $ptr
+        local.get $end
+        i32.lt_u
+        br_if $digits
+      end
+      local.get $negative
+      if
+        i32.const 2
+        local.get $n
+        return
+      end
+      local.get $n
+      i64.const -9223372036854775808
+      i64.eq
+      br_if $invalid
+      i32.const 2
+      i64.const 0
+      local.get $n
+      i64.sub
+      return
+    end
+    i32.const 0
+    global.set $message_len
+    @<Cannot parse ">
+    call $message_append
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.get $v
+    i32.wrap_i64
+    call $message_append
+    @<" as a number>
+    call $message_append
+    call $message_fail
+    i32.const 1
+    i64.const 0)
+
+  ;; Generated modules get no filesystem access, like `run` without
+  ;; --allow-read or --allow-write.
+  (func $deny (param $name i32) (param $name_len i32) (param $access i32) (param $access_len i32) (param $tag i32) (param $v i64)
+    local.get $tag
+    i32.const 3
+    i32.ne
+    if
+      local.get $name
+      local.get $name_len
+      local.get $tag
+      call $invalid_arguments
+      call $message_fail
+    end
+    i32.const 0
+    global.set $message_len
+    @<Not allowed to >
+    call $message_append
+    local.get $access
+    local.get $access_len
+    call $message_append
+    @< ">
+    call $message_append
+    local.get $v
+    i64.const 32
+    i64.shr_u
+    i32.wrap_i64
+    local.get $v
+    i32.wrap_i64
+    call $message_append
+    @<">
+    call $message_append
+    call $message_fail)
+
+  (func $builtin_read_file (param $tag i32) (param $v i64) (result i32 i64)
+    @<read_file>
+    @<read>
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_write_file (param $path_tag i32) (param $path i64) (param $text_tag i32) (param $text i64) (result i32 i64)
+    local.get $path_tag
+    i32.const 3
+    i32.ne
+    local.get $text_tag
+    i32.const 3
+    i32.ne
+    i32.or
+    if
+      @<write_file>
+      local.get $path_tag
+      call $invalid_arguments
+      @<, >
+      call $message_append
+      local.get $text_tag
+      call $message_type
+      call $message_fail
+    end
+    @<write_file>
+    @<write>
+    local.get $path_tag
+    local.get $path
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_list_dir (param $tag i32) (param $v i64) (result i32 i64)
+    @<list_dir>
+    @<read>
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_file_exists (param $tag i32) (param $v i64) (result i32 i64)
+    @<file_exists>
+    @<read>
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+
+  (func $builtin_delete_file (param $tag i32) (param $v i64) (result i32 i64)
+    @<delete_file>
+    @<write>
+    local.get $tag
+    local.get $v
+    call $deny
+    i32.const 1
+    i64.const 0)
+"#;
+
+/// Where the runtime's strings start. Below them: digit scratch space (0-31)
+/// and the runtime error message buffer (32-1023).
+const DATA_START: usize = 1024;
+/// Where the program's strings start, after room for the runtime's. It's
+/// fixed so that changing the runtime doesn't move them.
+const PROGRAM_DATA_START: usize = 4096;
+const PAGE_SIZE: usize = 65536;
+const INDENT: &str = "  ";
+
+pub fn generate(program: &Node, source_name: &str) -> String {
+    let mut runtime_data = Data::new(DATA_START);
+    let runtime = runtime_data.expand(RUNTIME);
+    assert!(DATA_START + runtime_data.bytes.len() <= PROGRAM_DATA_START, "the runtime's strings don't fit");
+
+    let data = Data::new(PROGRAM_DATA_START);
+    let mut gen = Generator { out: String::new(), depth: 2, declared: Declared::new(), data, labels: 0 };
+    gen.statement(program);
+    let Generator { out: body, data, .. } = gen;
+
+    let end = PROGRAM_DATA_START + data.bytes.len();
+    let heap = (end + 7) & !7;
+    let pages = end / PAGE_SIZE + 1;
+
+    let mut out = format!(";; Generated by touch-grass transpile from {}\n", source_name.replace('\n', " "));
+    out.push_str(";; The touch-grass runtime, the same in every program.\n");
+    out.push_str(";; The host provides the imports below and calls `main`, which returns\n");
+    out.push_str(";; the exit code. On a runtime error it calls `error`, then traps.\n");
+    out.push_str("(module\n");
+    out.push_str("  (import \"touch_grass\" \"write\" (func $write (param i32 i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"error\" (func $error (param i32 i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"arg_count\" (func $arg_count (result i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"arg_length\" (func $arg_length (param i32) (result i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"arg_read\" (func $arg_read (param i32 i32)))\n");
+    out.push_str("  ;; env_length returns -1 when the variable isn't set.\n");
+    out.push_str("  (import \"touch_grass\" \"env_length\" (func $env_length (param i32 i32) (result i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"env_read\" (func $env_read (param i32 i32 i32)))\n");
+    out.push_str("  ;; read_line returns the length of the next line, or -1 at the end of input;\n");
+    out.push_str("  ;; read_all the length of the rest. input_read copies what was just read.\n");
+    out.push_str("  (import \"touch_grass\" \"read_line\" (func $read_line (result i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"read_all\" (func $read_all (result i32)))\n");
+    out.push_str("  (import \"touch_grass\" \"input_read\" (func $input_read (param i32)))\n\n");
+    out.push_str("  (global $exit_code (mut i32) (i32.const 0))\n");
+    out.push_str("  (global $message_len (mut i32) (i32.const 0))\n");
+    out.push_str(&format!("  (data (i32.const {}) \"{}\")\n\n", DATA_START, escape(&runtime_data.bytes)));
+    out.push_str(&runtime);
+    out.push_str("  ;; End of the runtime.\n\n");
+
+    out.push_str(&format!("  (memory (export \"memory\") {})\n", pages));
+    out.push_str(&format!("  (global $heap (mut i32) (i32.const {}))\n", heap));
+    out.push_str(&format!("  (data (i32.const {}) \"{}\")\n", PROGRAM_DATA_START, escape(&data.bytes)));
+    out.push_str("\n  (func $main (export \"main\") (result i32)\n");
+    let mut locals = vec![local("args")];
+    locals.extend(variables(program).iter().filter(|n| *n != "args").map(|n| local(n)));
+    for local in locals {
+        // Locals start at zero: the undeclared tag.
+        out.push_str(&format!("    (local {}.t i32) (local {}.v i64)\n", local, local));
+    }
+    let args = local("args");
+    out.push_str(&format!("    call $args\n    local.set {}.v\n    local.set {}.t\n", args, args));
+    out.push_str(&body);
+    out.push_str("    global.get $exit_code)\n)\n");
+    out
+}
+
+/// WAT names for variables: `$v.` and the name when it's plain ASCII, or
+/// `$u.` and the hex code points otherwise.
+fn local(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        format!("$v.{}", name)
+    } else {
+        let codes: Vec<String> = name.chars().map(|c| format!("{:x}", c as u32)).collect();
+        format!("$u.{}", codes.join("_"))
+    }
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in bytes {
+        if b.is_ascii_graphic() && b != b'"' && b != b'\\' || b == b' ' {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("\\{:02x}", b));
+        }
+    }
+    out
+}
+
+/// A data segment. Equal strings share their bytes.
+struct Data {
+    start: usize,
+    bytes: Vec<u8>,
+    strings: HashMap<String, usize>,
+}
+
+impl Data {
+    fn new(start: usize) -> Self {
+        Data { start, bytes: Vec::new(), strings: HashMap::new() }
+    }
+
+    fn add(&mut self, s: &str) -> (usize, usize) {
+        let offset = match self.strings.get(s) {
+            Some(&offset) => offset,
+            None => {
+                let offset = self.start + self.bytes.len();
+                self.bytes.extend_from_slice(s.as_bytes());
+                self.strings.insert(s.to_string(), offset);
+                offset
+            }
+        };
+        (offset, s.len())
+    }
+
+    /// Replaces each `@<text>` in the runtime with instructions pushing the
+    /// offset and length of `text`.
+    fn expand(&mut self, template: &str) -> String {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("@<") {
+            out.push_str(&rest[..start]);
+            let indent = out.len() - out.rfind('\n').map_or(0, |i| i + 1);
+            let end = start + rest[start..].find('>').expect("unterminated @< in runtime");
+            let (offset, len) = self.add(&rest[start + 2..end]);
+            out.push_str(&format!("i32.const {}\n{}i32.const {}", offset, " ".repeat(indent), len));
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+struct Generator {
+    out: String,
+    depth: usize,
+    declared: Declared,
+    data: Data,
+    labels: usize,
+}
+
+impl Generator {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn string(&mut self, s: &str) {
+        let (offset, len) = self.data.add(s);
+        self.line(&format!("i32.const 3\n{}i64.const {}", INDENT.repeat(self.depth), ((offset as i64) << 32) | len as i64));
+    }
+
+    fn message(&mut self, s: &str) {
+        let (offset, len) = self.data.add(s);
+        self.line(&format!("i32.const {}", offset));
+        self.line(&format!("i32.const {}", len));
+    }
+
+    fn block(&mut self, block: &Node) {
+        self.depth += 1;
+        self.declared.enter_block();
+        self.statement(block);
+        self.declared.leave_block();
+        self.depth -= 1;
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(statements) | Node::Block(statements) => {
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+            }
+            Node::Statement { statement, .. } => self.statement(statement),
+            Node::VarDeclaration { name, value, .. } => {
+                self.expression(value);
+                let var = local(name);
+                self.line(&format!("local.set {}.v", var));
+                self.line(&format!("local.set {}.t", var));
+                self.declared.insert(name);
+            }
+            Node::Assignment { name, value } => {
+                self.expression(value);
+                let var = local(name);
+                if !self.declared.contains(name) {
+                    self.line(&format!("local.get {}.t", var));
+                    self.message(&format!("Undefined variable: {}", name));
+                    self.line("call $check_declared");
+                }
+                self.line(&format!("local.set {}.v", var));
+                self.line(&format!("local.set {}.t", var));
+            }
+            Node::Print(expr) => {
+                self.expression(expr);
+                self.line("call $print");
+            }
+            Node::GoOutside { condition, then_branch, else_branch } => {
+                self.expression(condition);
+                self.line("call $truthy");
+                self.line("if");
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.line("else");
+                    self.block(else_branch);
+                }
+                self.line("end");
+            }
+            Node::WhileLoop { condition, body } => {
+                self.labels += 1;
+                let label = self.labels;
+                self.line(&format!("block $break{}", label));
+                self.depth += 1;
+                self.line(&format!("loop $continue{}", label));
+                self.depth += 1;
+                self.expression(condition);
+                self.line("call $truthy");
+                self.line("i32.eqz");
+                self.line(&format!("br_if $break{}", label));
+                self.depth -= 1;
+                self.block(body);
+                self.depth += 1;
+                self.line(&format!("br $continue{}", label));
+                self.depth -= 1;
+                self.line("end");
+                self.depth -= 1;
+                self.line("end");
+            }
+            Node::Comment { text, trailing } => {
+                // A `#!` line only means something at the top of a .tg file.
+                let Some(text) = text.strip_prefix("//") else { return };
+                let comment = format!(";;{}", text);
+                if *trailing && self.out.ends_with('\n') {
+                    self.out.pop();
+                    self.out.push(' ');
+                    self.out.push_str(&comment);
+                    self.out.push('\n');
+                } else {
+                    self.line(&comment);
+                }
+            }
+            expr => {
+                self.expression(expr);
+                self.line("drop");
+                self.line("drop");
+            }
+        }
+    }
+
+    /// Pushes the tag and payload of an expression's value.
+    fn expression(&mut self, node: &Node) {
+        match node {
+            Node::Identifier(name) => {
+                let var = local(name);
+                self.line(&format!("local.get {}.t", var));
+                self.line(&format!("local.get {}.v", var));
+                if !self.declared.contains(name) {
+                    self.line("call $read");
+                }
+            }
+            Node::NumberLiteral(n) => {
+                self.line("i32.const 2");
+                self.line(&format!("i64.const {}", n));
+            }
+            Node::StringLiteral(s) => self.string(s),
+            Node::Boolean(b) => {
+                self.line("i32.const 4");
+                self.line(&format!("i64.const {}", *b as i64));
+            }
+            Node::Bugatti => self.string("Bugatti!"),
+            Node::BinaryOp { left, operator, right } => {
+                self.expression(left);
+                self.expression(right);
+                let function = match operator {
+                    Operator::Plus => "add",
+                    Operator::Minus => "sub",
+                    Operator::GreaterThan => "gt",
+                    Operator::LessThan => "lt",
+                    Operator::Equals => "eq",
+                };
+                self.line(&format!("call ${}", function));
+            }
+            Node::Call { name, args } => {
+                for arg in args {
+                    self.expression(arg);
+                }
+                // Arity is known here, so those errors are plain messages.
+                let error = match builtins::lookup(name) {
+                    None => Some(format!("Unknown function: {}", name)),
+                    Some(builtin) if args.len() < builtin.min_args || args.len() > builtin.max_args => {
+                        let expected = if builtin.min_args == builtin.max_args {
+                            builtin.min_args.to_string()
+                        } else {
+                            format!("{} to {}", builtin.min_args, builtin.max_args)
+                        };
+                        Some(format!("{} expects {} argument(s) but got {}", name, expected, args.len()))
+                    }
+                    Some(_) => None,
+                };
+                match error {
+                    Some(message) => {
+                        for _ in args {
+                            self.line("drop");
+                            self.line("drop");
+                        }
+                        self.message(&message);
+                        self.line("call $fail");
+                        self.line("i32.const 1");
+                        self.line("i64.const 0");
+                    }
+                    None => {
+                        // Optional arguments that weren't passed get the undeclared tag.
+                        let max_args = builtins::lookup(name).map_or(0, |builtin| builtin.max_args);
+                        for _ in args.len()..max_args {
+                            self.line("i32.const 0");
+                            self.line("i64.const 0");
+                        }
+                        self.line(&format!("call $builtin_{}", name));
+                    }
+                }
+            }
+            _ => {
+                self.line("i32.const 1");
+                self.line("i64.const 0");
+            }
+        }
+    }
+}
@@ -0,0 +1,827 @@
+use std::collections::{HashMap, HashSet};
+
+/// Checks a WAT module the way a WebAssembly engine would before running it:
+/// the module's fields are well formed, every name refers to something, data
+/// fits in memory, and each function's instructions type-check against the
+/// operand stack. It understands the flat instruction syntax the `wat` target
+/// writes, not every corner of the text format.
+pub fn validate(text: &str) -> Result<(), String> {
+    let tokens = tokenize(text)?;
+    let mut pos = 0;
+    let module = parse(&tokens, &mut pos)?;
+    if let Some(token) = tokens.get(pos) {
+        return Err(format!("line {}: unexpected text after the module", token.line));
+    }
+    Module::default().check(&module)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValType {
+    I32,
+    I64,
+}
+
+impl ValType {
+    fn from_name(name: &str) -> Option<ValType> {
+        match name {
+            "i32" => Some(ValType::I32),
+            "i64" => Some(ValType::I64),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Atom(String),
+    Str(Vec<u8>),
+}
+
+struct Located {
+    token: Token,
+    line: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Located>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            ';' if chars.get(i + 1) == Some(&';') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '(' if chars.get(i + 1) == Some(&';') => {
+                let start = line;
+                let mut depth = 0;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('('), Some(';')) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        (Some(';'), Some(')')) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        (Some(c), _) => {
+                            if *c == '\n' {
+                                line += 1;
+                            }
+                            i += 1;
+                        }
+                        (None, _) => return Err(format!("line {}: unterminated block comment", start)),
+                    }
+                }
+            }
+            '(' => {
+                tokens.push(Located { token: Token::Open, line });
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Located { token: Token::Close, line });
+                i += 1;
+            }
+            '"' => {
+                let mut bytes = Vec::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(format!("line {}: unterminated string", line)),
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escape = chars.get(i + 1).copied();
+                            i += 2;
+                            match escape {
+                                Some('n') => bytes.push(b'\n'),
+                                Some('t') => bytes.push(b'\t'),
+                                Some('r') => bytes.push(b'\r'),
+                                Some('"') => bytes.push(b'"'),
+                                Some('\'') => bytes.push(b'\''),
+                                Some('\\') => bytes.push(b'\\'),
+                                Some(high) if high.is_ascii_hexdigit() => {
+                                    let low = chars.get(i).copied().filter(|c| c.is_ascii_hexdigit());
+                                    let low = low.ok_or_else(|| format!("line {}: bad escape in string", line))?;
+                                    let hex: String = [high, low].iter().collect();
+                                    bytes.push(u8::from_str_radix(&hex, 16).unwrap_or(0));
+                                    i += 1;
+                                }
+                                _ => return Err(format!("line {}: bad escape in string", line)),
+                            }
+                        }
+                        Some(c) => {
+                            let mut buf = [0; 4];
+                            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Located { token: Token::Str(bytes), line });
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '"' | ';') {
+                    i += 1;
+                }
+                tokens.push(Located { token: Token::Atom(chars[start..i].iter().collect()), line });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    List(Vec<Expr>, usize),
+    Atom(String, usize),
+    Str(Vec<u8>, usize),
+}
+
+impl Expr {
+    fn line(&self) -> usize {
+        match self {
+            Expr::List(_, line) | Expr::Atom(_, line) | Expr::Str(_, line) => *line,
+        }
+    }
+
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Expr::Atom(atom, _) => Some(atom),
+            _ => None,
+        }
+    }
+
+    /// The items of a list whose first item is `keyword`.
+    fn form(&self, keyword: &str) -> Option<&[Expr]> {
+        match self {
+            Expr::List(items, _) if items.first().and_then(Expr::atom) == Some(keyword) => Some(&items[1..]),
+            _ => None,
+        }
+    }
+}
+
+fn parse(tokens: &[Located], pos: &mut usize) -> Result<Expr, String> {
+    let located = tokens.get(*pos).ok_or("unexpected end of input")?;
+    let line = located.line;
+    *pos += 1;
+    match &located.token {
+        Token::Open => {
+            let mut items = Vec::new();
+            loop {
+                match tokens.get(*pos).map(|t| &t.token) {
+                    None => return Err(format!("line {}: unclosed parenthesis", line)),
+                    Some(Token::Close) => {
+                        *pos += 1;
+                        return Ok(Expr::List(items, line));
+                    }
+                    Some(_) => items.push(parse(tokens, pos)?),
+                }
+            }
+        }
+        Token::Close => Err(format!("line {}: unexpected `)`", line)),
+        Token::Atom(atom) => Ok(Expr::Atom(atom.clone(), line)),
+        Token::Str(bytes) => Ok(Expr::Str(bytes.clone(), line)),
+    }
+}
+
+fn is_id(atom: &str) -> bool {
+    atom.len() > 1 && atom.starts_with('$')
+}
+
+fn parse_int(atom: &str, line: usize, ty: ValType) -> Result<i64, String> {
+    let (negative, digits) = match atom.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, atom.strip_prefix('+').unwrap_or(atom)),
+    };
+    let digits = digits.replace('_', "");
+    let magnitude = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    }
+    .map_err(|_| format!("line {}: `{}` is not an integer", line, atom))?;
+    let (min, max) = match ty {
+        ValType::I32 => (i32::MIN as i128, u32::MAX as i128),
+        ValType::I64 => (i64::MIN as i128, u64::MAX as i128),
+    };
+    let value = if negative { -(magnitude as i128) } else { magnitude as i128 };
+    if value < min || value > max {
+        return Err(format!("line {}: `{}` doesn't fit in {}", line, atom, ty.name()));
+    }
+    Ok(value as i64)
+}
+
+#[derive(Debug, Clone, Default)]
+struct Signature {
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+}
+
+struct Global {
+    ty: ValType,
+    mutable: bool,
+}
+
+#[derive(Default)]
+struct Module {
+    functions: Vec<Signature>,
+    function_names: HashMap<String, usize>,
+    globals: Vec<Global>,
+    global_names: HashMap<String, usize>,
+    /// Initial size of the memory in pages, if there is one.
+    memory: Option<u64>,
+    exports: HashSet<String>,
+}
+
+impl Module {
+    fn check(mut self, module: &Expr) -> Result<(), String> {
+        let fields = module.form("module").ok_or_else(|| format!("line {}: expected `(module ...)`", module.line()))?;
+
+        // Functions, the memory and globals can be used before they're
+        // defined, so collect them first.
+        let mut defined = false;
+        for field in fields {
+            let line = field.line();
+            if let Some(import) = field.form("import") {
+                if defined {
+                    return Err(format!("line {}: imports must come before every definition", line));
+                }
+                let desc = match import {
+                    [Expr::Str(..), Expr::Str(..), desc] => desc,
+                    _ => return Err(format!("line {}: expected `(import \"module\" \"name\" (func ...))`", line)),
+                };
+                let items = desc.form("func").ok_or_else(|| format!("line {}: only functions can be imported", line))?;
+                let (signature, rest) = self.signature(items, line)?;
+                if let Some(extra) = rest.first() {
+                    return Err(format!("line {}: unexpected `{}` in an imported function", extra.line(), describe(extra)));
+                }
+                self.add_function(items, signature, line)?;
+            } else if let Some(items) = field.form("func") {
+                defined = true;
+                let (signature, _) = self.signature(items, line)?;
+                self.add_function(items, signature, line)?;
+            } else if let Some(items) = field.form("memory") {
+                defined = true;
+                self.check_memory(items, line)?;
+            } else if let Some(items) = field.form("global") {
+                defined = true;
+                self.check_global(items, line)?;
+            }
+        }
+
+        for field in fields {
+            let line = field.line();
+            let keyword = match field {
+                Expr::List(items, _) => items.first().and_then(Expr::atom).unwrap_or(""),
+                _ => "",
+            };
+            match keyword {
+                "import" | "memory" | "global" => {}
+                "func" => self.check_function(field.form("func").unwrap_or(&[]), line)?,
+                "data" => self.check_data(field.form("data").unwrap_or(&[]), line)?,
+                "export" => self.check_export(field.form("export").unwrap_or(&[]), line)?,
+                _ => return Err(format!("line {}: unsupported module field `{}`", line, describe(field))),
+            }
+        }
+        Ok(())
+    }
+
+    fn add_function(&mut self, items: &[Expr], signature: Signature, line: usize) -> Result<(), String> {
+        if let Some(id) = items.first().and_then(Expr::atom).filter(|a| is_id(a)) {
+            if self.function_names.insert(id.to_string(), self.functions.len()).is_some() {
+                return Err(format!("line {}: duplicate function {}", line, id));
+            }
+        }
+        self.functions.push(signature);
+        Ok(())
+    }
+
+    fn add_export(&mut self, name: &Expr, line: usize) -> Result<(), String> {
+        let Expr::Str(bytes, _) = name else {
+            return Err(format!("line {}: an export name must be a string", line));
+        };
+        let name = String::from_utf8_lossy(bytes).into_owned();
+        if !self.exports.insert(name.clone()) {
+            return Err(format!("line {}: duplicate export \"{}\"", line, name));
+        }
+        Ok(())
+    }
+
+    /// Reads `$id? (export ...)* (param ...)* (result ...)*` from the start of
+    /// a function, returning its signature and what follows.
+    fn signature<'a>(&self, items: &'a [Expr], line: usize) -> Result<(Signature, &'a [Expr]), String> {
+        let mut rest = items;
+        if rest.first().and_then(Expr::atom).is_some_and(is_id) {
+            rest = &rest[1..];
+        }
+        while rest.first().and_then(|e| e.form("export")).is_some() {
+            rest = &rest[1..];
+        }
+        let mut signature = Signature::default();
+        while let Some(params) = rest.first().and_then(|e| e.form("param")) {
+            let types = match params {
+                [Expr::Atom(id, _), ty] if is_id(id) => std::slice::from_ref(ty),
+                _ => params,
+            };
+            for ty in types {
+                signature.params.push(val_type(ty, line)?);
+            }
+            rest = &rest[1..];
+        }
+        while let Some(results) = rest.first().and_then(|e| e.form("result")) {
+            for ty in results {
+                signature.results.push(val_type(ty, line)?);
+            }
+            rest = &rest[1..];
+        }
+        Ok((signature, rest))
+    }
+
+    fn check_function(&mut self, items: &[Expr], line: usize) -> Result<(), String> {
+        for export in items.iter().filter_map(|e| e.form("export")) {
+            match export {
+                [name] => self.add_export(name, line)?,
+                _ => return Err(format!("line {}: expected `(export \"name\")`", line)),
+            }
+        }
+
+        // Parameters and locals share one index space.
+        let mut locals = Vec::new();
+        let mut local_names = HashMap::new();
+        let mut rest = items;
+        if rest.first().and_then(Expr::atom).is_some_and(is_id) {
+            rest = &rest[1..];
+        }
+        while rest.first().and_then(|e| e.form("export")).is_some() {
+            rest = &rest[1..];
+        }
+        let mut results = Vec::new();
+        while let Some(first) = rest.first() {
+            let declaration = first.form("param").or_else(|| first.form("local"));
+            if let Some(declaration) = declaration {
+                match declaration {
+                    [Expr::Atom(id, l), ty] if is_id(id) => {
+                        if local_names.insert(id.clone(), locals.len()).is_some() {
+                            return Err(format!("line {}: duplicate local {}", l, id));
+                        }
+                        locals.push(val_type(ty, *l)?);
+                    }
+                    types => {
+                        for ty in types {
+                            locals.push(val_type(ty, first.line())?);
+                        }
+                    }
+                }
+            } else if let Some(types) = first.form("result") {
+                for ty in types {
+                    results.push(val_type(ty, first.line())?);
+                }
+            } else {
+                break;
+            }
+            rest = &rest[1..];
+        }
+
+        let mut checker = Checker {
+            module: self,
+            locals,
+            local_names,
+            stack: Vec::new(),
+            frames: vec![Frame { kind: FrameKind::Function, label: None, results: results.clone(), height: 0, unreachable: false }],
+        };
+        let mut body = rest.iter().peekable();
+        while let Some(expr) = body.next() {
+            let Expr::Atom(op, line) = expr else {
+                return Err(format!("line {}: expected an instruction, found `{}`", expr.line(), describe(expr)));
+            };
+            checker.instruction(op, *line, &mut body)?;
+            if checker.frames.is_empty() {
+                return Err(format!("line {}: `end` without a block", line));
+            }
+        }
+        if checker.frames.len() > 1 {
+            return Err(format!("line {}: a block in this function is missing its `end`", line));
+        }
+        checker.end(line)
+    }
+
+    fn check_memory(&mut self, items: &[Expr], line: usize) -> Result<(), String> {
+        if self.memory.is_some() {
+            return Err(format!("line {}: a module can only have one memory", line));
+        }
+        let mut rest = items;
+        if rest.first().and_then(Expr::atom).is_some_and(is_id) {
+            rest = &rest[1..];
+        }
+        while let Some(export) = rest.first().and_then(|e| e.form("export")) {
+            match export {
+                [name] => self.add_export(name, line)?,
+                _ => return Err(format!("line {}: expected `(export \"name\")`", line)),
+            }
+            rest = &rest[1..];
+        }
+        let limits: Vec<u64> = rest
+            .iter()
+            .map(|e| e.atom().and_then(|a| a.parse().ok()).ok_or_else(|| format!("line {}: memory limits must be page counts", line)))
+            .collect::<Result<_, _>>()?;
+        match limits.as_slice() {
+            [min] if *min <= 65536 => self.memory = Some(*min),
+            [min, max] if min <= max && *max <= 65536 => self.memory = Some(*min),
+            _ => return Err(format!("line {}: invalid memory limits", line)),
+        }
+        Ok(())
+    }
+
+    fn check_global(&mut self, items: &[Expr], line: usize) -> Result<(), String> {
+        let (id, rest) = match items.first().and_then(Expr::atom) {
+            Some(id) if is_id(id) => (Some(id), &items[1..]),
+            _ => (None, items),
+        };
+        let (ty, mutable, init) = match rest {
+            [ty, init] => match ty.form("mut") {
+                Some([ty]) => (val_type(ty, line)?, true, init),
+                Some(_) => return Err(format!("line {}: expected `(mut <type>)`", line)),
+                None => (val_type(ty, line)?, false, init),
+            },
+            _ => return Err(format!("line {}: expected `(global $name <type> (<type>.const n))`", line)),
+        };
+        match init {
+            Expr::List(items, _) if items.len() == 2 && items[0].atom() == Some(&format!("{}.const", ty.name())) => {
+                let value = items[1].atom().ok_or_else(|| format!("line {}: expected a constant", line))?;
+                parse_int(value, line, ty)?;
+            }
+            _ => return Err(format!("line {}: a {} global must start as an `{}.const`", line, ty.name(), ty.name())),
+        }
+        if let Some(id) = id {
+            if self.global_names.insert(id.to_string(), self.globals.len()).is_some() {
+                return Err(format!("line {}: duplicate global {}", line, id));
+            }
+        }
+        self.globals.push(Global { ty, mutable });
+        Ok(())
+    }
+
+    fn check_data(&self, items: &[Expr], line: usize) -> Result<(), String> {
+        let pages = self.memory.ok_or_else(|| format!("line {}: data needs a memory", line))?;
+        let offset = match items.first().and_then(|e| e.form("i32.const")) {
+            Some([Expr::Atom(n, _)]) => parse_int(n, line, ValType::I32)? as u32 as u64,
+            _ => return Err(format!("line {}: data must start with `(i32.const offset)`", line)),
+        };
+        let mut len = 0;
+        for item in &items[1..] {
+            match item {
+                Expr::Str(bytes, _) => len += bytes.len() as u64,
+                other => return Err(format!("line {}: data can only contain strings", other.line())),
+            }
+        }
+        if offset + len > pages * 65536 {
+            return Err(format!("line {}: data ends at byte {}, past the end of memory ({} bytes)", line, offset + len, pages * 65536));
+        }
+        Ok(())
+    }
+
+    fn check_export(&mut self, items: &[Expr], line: usize) -> Result<(), String> {
+        let (name, desc) = match items {
+            [name, desc] => (name, desc),
+            _ => return Err(format!("line {}: expected `(export \"name\" (func $f))`", line)),
+        };
+        self.add_export(name, line)?;
+        match (desc.form("func"), desc.form("memory")) {
+            (Some([Expr::Atom(f, _)]), _) => self.function(f, line).map(|_| ()),
+            (_, Some([_])) if self.memory.is_some() => Ok(()),
+            _ => Err(format!("line {}: can only export a function or the memory", line)),
+        }
+    }
+
+    fn function(&self, reference: &str, line: usize) -> Result<&Signature, String> {
+        let index = if is_id(reference) {
+            self.function_names.get(reference).copied()
+        } else {
+            reference.parse::<usize>().ok()
+        };
+        index
+            .and_then(|i| self.functions.get(i))
+            .ok_or_else(|| format!("line {}: unknown function {}", line, reference))
+    }
+
+    fn global(&self, reference: &str, line: usize) -> Result<&Global, String> {
+        let index = if is_id(reference) {
+            self.global_names.get(reference).copied()
+        } else {
+            reference.parse::<usize>().ok()
+        };
+        index
+            .and_then(|i| self.globals.get(i))
+            .ok_or_else(|| format!("line {}: unknown global {}", line, reference))
+    }
+}
+
+fn val_type(expr: &Expr, line: usize) -> Result<ValType, String> {
+    expr.atom()
+        .and_then(ValType::from_name)
+        .ok_or_else(|| format!("line {}: unsupported type `{}`", line, describe(expr)))
+}
+
+fn describe(expr: &Expr) -> String {
+    match expr {
+        Expr::Atom(atom, _) => atom.clone(),
+        Expr::Str(..) => "string".to_string(),
+        Expr::List(items, _) => match items.first().and_then(Expr::atom) {
+            Some(keyword) => format!("({} ...)", keyword),
+            None => "(...)".to_string(),
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+}
+
+struct Frame {
+    kind: FrameKind,
+    label: Option<String>,
+    results: Vec<ValType>,
+    /// Stack height when the frame started.
+    height: usize,
+    /// After `br`, `return` or `unreachable`, until the frame ends.
+    unreachable: bool,
+}
+
+/// Type-checks a function body. `None` on the stack is a value of unknown
+/// type, which only turns up in unreachable code.
+struct Checker<'a> {
+    module: &'a Module,
+    locals: Vec<ValType>,
+    local_names: HashMap<String, usize>,
+    stack: Vec<Option<ValType>>,
+    frames: Vec<Frame>,
+}
+
+/// Operand and result types of the simple instructions.
+fn simple(op: &str) -> Option<(&'static [ValType], &'static [ValType])> {
+    use ValType::{I32, I64};
+    Some(match op {
+        "i32.add" | "i32.sub" | "i32.mul" | "i32.div_s" | "i32.div_u" | "i32.rem_s" | "i32.rem_u" | "i32.and"
+        | "i32.or" | "i32.xor" | "i32.shl" | "i32.shr_s" | "i32.shr_u" => (&[I32, I32], &[I32]),
+        "i32.eq" | "i32.ne" | "i32.lt_s" | "i32.lt_u" | "i32.gt_s" | "i32.gt_u" | "i32.le_s" | "i32.le_u"
+        | "i32.ge_s" | "i32.ge_u" => (&[I32, I32], &[I32]),
+        "i32.eqz" => (&[I32], &[I32]),
+        "i64.add" | "i64.sub" | "i64.mul" | "i64.div_s" | "i64.div_u" | "i64.rem_s" | "i64.rem_u" | "i64.and"
+        | "i64.or" | "i64.xor" | "i64.shl" | "i64.shr_s" | "i64.shr_u" => (&[I64, I64], &[I64]),
+        "i64.eq" | "i64.ne" | "i64.lt_s" | "i64.lt_u" | "i64.gt_s" | "i64.gt_u" | "i64.le_s" | "i64.le_u"
+        | "i64.ge_s" | "i64.ge_u" => (&[I64, I64], &[I32]),
+        "i64.eqz" | "i32.wrap_i64" => (&[I64], &[I32]),
+        "i64.extend_i32_u" | "i64.extend_i32_s" => (&[I32], &[I64]),
+        "i32.load" | "i32.load8_u" | "i32.load8_s" => (&[I32], &[I32]),
+        "i64.load" | "i64.load32_u" | "i64.load32_s" => (&[I32], &[I64]),
+        "i32.store" | "i32.store8" => (&[I32, I32], &[]),
+        "i64.store" | "i64.store32" => (&[I32, I64], &[]),
+        "memory.size" => (&[], &[I32]),
+        "memory.grow" => (&[I32], &[I32]),
+        _ => return None,
+    })
+}
+
+impl Checker<'_> {
+    fn frame(&self) -> &Frame {
+        &self.frames[self.frames.len() - 1]
+    }
+
+    fn push(&mut self, ty: ValType) {
+        self.stack.push(Some(ty));
+    }
+
+    fn pop(&mut self, expected: Option<ValType>, op: &str, line: usize) -> Result<Option<ValType>, String> {
+        let frame = self.frame();
+        if self.stack.len() == frame.height {
+            if frame.unreachable {
+                return Ok(expected);
+            }
+            return Err(format!("line {}: `{}` needs more values than are on the stack", line, op));
+        }
+        let actual = self.stack.pop().flatten();
+        match (expected, actual) {
+            (Some(expected), Some(actual)) if expected != actual => {
+                Err(format!("line {}: `{}` expected {} but found {}", line, op, expected.name(), actual.name()))
+            }
+            _ => Ok(actual.or(expected)),
+        }
+    }
+
+    fn pop_all(&mut self, types: &[ValType], op: &str, line: usize) -> Result<(), String> {
+        for ty in types.iter().rev() {
+            self.pop(Some(*ty), op, line)?;
+        }
+        Ok(())
+    }
+
+    fn set_unreachable(&mut self) {
+        let height = self.frame().height;
+        self.stack.truncate(height);
+        let last = self.frames.len() - 1;
+        self.frames[last].unreachable = true;
+    }
+
+    /// Checks the stack holds exactly the current frame's results.
+    fn check_results(&mut self, op: &str, line: usize) -> Result<(), String> {
+        let results = self.frame().results.clone();
+        self.pop_all(&results, op, line)?;
+        if self.stack.len() != self.frame().height {
+            return Err(format!("line {}: `{}` leaves {} extra value(s) on the stack", line, op, self.stack.len() - self.frame().height));
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, line: usize) -> Result<(), String> {
+        self.check_results("end", line)?;
+        let frame = self.frames.pop().ok_or_else(|| format!("line {}: `end` without a block", line))?;
+        if frame.kind == FrameKind::If && !frame.results.is_empty() {
+            return Err(format!("line {}: an `if` with results needs an `else`", line));
+        }
+        for ty in frame.results {
+            self.push(ty);
+        }
+        Ok(())
+    }
+
+    /// The types a branch to `label` carries.
+    fn label(&self, label: &str, line: usize) -> Result<Vec<ValType>, String> {
+        let depth = if is_id(label) {
+            self.frames.iter().rev().position(|f| f.label.as_deref() == Some(label))
+        } else {
+            label.parse::<usize>().ok().filter(|d| *d < self.frames.len())
+        };
+        let depth = depth.ok_or_else(|| format!("line {}: unknown label {}", line, label))?;
+        let frame = &self.frames[self.frames.len() - 1 - depth];
+        Ok(if frame.kind == FrameKind::Loop { Vec::new() } else { frame.results.clone() })
+    }
+
+    fn local(&self, reference: &str, line: usize) -> Result<ValType, String> {
+        let index = if is_id(reference) {
+            self.local_names.get(reference).copied()
+        } else {
+            reference.parse::<usize>().ok()
+        };
+        index
+            .and_then(|i| self.locals.get(i).copied())
+            .ok_or_else(|| format!("line {}: unknown local {}", line, reference))
+    }
+
+    fn instruction<'e>(&mut self, op: &str, line: usize, rest: &mut std::iter::Peekable<std::slice::Iter<'e, Expr>>) -> Result<(), String> {
+        let mut immediate = |what: &str| -> Result<String, String> {
+            match rest.next() {
+                Some(Expr::Atom(atom, _)) => Ok(atom.clone()),
+                _ => Err(format!("line {}: `{}` needs {}", line, op, what)),
+            }
+        };
+        match op {
+            "i32.const" => {
+                parse_int(&immediate("a value")?, line, ValType::I32)?;
+                self.push(ValType::I32);
+            }
+            "i64.const" => {
+                parse_int(&immediate("a value")?, line, ValType::I64)?;
+                self.push(ValType::I64);
+            }
+            "local.get" => {
+                let ty = self.local(&immediate("a local")?, line)?;
+                self.push(ty);
+            }
+            "local.set" | "local.tee" => {
+                let ty = self.local(&immediate("a local")?, line)?;
+                self.pop(Some(ty), op, line)?;
+                if op == "local.tee" {
+                    self.push(ty);
+                }
+            }
+            "global.get" => {
+                let ty = self.module.global(&immediate("a global")?, line)?.ty;
+                self.push(ty);
+            }
+            "global.set" => {
+                let name = immediate("a global")?;
+                let global = self.module.global(&name, line)?;
+                if !global.mutable {
+                    return Err(format!("line {}: global {} is immutable", line, name));
+                }
+                let ty = global.ty;
+                self.pop(Some(ty), op, line)?;
+            }
+            "call" => {
+                let signature = self.module.function(&immediate("a function")?, line)?.clone();
+                self.pop_all(&signature.params, op, line)?;
+                for ty in signature.results {
+                    self.push(ty);
+                }
+            }
+            "block" | "loop" | "if" => {
+                let label = match rest.peek() {
+                    Some(Expr::Atom(atom, _)) if is_id(atom) => {
+                        rest.next();
+                        Some(atom.clone())
+                    }
+                    _ => None,
+                };
+                let mut results = Vec::new();
+                if let Some(types) = rest.peek().and_then(|e| e.form("result")) {
+                    for ty in types {
+                        results.push(val_type(ty, line)?);
+                    }
+                    rest.next();
+                }
+                if op == "if" {
+                    self.pop(Some(ValType::I32), op, line)?;
+                }
+                let kind = match op {
+                    "block" => FrameKind::Block,
+                    "loop" => FrameKind::Loop,
+                    _ => FrameKind::If,
+                };
+                self.frames.push(Frame { kind, label, results, height: self.stack.len(), unreachable: false });
+            }
+            "else" => {
+                if self.frame().kind != FrameKind::If {
+                    return Err(format!("line {}: `else` outside an `if`", line));
+                }
+                self.check_results(op, line)?;
+                let last = self.frames.len() - 1;
+                self.frames[last].kind = FrameKind::Else;
+                self.frames[last].unreachable = false;
+            }
+            "end" => {
+                if self.frames.len() == 1 {
+                    return Err(format!("line {}: `end` without a block", line));
+                }
+                self.end(line)?;
+            }
+            "br" => {
+                let types = self.label(&immediate("a label")?, line)?;
+                self.pop_all(&types, op, line)?;
+                self.set_unreachable();
+            }
+            "br_if" => {
+                let types = self.label(&immediate("a label")?, line)?;
+                self.pop(Some(ValType::I32), op, line)?;
+                self.pop_all(&types, op, line)?;
+                for ty in types {
+                    self.push(ty);
+                }
+            }
+            "return" => {
+                let results = self.frames[0].results.clone();
+                self.pop_all(&results, op, line)?;
+                self.set_unreachable();
+            }
+            "unreachable" => self.set_unreachable(),
+            "drop" => {
+                self.pop(None, op, line)?;
+            }
+            _ => {
+                let (params, results) = simple(op).ok_or_else(|| format!("line {}: unknown instruction `{}`", line, op))?;
+                if op.contains(".load") || op.contains(".store") || op.starts_with("memory.") {
+                    while let Some(Expr::Atom(atom, _)) = rest.peek() {
+                        let Some((key, value)) = atom.split_once('=') else { break };
+                        if !matches!(key, "offset" | "align") || value.parse::<u32>().is_err() {
+                            return Err(format!("line {}: bad memory argument `{}`", line, atom));
+                        }
+                        rest.next();
+                    }
+                    if self.module.memory.is_none() {
+                        return Err(format!("line {}: `{}` needs a memory", line, op));
+                    }
+                }
+                self.pop_all(params, op, line)?;
+                for ty in results {
+                    self.push(*ty);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use crate::ast::Operator;
+use crate::builtins;
+use crate::bytecode::{Chunk, Op};
+use crate::console::{Input, Output, Stdin, Stdout};
+use crate::interpreter::{binary_op, is_truthy, RuntimeError, Value};
+use crate::sandbox::{Interrupt, Limits, Meter, Permissions};
+
+/// Runs compiled bytecode. Behaves exactly like `Interpreter` on the same
+/// program, minus the hooks used by the debuggers, tracer and profiler.
+pub struct Vm {
+    /// Variables by slot; `None` until declared.
+    slots: Vec<Option<Value>>,
+    stack: Vec<Value>,
+    /// Set by the `exit_code` builtin; the CLI exits with it after the program finishes.
+    pub exit_code: Option<i32>,
+    output: Box<dyn Output>,
+    input: Box<dyn Input>,
+    permissions: Permissions,
+    /// The next instruction to run.
+    pc: usize,
+    meter: Meter,
+    interrupt: Interrupt,
+}
+
+impl Vm {
+    pub fn new(chunk: &Chunk) -> Self {
+        let mut vm = Vm {
+            slots: vec![None; chunk.slots.len()],
+            stack: Vec::new(),
+            exit_code: None,
+            output: Box::new(Stdout),
+            input: Box::new(Stdin),
+            permissions: Permissions::new(),
+            pc: 0,
+            meter: Meter::default(),
+            interrupt: Interrupt::new(),
+        };
+        vm.set_args(chunk, Vec::new());
+        vm
+    }
+
+    /// Exposes command-line arguments to the program as the `args` list.
+    pub fn set_args(&mut self, chunk: &Chunk, args: Vec<String>) {
+        if let Some(slot) = chunk.slot("args") {
+            self.slots[slot] = Some(Value::List(args.into_iter().map(Value::String).collect()));
+        }
+    }
+
+    /// Sends `print` somewhere other than stdout.
+    pub fn set_output(&mut self, output: Box<dyn Output>) {
+        self.output = output;
+    }
+
+    /// Reads from somewhere other than stdin.
+    pub fn set_input(&mut self, input: Box<dyn Input>) {
+        self.input = input;
+    }
+
+    /// What the filesystem builtins may reach. Nothing, until this is called.
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.permissions = permissions;
+    }
+
+    /// Caps how much work each run may do. Each instruction is a step, and
+    /// every value pushed counts towards the memory limit. There is no
+    /// nesting in bytecode, so `max_depth` doesn't apply.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    /// A handle that stops the running program from another thread.
+    pub fn interrupt_handle(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    /// Listens to `interrupt` instead of the VM's own handle.
+    pub fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt = interrupt;
+    }
+
+    /// The source line of the instruction that ran last, e.g. where an
+    /// error or an interrupt stopped the program. 0 if it isn't known.
+    pub fn line(&self, chunk: &Chunk) -> usize {
+        self.pc.checked_sub(1).and_then(|pc| chunk.lines.get(pc)).copied().unwrap_or(0)
+    }
+
+    pub fn run(&mut self, chunk: &Chunk) -> Result<(), RuntimeError> {
+        self.meter.begin();
+        let result = self.execute(chunk);
+        self.meter.end();
+        result
+    }
+
+    fn execute(&mut self, chunk: &Chunk) -> Result<(), RuntimeError> {
+        self.pc = 0;
+        while let Some(op) = chunk.code.get(self.pc) {
+            self.pc += 1;
+            self.interrupt.check()?;
+            self.meter.step()?;
+            match *op {
+                Op::Constant(i) => self.push(chunk.constants[i as usize].clone())?,
+                Op::Load(slot) => {
+                    let value = self.slots[slot as usize].clone().unwrap_or(Value::Null);
+                    self.push(value)?;
+                }
+                Op::Declare(slot) => self.slots[slot as usize] = Some(self.pop()),
+                Op::Assign(slot) => {
+                    let value = self.pop();
+                    match &mut self.slots[slot as usize] {
+                        Some(var) => *var = value,
+                        None => {
+                            return Err(RuntimeError::new(format!("Undefined variable: {}", chunk.slots[slot as usize])));
+                        }
+                    }
+                }
+                Op::Add => self.binary(&Operator::Plus)?,
+                Op::Subtract => self.binary(&Operator::Minus)?,
+                Op::Greater => self.binary(&Operator::GreaterThan)?,
+                Op::Less => self.binary(&Operator::LessThan)?,
+                Op::Equal => self.binary(&Operator::Equals)?,
+                Op::Print => {
+                    let value = self.pop();
+                    self.output.write(&format!("{}\n", value));
+                }
+                Op::Call { name, argc } => {
+                    let args = self.stack.split_off(self.stack.len() - argc as usize);
+                    let name = match &chunk.constants[name as usize] {
+                        Value::String(name) => name,
+                        other => return Err(RuntimeError::new(format!("Invalid function name: {}", other))),
+                    };
+                    let result = builtins::call(
+                        &mut self.exit_code,
+                        self.input.as_mut(),
+                        self.output.as_mut(),
+                        &self.permissions,
+                        name,
+                        args,
+                    )?;
+                    self.push(result)?;
+                }
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Jump(to) => self.pc = to as usize,
+                Op::JumpIfFalse(to) => {
+                    if !is_truthy(&self.pop()) {
+                        self.pc = to as usize;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes a value the program produced, charging for its memory.
+    fn push(&mut self, value: Value) -> Result<(), RuntimeError> {
+        self.meter.allocate(&value)?;
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the compiler keeps the stack balanced")
+    }
+
+    fn binary(&mut self, operator: &Operator) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop();
+        self.push(binary_op(left, operator, right))
+    }
+}